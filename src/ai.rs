@@ -0,0 +1,333 @@
+//! Computer player. It looks only at the current position: where its units can get
+//! this turn, which enemies they could beat from there and which enemies could beat them back.
+//! The only objective in the game is destroying the enemy, so that's what it goes after.
+
+use crate::game::{GameCommand, Phase, Scenario};
+use crate::history::Command;
+use crate::maps::hexagons::HexCoordinates;
+use crate::replay::Replay;
+use crate::systems::combat::{self, CombatSystem};
+use crate::systems::influence::InfluenceMap;
+use crate::units::unit::{Side, Unit};
+
+use std::str::FromStr;
+
+/// How well the computer plays.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Difficulty {
+    /// Hexes of the movement range each unit looks at, the cheapest to reach first.
+    pub search_width: usize,
+    /// Worth of closing in on the enemy against staying out of its reach, from 0 to 100.
+    pub aggression: i32,
+    /// Whether terrain and rivers count in the combat odds, or only the strength of units.
+    pub reads_terrain: bool,
+}
+
+impl Difficulty {
+    pub fn easy() -> Self {
+        Difficulty {
+            search_width: 4,
+            aggression: 80,
+            reads_terrain: false,
+        }
+    }
+
+    pub fn normal() -> Self {
+        Difficulty {
+            search_width: 12,
+            aggression: 50,
+            reads_terrain: true,
+        }
+    }
+
+    pub fn hard() -> Self {
+        Difficulty {
+            search_width: usize::MAX,
+            aggression: 40,
+            reads_terrain: true,
+        }
+    }
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Self::normal()
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "easy" => Ok(Self::easy()),
+            "normal" => Ok(Self::normal()),
+            "hard" => Ok(Self::hard()),
+            _ => Err("Unknown difficulty, choose easy, normal or hard."),
+        }
+    }
+}
+
+struct Enemy<'a> {
+    id: i32,
+    unit: &'a Unit,
+    hex: HexCoordinates,
+}
+
+/// Computer playing one side, it picks one command at a time
+/// so its moves go through the same history and replays as the player's.
+#[derive(Debug, Copy, Clone)]
+pub struct Ai {
+    side: Side,
+    difficulty: Difficulty,
+}
+
+impl Ai {
+    pub fn new(side: Side, difficulty: Difficulty) -> Self {
+        Ai { side, difficulty }
+    }
+
+    pub fn side(&self) -> Side {
+        self.side
+    }
+
+    /// Picks the next command, None if it's not the turn of the side or the game is over.
+    pub fn next_command(&self, scenario: &Scenario) -> Option<GameCommand> {
+        if scenario.turn.side() != self.side || scenario.winner().is_some() {
+            return None;
+        }
+        Some(match scenario.turn.phase() {
+            Phase::Movement => self
+                .best_move(scenario)
+                .map_or(GameCommand::NextPhase, |(id, hex)| {
+                    GameCommand::Move(id, hex)
+                }),
+            Phase::Combat => self
+                .best_attack(scenario)
+                .map_or(GameCommand::EndTurn, |(attacker, defender)| {
+                    GameCommand::Attack(attacker, defender)
+                }),
+        })
+    }
+
+    /// Plays until the turn passes to the other side or the game ends.
+    pub fn play_turn(&self, scenario: &mut Scenario) -> Result<Vec<GameCommand>, &'static str> {
+        let mut commands = Vec::new();
+        while let Some(command) = self.next_command(scenario) {
+            command.apply(scenario)?;
+            commands.push(command);
+        }
+        Ok(commands)
+    }
+
+    //units of the side on the map, sorted by id
+    fn units<'a>(
+        &self,
+        scenario: &'a Scenario,
+        side: Side,
+    ) -> Vec<(i32, &'a Unit, HexCoordinates)> {
+        let mut units: Vec<_> = scenario
+            .units
+            .units()
+            .iter()
+            .filter(|(_, unit)| unit.side() == side)
+            .filter_map(|(id, unit)| {
+                let mc = scenario.systems.moving.components.get(id)?;
+                Some((*id, unit, mc.occupation?))
+            })
+            .collect();
+        units.sort_by_key(|(id, _, _)| *id);
+        units
+    }
+
+    fn enemies<'a>(&self, scenario: &'a Scenario) -> Vec<Enemy<'a>> {
+        let enemy_side = match self.side {
+            Side::Blue => Side::Red,
+            Side::Red => Side::Blue,
+        };
+        self.units(scenario, enemy_side)
+            .into_iter()
+            .map(|(id, unit, hex)| Enemy { id, unit, hex })
+            .collect()
+    }
+
+    //defence as the computer sees it, the easy one doesn't count terrain and rivers
+    fn defence(
+        &self,
+        unit: &Unit,
+        hex: HexCoordinates,
+        from: HexCoordinates,
+        scenario: &Scenario,
+    ) -> i32 {
+        if self.difficulty.reads_terrain {
+            CombatSystem::defence(unit, hex, from, &scenario.map)
+        } else {
+            combat::defence_strength(unit.kind())
+        }
+    }
+
+    fn score(
+        &self,
+        unit: &Unit,
+        hex: HexCoordinates,
+        scenario: &Scenario,
+        enemies: &[Enemy],
+        influence: &InfluenceMap,
+    ) -> i32 {
+        let mut score = 0;
+        let best_odds = enemies
+            .iter()
+            .filter(|enemy| enemy.hex.distance_to(&hex) == 1)
+            .map(|enemy| {
+                combat::attack_strength(unit.kind())
+                    - self.defence(enemy.unit, enemy.hex, hex, scenario)
+            })
+            .max();
+        if let Some(odds) = best_odds.filter(|odds| *odds > 0) {
+            score += 100 + 10 * odds;
+        }
+
+        //every enemy able to reach the hex counts, more careful than any single attack needs
+        if influence.threat(&hex) > self.defence(unit, hex, hex, scenario) {
+            score -= 100 - self.difficulty.aggression;
+        }
+        score += influence.support(&hex);
+        let distance = influence.distance_to_objective(&hex).unwrap_or(0);
+        score -= distance * self.difficulty.aggression / 10;
+        if self.difficulty.reads_terrain {
+            if let Some(site) = scenario.map.hex_at(hex) {
+                score += 5 * combat::terrain_defence_bonus(site.kind());
+            }
+        }
+        score
+    }
+
+    //move of a unit that didn't move yet, which improves its position the most
+    fn best_move(&self, scenario: &Scenario) -> Option<(i32, HexCoordinates)> {
+        let enemies = self.enemies(scenario);
+        let influence = InfluenceMap::new(
+            self.side,
+            &scenario.units,
+            &scenario.systems.moving,
+            &scenario.map,
+            None,
+        );
+        let mut best = None;
+        let mut best_gain = 0;
+        for (id, unit, from) in self.units(scenario, self.side) {
+            let mc = &scenario.systems.moving.components[&id];
+            if mc.moving_pts() < mc.default_moving_pts() || !scenario.can_move(id) {
+                continue;
+            }
+            let range = match scenario.movement_range(id) {
+                Ok(range) => range,
+                Err(_) => continue,
+            };
+            let mut hexes: Vec<_> = range
+                .costs
+                .iter()
+                .filter(|(hex, _)| **hex != from)
+                .map(|(hex, cost)| (*cost, hex.q(), hex.p(), *hex))
+                .collect();
+            hexes.sort_by_key(|(cost, q, p, _)| (*cost, *q, *p));
+
+            let stay = self.score(unit, from, scenario, &enemies, &influence);
+            for (_, _, _, hex) in hexes.into_iter().take(self.difficulty.search_width) {
+                let gain = self.score(unit, hex, scenario, &enemies, &influence) - stay;
+                if gain > best_gain {
+                    best_gain = gain;
+                    best = Some((id, hex));
+                }
+            }
+        }
+        best
+    }
+
+    //attack with the best odds, the weakest enemies go first
+    fn best_attack(&self, scenario: &Scenario) -> Option<(i32, i32)> {
+        let enemies = self.enemies(scenario);
+        let mut best = None;
+        let mut best_odds = 0;
+        for (id, unit, from) in self.units(scenario, self.side) {
+            if !scenario.can_attack(id) {
+                continue;
+            }
+            for enemy in enemies.iter().filter(|e| e.hex.distance_to(&from) == 1) {
+                let odds = combat::attack_strength(unit.kind())
+                    - self.defence(enemy.unit, enemy.hex, from, scenario);
+                if odds > best_odds {
+                    best_odds = odds;
+                    best = Some((id, enemy.id));
+                }
+            }
+        }
+        best
+    }
+}
+
+/// Game of the computer against itself, until one side wins or the turns run out.
+/// It needs no window nor terminal, returning the commands played.
+pub fn play_game(
+    scenario: &mut Scenario,
+    blue: Ai,
+    red: Ai,
+    max_turns: u32,
+) -> Result<Replay, &'static str> {
+    let mut replay = Replay::new(scenario);
+    while scenario.winner().is_none() && scenario.turn.number() <= max_turns {
+        let ai = match scenario.turn.side() {
+            Side::Blue => blue,
+            Side::Red => red,
+        };
+        for command in ai.play_turn(scenario)? {
+            replay.record(command);
+        }
+    }
+    Ok(replay)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::Playback;
+    use crate::rng;
+
+    #[test]
+    fn attacking_weak_enemies() {
+        let mut scenario = Scenario::test();
+        //the first die rolls a six
+        scenario.dice = rng::Rng::new(6);
+        let ai = Ai::new(Side::Blue, Difficulty::normal());
+        assert!(Ai::new(Side::Red, Difficulty::normal())
+            .next_command(&scenario)
+            .is_none());
+
+        //the mechanized unit gets next to the armoured one, which defends poorly
+        let commands = ai.play_turn(&mut scenario).unwrap();
+        assert_eq!(commands.last(), Some(&GameCommand::Attack(0, 2)));
+        assert!(commands.contains(&GameCommand::NextPhase));
+        assert_eq!(scenario.winner(), Some(Side::Blue));
+        assert!(ai.next_command(&scenario).is_none());
+    }
+
+    #[test]
+    fn computer_against_computer() {
+        let play = |blue, red| {
+            let mut scenario = Scenario::generated(7);
+            let blue = Ai::new(Side::Blue, blue);
+            let red = Ai::new(Side::Red, red);
+            let replay = play_game(&mut scenario, blue, red, 10).unwrap();
+            assert!(scenario.winner().is_some() || scenario.turn.number() > 10);
+            (scenario, replay)
+        };
+        let (scenario, replay) = play(Difficulty::easy(), Difficulty::hard());
+        assert_eq!(play(Difficulty::easy(), Difficulty::hard()).1, replay);
+        let mut playback = Playback::new(replay);
+        playback.fast_forward().unwrap();
+        assert_eq!(playback.scenario().turn, scenario.turn);
+        play(Difficulty::hard(), Difficulty::normal());
+
+        assert_eq!("hard".parse(), Ok(Difficulty::hard()));
+        assert!("impossible".parse::<Difficulty>().is_err());
+    }
+}
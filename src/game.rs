@@ -1,33 +1,452 @@
 extern crate log;
-extern crate sfml;
 
+#[cfg(feature = "sfml")]
 use sfml::graphics::*;
-use sfml::system::{Vector2f, Vector2i};
+#[cfg(feature = "sfml")]
+use sfml::system::{Clock, Vector2f};
+#[cfg(feature = "sfml")]
 use sfml::window::*;
 
+#[cfg(feature = "sfml")]
 use super::graphics;
+use super::history::Command;
+#[cfg(feature = "sfml")]
+use super::input;
 use super::maps::*;
+use super::rng;
+#[cfg(feature = "sfml")]
+use super::states;
 use super::systems;
+use super::systems::combat::CombatResult;
+use super::systems::components::{Component, MovingComponent};
+use super::systems::moving::MovementRange;
+use super::systems::vision;
 use super::units;
+use super::units::unit::Side;
 
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Phase {
+    Movement,
+    Combat,
+}
+
+/// Turn number, side to move and phase of its turn.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Turn {
+    number: u32,
+    side: Side,
+    phase: Phase,
+}
+
+impl Default for Turn {
+    fn default() -> Self {
+        Turn {
+            number: 1,
+            side: Side::Blue,
+            phase: Phase::Movement,
+        }
+    }
+}
+
+impl Turn {
+    pub fn new(number: u32, side: Side, phase: Phase) -> Self {
+        Turn {
+            number,
+            side,
+            phase,
+        }
+    }
+
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+
+    pub fn side(&self) -> Side {
+        self.side
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    pub fn next_phase(&mut self) {
+        match self.phase {
+            Phase::Movement => self.phase = Phase::Combat,
+            Phase::Combat => self.end_side_turn(),
+        }
+    }
+
+    /// Skips the remaining phases, passing the turn to the other side.
+    /// A new turn starts after the red side is done.
+    pub fn end_side_turn(&mut self) {
+        self.phase = Phase::Movement;
+        self.side = match self.side {
+            Side::Blue => Side::Red,
+            Side::Red => {
+                self.number += 1;
+                Side::Blue
+            }
+        };
+    }
+}
+
+/// Map with the units placed on it, everything that's needed to play.
+pub struct Scenario {
+    pub map: map::Map,
+    pub systems: systems::GameSystems,
+    pub units: units::unit_set::UnitSet,
+    pub turn: Turn,
+    /// Seed of the generated map, None for the test scenario.
+    pub seed: Option<u64>,
+    /// Rolls the dice of combat, seeded like the map so replaying the commands
+    /// of a game gives the same results.
+    pub dice: rng::Rng,
+}
+
+impl Scenario {
+    pub fn test() -> Self {
+        Self::with_map(map::Map::create_test_map(), None)
+    }
+
+    pub fn generated(seed: u64) -> Self {
+        info!("Generating map with seed {}.", seed);
+        let coords = shapes::rectangle_pointy_coords(-10..=10, -10..=10);
+        Self::with_map(
+            terrain::TerrainGenerator::new(seed).generate(coords),
+            Some(seed),
+        )
+    }
+
+    fn with_map(mut map: map::Map, seed: Option<u64>) -> Self {
+        //nothing has seen the map yet, so there's no one to replay its creation to
+        map.forget_changes();
+        let mut systems = systems::GameSystems::new();
+        let units = units::unit_set::UnitSet::create_test_unit_set(&mut systems);
+        Scenario {
+            map,
+            systems,
+            units,
+            turn: Turn::default(),
+            seed,
+            dice: rng::Rng::new(seed.unwrap_or(0)),
+        }
+    }
+
+    /// Passes the turn to the other side, whose units get their moving points back.
+    pub fn end_side_turn(&mut self) {
+        self.turn.end_side_turn();
+        self.systems.combat.reset();
+        let side = self.turn.side();
+        for (id, unit) in self.units.units().iter() {
+            if unit.side() == side {
+                if let Some(mc) = self.systems.moving.components.get_mut(id) {
+                    mc.reest_mv_pts();
+                }
+            }
+        }
+        info!(
+            "Turn {}, {:?} side to move.",
+            self.turn.number(),
+            self.turn.side()
+        );
+    }
+
+    pub fn next_phase(&mut self) {
+        if self.turn.phase() == Phase::Combat {
+            self.end_side_turn();
+        } else {
+            self.turn.next_phase();
+        }
+    }
+
+    /// Whether the unit can be moved by the side currently playing.
+    pub fn can_move(&self, unit_id: i32) -> bool {
+        self.turn.phase() == Phase::Movement
+            && self
+                .units
+                .units()
+                .get(&unit_id)
+                .is_some_and(|u| u.side() == self.turn.side())
+    }
+
+    /// Whether the unit can attack in the combat phase of its side.
+    pub fn can_attack(&self, unit_id: i32) -> bool {
+        self.turn.phase() == Phase::Combat
+            && !self.systems.combat.has_attacked(unit_id)
+            && self
+                .units
+                .units()
+                .get(&unit_id)
+                .is_some_and(|u| u.side() == self.turn.side())
+    }
+
+    /// Hexes held by units of the other side, units of the side can't move through them.
+    pub fn enemy_hexes(&self, side: Side) -> HashSet<hexagons::HexCoordinates> {
+        let units = self.units.units();
+        self.systems
+            .moving
+            .components
+            .values()
+            .filter(|mc| units.get(&mc.owner_id()).map(|u| u.side()) != Some(side))
+            .filter_map(|mc| mc.occupation)
+            .collect()
+    }
+
+    /// Hexes held by the enemy in sight of the side's units. Units go around these,
+    /// the hidden ones stop the units running into them.
+    pub fn seen_enemy_hexes(&self, side: Side) -> HashSet<hexagons::HexCoordinates> {
+        let visible = vision::visible_hexes(side, &self.units, &self.systems.moving, &self.map);
+        self.enemy_hexes(side)
+            .into_iter()
+            .filter(|hex| visible.contains(hex))
+            .collect()
+    }
+
+    /// Hexes the unit can reach with its moving points, going around the enemy in sight.
+    pub fn movement_range(&self, unit_id: i32) -> Result<MovementRange, &'static str> {
+        let side = self
+            .units
+            .units()
+            .get(&unit_id)
+            .ok_or("No such unit.")?
+            .side();
+        self.systems
+            .moving
+            .movement_range(unit_id, &self.map, &self.seen_enemy_hexes(side))
+    }
+
+    /// Moves the unit, if the rules allow it, frontends should move units only this way.
+    /// A unit running into a hidden enemy stops on the hex before it.
+    pub fn move_unit(
+        &mut self,
+        unit_id: i32,
+        target: hexagons::HexCoordinates,
+    ) -> Result<(), &'static str> {
+        if !self.can_move(unit_id) {
+            return Err("Unit cannot move now.");
+        }
+        let side = self.turn.side();
+        let enemies = self.enemy_hexes(side);
+        let blocked = self.seen_enemy_hexes(side);
+        //the hex next to the unit is always in sight, so it never stops where it stands
+        let stop = self
+            .systems
+            .moving
+            .movement_range(unit_id, &self.map, &blocked)?
+            .path_to(target)
+            .and_then(|path| {
+                path.windows(2)
+                    .find(|step| enemies.contains(&step[1]))
+                    .map(|step| step[0])
+            });
+        if let Some(hex) = stop {
+            info!(
+                "Unit {} ran into the enemy, stopping on {:?}.",
+                unit_id, hex
+            );
+        }
+        let target = stop.unwrap_or(target);
+        self.systems
+            .moving
+            .move_unit(unit_id, target, &self.map, &blocked)
+            .map(|_| info!("Moved unit {} to {:?}.", unit_id, target))
+    }
+
+    /// Deploys the unit on the hex, during the first movement phase of its side.
+    pub fn place_unit(
+        &mut self,
+        unit_id: i32,
+        hex: hexagons::HexCoordinates,
+    ) -> Result<(), &'static str> {
+        if self.turn.number() != 1 || !self.can_move(unit_id) {
+            return Err("Unit cannot be placed now.");
+        }
+        if self.map.hex_at(hex).is_none() {
+            return Err("Map doesn't contain such hex.");
+        }
+        if self.enemy_hexes(self.turn.side()).contains(&hex) {
+            return Err("Hex is occupied by the enemy.");
+        }
+        let mc = self
+            .systems
+            .moving
+            .components
+            .get_mut(&unit_id)
+            .ok_or("Unit not registered in moving system.")?;
+        if mc.occupation.is_some() {
+            return Err("Unit is already on the map.");
+        }
+        mc.occupation = Some(hex);
+        info!("Placed unit {} on {:?}.", unit_id, hex);
+        Ok(())
+    }
+
+    /// Attacks the defender, if the rules allow it.
+    pub fn attack(&mut self, attacker: i32, defender: i32) -> Result<CombatResult, &'static str> {
+        if !self.can_attack(attacker) {
+            return Err("Unit cannot attack now.");
+        }
+        let units = self.units.units();
+        let defender = units.get(&defender).ok_or("No such unit.")?;
+        let res = self.systems.combat.attack(
+            &units[&attacker],
+            defender,
+            &mut self.systems.moving,
+            &self.map,
+            &mut self.dice,
+        )?;
+        info!(
+            "Unit {} attacked unit {}: {:?}.",
+            attacker,
+            defender.id(),
+            res
+        );
+        Ok(res)
+    }
+
+    /// Returns the side, whose units are the only ones left on the map.
+    pub fn winner(&self) -> Option<Side> {
+        let sides: HashSet<_> = self
+            .units
+            .units()
+            .iter()
+            .filter(|(id, _)| {
+                self.systems
+                    .moving
+                    .components
+                    .get(id)
+                    .is_some_and(|mc| mc.occupation.is_some())
+            })
+            .map(|(_, unit)| unit.side())
+            .collect();
+
+        if sides.len() == 1 {
+            sides.into_iter().next()
+        } else {
+            None
+        }
+    }
+}
+
+/// Action of a player, frontends change the scenario only with these
+/// so they can be undone, if the rules allow it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameCommand {
+    Move(i32, hexagons::HexCoordinates),
+    Place(i32, hexagons::HexCoordinates),
+    Attack(i32, i32),
+    NextPhase,
+    EndTurn,
+}
+
+impl GameCommand {
+    //unit whose moving component is restored by undo
+    fn unit_id(&self) -> Option<i32> {
+        match *self {
+            GameCommand::Move(unit_id, _) | GameCommand::Place(unit_id, _) => Some(unit_id),
+            _ => None,
+        }
+    }
+}
+
+//hexes are written as "odd-r" offset coordinates, like in the terminal frontend
+const COORDS_KIND: hexagons::OffsetKind = hexagons::OffsetKind::OddR;
+
+/// Written with the words of the terminal frontend, e.g. "move 2 0304" or "end".
+impl fmt::Display for GameCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label = |hex: hexagons::HexCoordinates| hex.to_offset(COORDS_KIND);
+        match *self {
+            GameCommand::Move(unit_id, hex) => write!(f, "move {} {}", unit_id, label(hex)),
+            GameCommand::Place(unit_id, hex) => write!(f, "place {} {}", unit_id, label(hex)),
+            GameCommand::Attack(attacker, defender) => {
+                write!(f, "attack {} {}", attacker, defender)
+            }
+            GameCommand::NextPhase => write!(f, "next"),
+            GameCommand::EndTurn => write!(f, "end"),
+        }
+    }
+}
+
+impl FromStr for GameCommand {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const ERR: &str = "Invalid command.";
+        let number = |word: &str| word.parse::<i32>().map_err(|_| ERR);
+        let hex = |word: &str| {
+            word.parse()
+                .map(|offset| hexagons::HexCoordinates::from_offset(offset, COORDS_KIND))
+        };
+        let words: Vec<_> = s.split_whitespace().collect();
+        match words[..] {
+            ["move", unit, target] => Ok(GameCommand::Move(number(unit)?, hex(target)?)),
+            ["place", unit, target] => Ok(GameCommand::Place(number(unit)?, hex(target)?)),
+            ["attack", attacker, defender] => {
+                Ok(GameCommand::Attack(number(attacker)?, number(defender)?))
+            }
+            ["next"] => Ok(GameCommand::NextPhase),
+            ["end"] => Ok(GameCommand::EndTurn),
+            _ => Err(ERR),
+        }
+    }
+}
+
+impl Command for GameCommand {
+    type Target = Scenario;
+    type Undo = Option<MovingComponent>;
+
+    fn apply(&self, scenario: &mut Scenario) -> Result<Self::Undo, &'static str> {
+        let before = self
+            .unit_id()
+            .and_then(|id| scenario.systems.moving.components.get(&id))
+            .cloned();
+        let side = scenario.turn.side();
+        let seen = scenario.seen_enemy_hexes(side);
+        match *self {
+            GameCommand::Move(unit_id, hex) => scenario.move_unit(unit_id, hex)?,
+            GameCommand::Place(unit_id, hex) => scenario.place_unit(unit_id, hex)?,
+            GameCommand::Attack(attacker, defender) => {
+                scenario.attack(attacker, defender)?;
+            }
+            GameCommand::NextPhase => scenario.next_phase(),
+            GameCommand::EndTurn => scenario.end_side_turn(),
+        }
+        //spotting the enemy can't be taken back, such moves leave nothing to undo
+        if !scenario.seen_enemy_hexes(side).is_subset(&seen) {
+            return Ok(None);
+        }
+        Ok(before)
+    }
+
+    fn undo(&self, scenario: &mut Scenario, before: Self::Undo) {
+        if let Some(mc) = before {
+            debug!("Undoing {:?}.", self);
+            scenario.systems.moving.components.insert(mc.owner_id(), mc);
+        }
+    }
+
+    /// Combat results are known once the attack is made, moves may spot the enemy,
+    /// and the other phases don't let the previous ones be changed.
+    fn is_checkpoint(&self, before: &Self::Undo) -> bool {
+        before.is_none()
+    }
+}
+
+/// Window of the game, running the stack of states.
+#[cfg(feature = "sfml")]
 pub struct Game {
     running: bool,
-    map: map::Map,
-    systems: systems::GameSystems,
-    units: units::unit_set::UnitSet,
 }
 
+#[cfg(feature = "sfml")]
 impl Game {
     pub fn new() -> Self {
-        let mut game = Self {
-            running: false,
-            map: map::Map::create_test_map(),
-            systems: systems::GameSystems::new(),
-            units: units::unit_set::UnitSet::new(),
-        };
-
-        game.units = units::unit_set::UnitSet::create_test_unit_set(&mut game.systems);
-        game
+        Self { running: false }
     }
 
     pub fn run(&mut self) -> Result<(), &'static str> {
@@ -39,114 +458,255 @@ impl Game {
             &Default::default(),
         );
         window.set_framerate_limit(60);
-        window.set_view(&View::new(
-            Vector2f { x: 0.0, y: 0.0 },
-            window.view().size(),
-        ));
-
-        let mut map_gfx = graphics::map::Map::new(
-            &self.map,
-            hexagons::Layout {
-                orientation: hexagons::Orientation::POINTY,
-                size: Vector2f { x: 50.0, y: 50.0 },
-                origin: Vector2f { x: 0.0, y: 0.0 },
-            },
-        );
 
         let font = Font::from_file("resources/fonts/OpenSans-Regular.ttf")
             .ok_or("Failed to load font.")?;
 
-        //    let mut unit = units::unit::Mechanized::new("test unit");
-        // unit.mc.occupation = Some(hexagons::HexCoordinates::new_axial(1, -1));
+        let bindings =
+            input::Bindings::from_file("resources/config/bindings.cfg").unwrap_or_else(|err| {
+                warn!("{} Using default key bindings.", err);
+                input::Bindings::default()
+            });
 
-        //  let mut token = graphics::tokens::Token::new(map_gfx.layout.clone(), &unit);
-
-        let mut current_mouse_pos = Vector2i::default();
+        let size = window.size();
+        let mut ctx = states::Context {
+            font,
+            input: input::Input::new(bindings),
+            themes: graphics::theme::Themes::load_dir("resources/themes"),
+            window_size: Vector2f {
+                x: size.x as f32,
+                y: size.y as f32,
+            },
+            has_focus: window.has_focus(),
+        };
+        let mut stack = states::StateStack::new(Box::new(states::menu::MainMenu::new()));
+        let mut clock = Clock::start();
 
         self.running = true;
         while self.running {
             while let Some(event) = window.poll_event() {
-                match event {
-                    Event::Closed => self.running = false,
-                    Event::KeyPressed { code, .. } => match code {
-                        Key::Right => {
-                            let mut view = window.view().to_owned();
-                            view.move_((8.0, 0.0));
-                            window.set_view(&view);
-                        }
-                        Key::Left => {
-                            let mut view = window.view().to_owned();
-                            view.move_((-8.0, 0.0));
-                            window.set_view(&view);
-                        }
-                        Key::Up => {
-                            let mut view = window.view().to_owned();
-                            view.move_((0.0, -8.0));
-                            window.set_view(&view);
-                        }
-                        Key::Down => {
-                            let mut view = window.view().to_owned();
-                            view.move_((0.0, 8.0));
-                            window.set_view(&view);
-                        }
-                        Key::Escape => window.close(),
-                        Key::W => map_gfx.layout.borrow_mut().size.y *= 0.95,
-                        Key::S => map_gfx.layout.borrow_mut().size.y *= 1.05,
-                        _ => {}
-                    },
-                    Event::MouseWheelScrolled {
-                        wheel: _,
-                        delta,
-                        x: _,
-                        y: _,
-                    } => {
-                        let mut view = window.view().to_owned();
-                        if delta > 0.0 {
-                            view.zoom(0.95);
-                        } else if delta < 0.0 {
-                            view.zoom(1.05);
-                        }
-                        window.set_view(&view);
-                    }
-                    Event::MouseMoved { x, y } => {
-                        current_mouse_pos.x = x;
-                        current_mouse_pos.y = y;
-                    }
-                    Event::Resized { width, height } => {
-                        let mut view = window.view().to_owned();
-                        view.set_size((width as f32, height as f32));
-                        window.set_view(&view);
-                    }
-                    _ => {}
+                if let Event::Closed = event {
+                    self.running = false;
                 }
+                stack.handle_event(&mut ctx, &event);
             }
 
-            map_gfx.update(&self.map);
-            //     token.update(&unit);
+            stack.update(&mut ctx, clock.restart().as_seconds());
+            if stack.is_empty() {
+                self.running = false;
+            }
 
             window.clear(&Color::CYAN);
+            stack.draw(&ctx, &mut window);
+            window.display();
+        }
+        window.close();
+        return Ok(());
+    }
+}
 
-            map_gfx.draw_hexes(&mut window);
-            map_gfx.draw_rivers(&mut window);
-            map_gfx.draw_outlines(&mut window);
-            map_gfx.draw_coords(&mut window, &font);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::History;
 
-            //       window.draw(token.fill_shape());
+    #[test]
+    fn scenario_winner() {
+        let mut scenario = Scenario::test();
+        assert_eq!(scenario.winner(), None);
 
-            let coordinate = hexagons::world_point_to_hex(
-                window.map_pixel_to_coords_current_view(&current_mouse_pos),
-                map_gfx.layout.borrow().clone(),
-            );
+        for (id, unit) in scenario.units.units().iter() {
+            if unit.side() == Side::Red {
+                scenario
+                    .systems
+                    .moving
+                    .components
+                    .get_mut(id)
+                    .unwrap()
+                    .occupation = None;
+            }
+        }
+        assert_eq!(scenario.winner(), Some(Side::Blue));
+    }
 
-            let entry = map_gfx.hexes.get(&coordinate);
-            match entry {
-                Some(shape) => window.draw(shape.highlight_shape()),
-                None => {}
-            };
+    #[test]
+    fn moving_and_attacking() {
+        let mut scenario = Scenario::test();
+        let red = hexagons::HexCoordinates::new_axial(-2, 3);
+        let next_to_red = hexagons::HexCoordinates::new_axial(-1, 3);
+        assert!(scenario.move_unit(2, next_to_red).is_err());
+        //red is out of sight, so the way looks free until the unit runs into it
+        assert!(scenario.movement_range(0).unwrap().contains(&red));
+        scenario.move_unit(0, red).unwrap();
+        let stopped = scenario.systems.moving.components[&0].occupation.unwrap();
+        assert_eq!(stopped.distance_to(&red), 1);
+        assert!(!scenario.movement_range(0).unwrap().contains(&red));
+        assert_eq!(
+            scenario.move_unit(0, red),
+            Err("Hex is occupied by the enemy.")
+        );
+        assert!(scenario.attack(0, 2).is_err());
 
-            window.display();
+        scenario.next_phase();
+        assert!(scenario.move_unit(1, next_to_red).is_err());
+        assert!(scenario.can_attack(0));
+        assert!(!scenario.can_attack(2));
+        let res = scenario.attack(0, 2).unwrap();
+        assert!(!scenario.can_attack(0));
+        assert!(scenario.attack(0, 2).is_err());
+
+        let occupation = |id| scenario.systems.moving.components[&id].occupation;
+        match res {
+            CombatResult::DefenderEliminated => assert_eq!(occupation(2), None),
+            CombatResult::AttackRepelled => assert_eq!(occupation(2), Some(red)),
+        }
+        assert_eq!(occupation(0), Some(stopped));
+
+        scenario.end_side_turn();
+        scenario.end_side_turn();
+        scenario.next_phase();
+        assert!(scenario.can_attack(0));
+    }
+
+    #[test]
+    fn undoing_commands() {
+        let mut scenario = Scenario::test();
+        //the infantry is held in reserve, off the map
+        scenario
+            .systems
+            .moving
+            .components
+            .get_mut(&1)
+            .unwrap()
+            .occupation = None;
+        let mut history = History::new();
+        let aside = hexagons::HexCoordinates::new_axial(1, 0);
+        let next_to_red = hexagons::HexCoordinates::new_axial(-1, 3);
+        let start = scenario.systems.moving.components[&0];
+        let reserve = scenario.systems.moving.components[&1];
+        let far = hexagons::HexCoordinates::new_axial(3, -3);
+
+        assert!(history
+            .execute(GameCommand::Place(2, far), &mut scenario)
+            .is_err());
+        history
+            .execute(GameCommand::Move(0, aside), &mut scenario)
+            .unwrap();
+        history
+            .execute(GameCommand::Place(1, far), &mut scenario)
+            .unwrap();
+        assert_ne!(scenario.systems.moving.components[&0], start);
+        history.undo(&mut scenario).unwrap();
+        history.undo(&mut scenario).unwrap();
+        assert_eq!(scenario.systems.moving.components[&0], start);
+        assert_eq!(scenario.systems.moving.components[&1], reserve);
+        assert_eq!(reserve.occupation, None);
+
+        history.redo(&mut scenario).unwrap();
+        let moved = scenario.systems.moving.components[&0];
+        assert_eq!(moved.occupation, Some(aside));
+        assert!(moved.moving_pts() < start.moving_pts());
+
+        //the red unit comes into sight, so the move can't be taken back
+        assert!(history.can_undo());
+        history
+            .execute(GameCommand::Move(0, next_to_red), &mut scenario)
+            .unwrap();
+        assert!(!history.can_undo());
+        assert_eq!(
+            scenario.systems.moving.components[&0].occupation,
+            Some(next_to_red)
+        );
+
+        history
+            .execute(GameCommand::NextPhase, &mut scenario)
+            .unwrap();
+        assert!(!history.can_undo());
+        history
+            .execute(GameCommand::Attack(0, 2), &mut scenario)
+            .unwrap();
+        assert!(history.undo(&mut scenario).is_err());
+        assert!(scenario.systems.combat.has_attacked(0));
+    }
+
+    #[test]
+    fn placing_units() {
+        let mut scenario = Scenario::test();
+        let far = hexagons::HexCoordinates::new_axial(3, -3);
+        let start = scenario.systems.moving.components[&0].occupation;
+        assert_eq!(
+            scenario.place_unit(0, far),
+            Err("Unit is already on the map.")
+        );
+        assert_eq!(scenario.systems.moving.components[&0].occupation, start);
+
+        scenario
+            .systems
+            .moving
+            .components
+            .get_mut(&0)
+            .unwrap()
+            .occupation = None;
+        scenario.place_unit(0, far).unwrap();
+        assert_eq!(scenario.systems.moving.components[&0].occupation, Some(far));
+        scenario.end_side_turn();
+        scenario.end_side_turn();
+        assert_eq!(
+            scenario.place_unit(0, far),
+            Err("Unit cannot be placed now.")
+        );
+    }
+
+    #[test]
+    fn writing_commands() {
+        let commands = [
+            GameCommand::Move(0, hexagons::HexCoordinates::new_axial(-1, 3)),
+            GameCommand::Place(1, hexagons::HexCoordinates::new_axial(-10, -2)),
+            GameCommand::Attack(0, 2),
+            GameCommand::NextPhase,
+            GameCommand::EndTurn,
+        ];
+        for command in commands.iter() {
+            assert_eq!(command.to_string().parse(), Ok(*command));
+        }
+        assert_eq!(commands[0].to_string(), "move 0 2,-1");
+        assert!("move 0".parse::<GameCommand>().is_err());
+        assert!("attack 0 x".parse::<GameCommand>().is_err());
+    }
+
+    #[test]
+    fn turn_order() {
+        let mut turn = Turn::default();
+        turn.next_phase();
+        assert_eq!(turn.phase(), Phase::Combat);
+        turn.next_phase();
+        assert_eq!((turn.number(), turn.side()), (1, Side::Red));
+        assert_eq!(turn.phase(), Phase::Movement);
+        turn.end_side_turn();
+        assert_eq!((turn.number(), turn.side()), (2, Side::Blue));
+    }
+
+    #[test]
+    fn ending_side_turn() {
+        let mut scenario = Scenario::test();
+        for mc in scenario.systems.moving.components.values_mut() {
+            mc.reduce_mv_pts(5).unwrap();
+        }
+        let blue: Vec<_> = scenario
+            .units
+            .units()
+            .keys()
+            .cloned()
+            .filter(|id| scenario.can_move(*id))
+            .collect();
+        assert_eq!(blue.len(), 2);
+
+        scenario.end_side_turn();
+        for (id, unit) in scenario.units.units().iter() {
+            let mc = &scenario.systems.moving.components[id];
+            let reset = mc.moving_pts() == mc.default_moving_pts();
+            assert_eq!(reset, unit.side() == Side::Red);
+            assert_eq!(scenario.can_move(*id), unit.side() == Side::Red);
         }
-        window.close();
-        return Ok(());
     }
 }
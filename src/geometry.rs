@@ -0,0 +1,207 @@
+//! Plain vector and rectangle types used by the map math, so the core of the game
+//! doesn't depend on any graphics library. With the sfml feature they convert
+//! to and from their SFML counterparts.
+
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Vector2<T> {
+    pub x: T,
+    pub y: T,
+}
+
+pub type Vector2f = Vector2<f32>;
+
+impl<T> Vector2<T> {
+    pub fn new(x: T, y: T) -> Self {
+        Vector2 { x, y }
+    }
+}
+
+impl<T: Add<Output = T>> Add for Vector2<T> {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Vector2::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl<T: AddAssign> AddAssign for Vector2<T> {
+    fn add_assign(&mut self, rhs: Self) {
+        self.x += rhs.x;
+        self.y += rhs.y;
+    }
+}
+
+impl<T: Sub<Output = T>> Sub for Vector2<T> {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Vector2::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl<T: SubAssign> SubAssign for Vector2<T> {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.x -= rhs.x;
+        self.y -= rhs.y;
+    }
+}
+
+impl<T: Mul<Output = T> + Copy> Mul<T> for Vector2<T> {
+    type Output = Self;
+    fn mul(self, rhs: T) -> Self {
+        Vector2::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+/// Component-wise product.
+impl<T: Mul<Output = T>> Mul for Vector2<T> {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Vector2::new(self.x * rhs.x, self.y * rhs.y)
+    }
+}
+
+impl Mul<Vector2f> for f32 {
+    type Output = Vector2f;
+    fn mul(self, rhs: Vector2f) -> Vector2f {
+        rhs * self
+    }
+}
+
+impl<T: MulAssign + Copy> MulAssign<T> for Vector2<T> {
+    fn mul_assign(&mut self, rhs: T) {
+        self.x *= rhs;
+        self.y *= rhs;
+    }
+}
+
+impl<T: Div<Output = T> + Copy> Div<T> for Vector2<T> {
+    type Output = Self;
+    fn div(self, rhs: T) -> Self {
+        Vector2::new(self.x / rhs, self.y / rhs)
+    }
+}
+
+/// Component-wise quotient.
+impl<T: Div<Output = T>> Div for Vector2<T> {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self {
+        Vector2::new(self.x / rhs.x, self.y / rhs.y)
+    }
+}
+
+impl<T: DivAssign + Copy> DivAssign<T> for Vector2<T> {
+    fn div_assign(&mut self, rhs: T) {
+        self.x /= rhs;
+        self.y /= rhs;
+    }
+}
+
+impl<T: Neg<Output = T>> Neg for Vector2<T> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Vector2::new(-self.x, -self.y)
+    }
+}
+
+impl<T> From<(T, T)> for Vector2<T> {
+    fn from((x, y): (T, T)) -> Self {
+        Vector2::new(x, y)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Rect<T> {
+    pub left: T,
+    pub top: T,
+    pub width: T,
+    pub height: T,
+}
+
+pub type FloatRect = Rect<f32>;
+
+impl<T: Copy + PartialOrd + Add<Output = T>> Rect<T> {
+    pub fn new(left: T, top: T, width: T, height: T) -> Self {
+        Rect {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+
+    /// Whether the point lies inside, the right and bottom edges are excluded.
+    pub fn contains(&self, point: Vector2<T>) -> bool {
+        point.x >= self.left
+            && point.x < self.left + self.width
+            && point.y >= self.top
+            && point.y < self.top + self.height
+    }
+}
+
+#[cfg(feature = "sfml")]
+mod sfml_conversions {
+    extern crate sfml;
+
+    use super::{Rect, Vector2};
+
+    impl<T> From<sfml::system::Vector2<T>> for Vector2<T> {
+        fn from(v: sfml::system::Vector2<T>) -> Self {
+            Vector2 { x: v.x, y: v.y }
+        }
+    }
+
+    impl<T> From<Vector2<T>> for sfml::system::Vector2<T> {
+        fn from(v: Vector2<T>) -> Self {
+            sfml::system::Vector2 { x: v.x, y: v.y }
+        }
+    }
+
+    impl<T> From<sfml::graphics::Rect<T>> for Rect<T> {
+        fn from(r: sfml::graphics::Rect<T>) -> Self {
+            Rect {
+                left: r.left,
+                top: r.top,
+                width: r.width,
+                height: r.height,
+            }
+        }
+    }
+
+    impl<T> From<Rect<T>> for sfml::graphics::Rect<T> {
+        fn from(r: Rect<T>) -> Self {
+            sfml::graphics::Rect {
+                left: r.left,
+                top: r.top,
+                width: r.width,
+                height: r.height,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vector_arithmetic() {
+        let a = Vector2f::new(1.0, 2.0);
+        let b = Vector2f::new(3.0, -4.0);
+        assert_eq!(a + b, Vector2f::new(4.0, -2.0));
+        assert_eq!(a - b, Vector2f::new(-2.0, 6.0));
+        assert_eq!(a * b, Vector2f::new(3.0, -8.0));
+        assert_eq!(2.0 * a, a * 2.0);
+        assert_eq!(b / 2.0, Vector2f::new(1.5, -2.0));
+        assert_eq!(-a, Vector2f::new(-1.0, -2.0));
+
+        let mut c = a;
+        c += b;
+        c *= 2.0;
+        assert_eq!(c, Vector2f::new(8.0, -4.0));
+
+        let rect = FloatRect::new(0.0, 0.0, 2.0, 2.0);
+        assert!(rect.contains(a - Vector2f::new(0.0, 1.0)));
+        assert!(!rect.contains(a));
+    }
+}
@@ -0,0 +1,219 @@
+extern crate sfml;
+
+use sfml::graphics::{FloatRect, View};
+use sfml::system::{Vector2f, Vector2i};
+
+use crate::maps::hexagons;
+
+/// Window view over the map, position and zoom are kept in world coordinates.
+#[derive(Debug, Clone)]
+pub struct Camera {
+    center: Vector2f,
+    //world units per window pixel
+    zoom: f32,
+    viewport: Vector2f,
+    bounds: Option<FloatRect>,
+    drag_anchor: Option<Vector2f>,
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    /// Panning speed, in window pixels per second.
+    pub pan_speed: f32,
+    /// Width of the window border, in pixels, that scrolls the view when hovered.
+    pub edge_margin: f32,
+}
+
+impl Camera {
+    pub fn new(viewport: Vector2f) -> Self {
+        Camera {
+            center: Vector2f::default(),
+            zoom: 1.0,
+            viewport,
+            bounds: None,
+            drag_anchor: None,
+            min_zoom: 0.25,
+            max_zoom: 4.0,
+            pan_speed: 600.0,
+            edge_margin: 8.0,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn center(&self) -> Vector2f {
+        self.center
+    }
+
+    #[cfg(test)]
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn view(&self) -> View {
+        View::new(self.center, self.viewport * self.zoom)
+    }
+
+    pub fn pixel_to_world(&self, pixel: Vector2i) -> Vector2f {
+        let pixel = Vector2f {
+            x: pixel.x as f32,
+            y: pixel.y as f32,
+        };
+        self.center + (pixel - self.viewport / 2.0) * self.zoom
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.viewport = Vector2f {
+            x: width as f32,
+            y: height as f32,
+        };
+        self.clamp();
+    }
+
+    /// Limits the camera to the given area, e.g. the bounding box of the map.
+    pub fn set_bounds(&mut self, bounds: Option<FloatRect>) {
+        self.bounds = bounds;
+        self.clamp();
+    }
+
+    /// Moves the view by the offset given in window pixels.
+    pub fn pan(&mut self, offset: Vector2f) {
+        self.center += offset * self.zoom;
+        self.clamp();
+    }
+
+    /// Zooms the view, keeping the world point under the cursor in place.
+    pub fn zoom_at(&mut self, factor: f32, pixel: Vector2i) {
+        let anchor = self.pixel_to_world(pixel);
+        let zoom = (self.zoom * factor).max(self.min_zoom).min(self.max_zoom);
+        self.center = anchor + (self.center - anchor) * (zoom / self.zoom);
+        self.zoom = zoom;
+        self.clamp();
+    }
+
+    pub fn center_on(&mut self, point: Vector2f) {
+        self.center = point;
+        self.clamp();
+    }
+
+    pub fn center_on_hex(&mut self, hex: hexagons::HexCoordinates, layout: hexagons::Layout) {
+        self.center_on(hexagons::hex_to_world_point(hex, layout).into());
+    }
+
+    pub fn start_drag(&mut self, pixel: Vector2i) {
+        self.drag_anchor = Some(self.pixel_to_world(pixel));
+    }
+
+    /// Moves the view, so the world point grabbed at the start of dragging stays under the cursor.
+    pub fn drag_to(&mut self, pixel: Vector2i) {
+        if let Some(anchor) = self.drag_anchor {
+            self.center += anchor - self.pixel_to_world(pixel);
+            self.clamp();
+        }
+    }
+
+    pub fn end_drag(&mut self) {
+        self.drag_anchor = None;
+    }
+
+    /// Scrolls the view when the cursor is close to the window border.
+    pub fn edge_scroll(&mut self, pixel: Vector2i, seconds: f32) {
+        let axis = |position: i32, size: f32| {
+            let position = position as f32;
+            if position < self.edge_margin {
+                -1.0
+            } else if position > size - self.edge_margin {
+                1.0
+            } else {
+                0.0
+            }
+        };
+        let direction = Vector2f {
+            x: axis(pixel.x, self.viewport.x),
+            y: axis(pixel.y, self.viewport.y),
+        };
+        self.pan(direction * self.pan_speed * seconds);
+    }
+
+    //keeps the visible area inside the bounds, or centered when it is larger than them
+    fn clamp(&mut self) {
+        let bounds = match self.bounds {
+            Some(b) => b,
+            None => return,
+        };
+        let half = self.viewport * self.zoom / 2.0;
+        let clamp_axis = |center: f32, half: f32, start: f32, length: f32| {
+            if 2.0 * half >= length {
+                start + length / 2.0
+            } else {
+                center.max(start + half).min(start + length - half)
+            }
+        };
+        self.center = Vector2f {
+            x: clamp_axis(self.center.x, half.x, bounds.left, bounds.width),
+            y: clamp_axis(self.center.y, half.y, bounds.top, bounds.height),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vector2f, b: Vector2f) {
+        assert!(
+            (a.x - b.x).abs() < 1e-3 && (a.y - b.y).abs() < 1e-3,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn zooming_at_cursor() {
+        let mut camera = Camera::new(Vector2f { x: 800.0, y: 600.0 });
+        let cursor = Vector2i { x: 600, y: 100 };
+        let before = camera.pixel_to_world(cursor);
+
+        camera.zoom_at(0.5, cursor);
+        assert_eq!(camera.zoom(), 0.5);
+        assert_close(camera.pixel_to_world(cursor), before);
+
+        camera.zoom_at(100.0, cursor);
+        assert_eq!(camera.zoom(), camera.max_zoom);
+        assert_close(camera.pixel_to_world(cursor), before);
+    }
+
+    #[test]
+    fn dragging() {
+        let mut camera = Camera::new(Vector2f { x: 800.0, y: 600.0 });
+        camera.zoom_at(2.0, Vector2i { x: 400, y: 300 });
+
+        camera.start_drag(Vector2i { x: 100, y: 100 });
+        let grabbed = camera.pixel_to_world(Vector2i { x: 100, y: 100 });
+        camera.drag_to(Vector2i { x: 150, y: 80 });
+        assert_close(camera.pixel_to_world(Vector2i { x: 150, y: 80 }), grabbed);
+        assert_close(camera.center(), Vector2f { x: -100.0, y: 40.0 });
+
+        camera.end_drag();
+        camera.drag_to(Vector2i { x: 0, y: 0 });
+        assert_close(camera.center(), Vector2f { x: -100.0, y: 40.0 });
+    }
+
+    #[test]
+    fn clamping_to_bounds() {
+        let mut camera = Camera::new(Vector2f { x: 100.0, y: 100.0 });
+        camera.set_bounds(Some(FloatRect::new(0.0, 0.0, 1000.0, 50.0)));
+        //the map is lower than the view, so it stays centered vertically
+        assert_close(camera.center(), Vector2f { x: 50.0, y: 25.0 });
+
+        camera.pan(Vector2f {
+            x: 2000.0,
+            y: 100.0,
+        });
+        assert_close(camera.center(), Vector2f { x: 950.0, y: 25.0 });
+
+        camera.edge_scroll(Vector2i { x: 1, y: 50 }, 0.1);
+        assert_close(camera.center(), Vector2f { x: 890.0, y: 25.0 });
+
+        camera.center_on(Vector2f { x: -500.0, y: 0.0 });
+        assert_close(camera.center(), Vector2f { x: 50.0, y: 25.0 });
+    }
+}
@@ -0,0 +1,200 @@
+extern crate sfml;
+
+use sfml::graphics::{
+    Color, FloatRect, Font, RectangleShape, RenderTarget, Shape, Text, Transformable, View,
+};
+use sfml::system::{Vector2f, Vector2i};
+
+pub const LINE_HEIGHT: f32 = 20.0;
+const TEXT_SIZE: u32 = 15;
+pub const PADDING: f32 = 6.0;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Widget {
+    //tooltips don't block clicks
+    Panel(FloatRect, bool),
+    Label(Vector2f, String),
+    Button(FloatRect, String, ButtonLook),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ButtonLook {
+    Normal,
+    Hovered,
+    Disabled,
+}
+
+fn to_f32(point: Vector2i) -> Vector2f {
+    Vector2f {
+        x: point.x as f32,
+        y: point.y as f32,
+    }
+}
+
+/// Immediate mode HUD drawn in window coordinates. Widgets are declared anew every frame,
+/// between `begin` and `draw`, buttons report clicks at the moment they are declared.
+#[derive(Debug, Clone, Default)]
+pub struct Hud {
+    mouse: Vector2f,
+    click: Option<Vector2f>,
+    widgets: Vec<Widget>,
+}
+
+impl Hud {
+    pub fn new() -> Self {
+        Hud::default()
+    }
+
+    pub fn begin(&mut self, mouse: Vector2i, click: Option<Vector2i>) {
+        self.mouse = to_f32(mouse);
+        self.click = click.map(to_f32);
+        self.widgets.clear();
+    }
+
+    /// Whether the point is covered by a panel or a button from the last frame,
+    /// so clicks there shouldn't reach the map.
+    pub fn contains(&self, point: Vector2i) -> bool {
+        let point = to_f32(point);
+        self.widgets.iter().any(|w| match w {
+            Widget::Panel(rect, true) | Widget::Button(rect, _, _) => rect.contains(point),
+            _ => false,
+        })
+    }
+
+    /// Panel with a line of text per entry, returns its area.
+    pub fn panel(&mut self, position: Vector2f, width: f32, lines: &[String]) -> FloatRect {
+        self.text_box(position, width, lines, true)
+    }
+
+    pub fn tooltip(&mut self, position: Vector2f, width: f32, lines: &[String]) -> FloatRect {
+        self.text_box(position, width, lines, false)
+    }
+
+    fn text_box(
+        &mut self,
+        position: Vector2f,
+        width: f32,
+        lines: &[String],
+        blocking: bool,
+    ) -> FloatRect {
+        let rect = FloatRect::new(
+            position.x,
+            position.y,
+            width,
+            lines.len() as f32 * LINE_HEIGHT + 2.0 * PADDING,
+        );
+        self.widgets.push(Widget::Panel(rect, blocking));
+        for (i, line) in lines.iter().enumerate() {
+            let label_pos = Vector2f {
+                x: rect.left + PADDING,
+                y: rect.top + PADDING + i as f32 * LINE_HEIGHT,
+            };
+            self.widgets.push(Widget::Label(label_pos, line.clone()));
+        }
+        rect
+    }
+
+    /// Returns true, if the button was clicked in this frame.
+    pub fn button(&mut self, rect: FloatRect, text: &str) -> bool {
+        let look = if rect.contains(self.mouse) {
+            ButtonLook::Hovered
+        } else {
+            ButtonLook::Normal
+        };
+        self.widgets
+            .push(Widget::Button(rect, text.to_owned(), look));
+        self.click.is_some_and(|c| rect.contains(c))
+    }
+
+    /// Greyed out button, it still blocks clicks but never reports them.
+    pub fn disabled_button(&mut self, rect: FloatRect, text: &str) {
+        self.widgets
+            .push(Widget::Button(rect, text.to_owned(), ButtonLook::Disabled));
+    }
+
+    pub fn draw(&self, target: &mut dyn RenderTarget, font: &Font, window_size: Vector2f) {
+        target.set_view(&View::from_rect(&FloatRect::new(
+            0.0,
+            0.0,
+            window_size.x,
+            window_size.y,
+        )));
+
+        let rectangle = |rect: &FloatRect, color: Color| {
+            let mut shape = RectangleShape::new();
+            shape.set_size(Vector2f {
+                x: rect.width,
+                y: rect.height,
+            });
+            shape.set_position((rect.left, rect.top));
+            shape.set_fill_color(&color);
+            shape.set_outline_color(&Color::BLACK);
+            shape.set_outline_thickness(-1.0);
+            shape
+        };
+        let text = |string: &str, position: Vector2f, color: Color| {
+            let mut text = Text::new(string, font, TEXT_SIZE);
+            text.set_fill_color(&color);
+            text.set_position(position);
+            text
+        };
+
+        for widget in self.widgets.iter() {
+            match widget {
+                Widget::Panel(rect, _) => {
+                    target.draw(&rectangle(rect, Color::rgba(240, 240, 220, 220)))
+                }
+                Widget::Label(position, string) => {
+                    target.draw(&text(string, *position, Color::BLACK))
+                }
+                Widget::Button(rect, string, look) => {
+                    let (color, text_color) = match look {
+                        ButtonLook::Normal => (Color::rgb(200, 200, 200), Color::BLACK),
+                        ButtonLook::Hovered => (Color::rgb(255, 220, 120), Color::BLACK),
+                        ButtonLook::Disabled => {
+                            (Color::rgb(220, 220, 220), Color::rgb(150, 150, 150))
+                        }
+                    };
+                    target.draw(&rectangle(rect, color));
+                    let bounds = Text::new(string, font, TEXT_SIZE).local_bounds();
+                    let position = Vector2f {
+                        x: rect.left + (rect.width - bounds.width) / 2.0 - bounds.left,
+                        y: rect.top + (rect.height - bounds.height) / 2.0 - bounds.top,
+                    };
+                    target.draw(&text(string, position, text_color));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hud_widgets() {
+        let mut hud = Hud::new();
+        let button = FloatRect::new(100.0, 100.0, 80.0, 30.0);
+
+        hud.begin(Vector2i { x: 0, y: 0 }, None);
+        let panel = hud.panel(
+            Vector2f { x: 10.0, y: 10.0 },
+            200.0,
+            &["first".to_owned(), "second".to_owned()],
+        );
+        assert_eq!(panel.height, 2.0 * LINE_HEIGHT + 2.0 * PADDING);
+        assert!(!hud.button(button, "Ok"));
+        assert!(hud.contains(Vector2i { x: 20, y: 20 }));
+        assert!(hud.contains(Vector2i { x: 120, y: 110 }));
+        assert!(!hud.contains(Vector2i { x: 300, y: 300 }));
+        hud.tooltip(Vector2f { x: 300.0, y: 300.0 }, 100.0, &["tip".to_owned()]);
+        assert!(!hud.contains(Vector2i { x: 310, y: 310 }));
+
+        hud.begin(Vector2i { x: 0, y: 0 }, Some(Vector2i { x: 120, y: 110 }));
+        assert!(hud.button(button, "Ok"));
+        assert!(!hud.button(FloatRect::new(0.0, 0.0, 10.0, 10.0), "Cancel"));
+        hud.disabled_button(FloatRect::new(110.0, 100.0, 80.0, 30.0), "Undo");
+        assert!(hud.contains(Vector2i { x: 180, y: 110 }));
+    }
+}
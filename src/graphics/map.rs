@@ -1,9 +1,14 @@
 extern crate log;
 extern crate sfml;
 
-use sfml::graphics::{Color, Font, RenderTarget, Text, TextStyle, Transformable};
+use sfml::graphics::{
+    Color, FloatRect, Font, PrimitiveType, RenderStates, RenderTarget, Text, TextStyle,
+    Transformable, VertexArray,
+};
+use sfml::system::Vector2f;
 
 use super::shapes::*;
+use super::theme::Theme;
 
 use crate::maps::*;
 
@@ -11,73 +16,427 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
 
-#[derive(Debug, Default)]
-pub struct Map<'a> {
+//coordinates smaller than this, in window pixels, are not drawn at all
+const MIN_COORDS_SIZE: f32 = 8.0;
+
+const OUTLINE_THICKNESS: f32 = 0.05;
+const RIVER_WIDTH: f32 = 0.1;
+
+//copies vertices of one hex over another one
+fn move_slot(array: &mut VertexArray, from: usize, to: usize, slot_size: usize) {
+    for i in 0..slot_size {
+        array[to * slot_size + i] = array[from * slot_size + i];
+    }
+}
+
+/// Whole map kept in a few vertex arrays, so it's drawn with a handful of draw calls.
+/// The arrays follow the change log of the map and are rebuilt from scratch only
+/// when the layout or the theme changes, or the log doesn't reach back far enough.
+#[derive(Debug)]
+pub struct Map {
     pub layout: Rc<RefCell<hexagons::Layout>>,
-    pub hexes: HashMap<hexagons::HexCoordinates, HexShape<'a>>,
-    pub rivers: Vec<RiverShape<'a>>,
+    pub coords_kind: hexagons::OffsetKind,
+    theme: Rc<Theme>,
+    //each hex occupies a slot of consecutive vertices in fills and outlines
+    hex_slots: HashMap<hexagons::HexCoordinates, usize>,
+    slot_hexes: Vec<hexagons::HexCoordinates>,
+    fills: VertexArray,
+    rivers: VertexArray,
+    outlines: VertexArray,
+    //map revision and layout the arrays were built for
+    revision: u64,
+    built_layout: Option<hexagons::Layout>,
 }
 
-impl<'a> Map<'a> {
+impl Map {
     pub fn new(map: &map::Map, layout: hexagons::Layout) -> Self {
         let layout = Rc::new(RefCell::new(layout));
         let mut res = Map {
             layout,
-            hexes: HashMap::new(),
-            rivers: Vec::new(),
+            coords_kind: Default::default(),
+            theme: Rc::new(Theme::new()),
+            hex_slots: HashMap::new(),
+            slot_hexes: Vec::new(),
+            fills: VertexArray::new(PrimitiveType::Triangles, 0),
+            rivers: VertexArray::new(PrimitiveType::Triangles, 0),
+            outlines: VertexArray::new(PrimitiveType::Triangles, 0),
+            revision: 0,
+            built_layout: None,
         };
         res.update(map);
         res
     }
 
+    pub fn theme(&self) -> &Rc<Theme> {
+        &self.theme
+    }
+
+    /// Replaces the theme, the map is rebuilt with the next update.
+    pub fn set_theme(&mut self, theme: Rc<Theme>) {
+        self.theme = theme;
+        self.built_layout = None;
+    }
+
+    #[cfg(test)]
+    pub fn is_up_to_date(&self, map: &map::Map) -> bool {
+        self.revision == map.revision() && self.built_layout == Some(*self.layout.borrow())
+    }
+
     pub fn update(&mut self, map: &map::Map) -> &mut Self {
-        info!("Updating graphical representation of the map.");
-        self.hexes = map
-            .hexes()
-            .values()
-            .map(|site| (*site.coord(), HexShape::new(self.layout.clone(), *site)))
-            .collect();
-
-        self.rivers = map
-            .rivers()
-            .values()
-            .map(|site| RiverShape::new(self.layout.clone(), *site))
-            .collect();
+        let layout = *self.layout.borrow();
+        if self.built_layout != Some(layout) {
+            return self.rebuild(map);
+        }
+        let changes = match map.changes_since(self.revision) {
+            Some(changes) => changes,
+            None => return self.rebuild(map),
+        };
+        if changes.is_empty() {
+            return self;
+        }
+
+        trace!("Applying {} map changes to its graphics.", changes.len());
+        let mut rivers_removed = false;
+        for change in changes {
+            match change {
+                map::MapChange::HexAdded(site) => self.add_hex(layout, site),
+                map::MapChange::HexChanged { new, .. } => self.repaint_hex(layout, new),
+                map::MapChange::HexRemoved(site) => self.remove_hex(site.coord()),
+                map::MapChange::RiverAdded(site) => self.add_river(layout, site),
+                map::MapChange::RiverRemoved(_) => rivers_removed = true,
+            }
+        }
+        if rivers_removed {
+            self.rivers.clear();
+            self.add_rivers(layout, map);
+        }
+
+        self.revision = map.revision();
         self
     }
 
-    pub fn draw_hexes(&self, target: &mut RenderTarget) {
-        for hex in self.hexes.values() {
-            target.draw(hex.fill_shape());
+    fn rebuild(&mut self, map: &map::Map) -> &mut Self {
+        debug!(
+            "Rebuilding graphical representation of the map, {} hexes.",
+            map.hexes().len()
+        );
+        let layout = *self.layout.borrow();
+        self.hex_slots.clear();
+        self.slot_hexes.clear();
+        self.fills.clear();
+        self.outlines.clear();
+        self.rivers.clear();
+
+        //sorted, so the arrays don't depend on the order of hash maps
+        let mut hex_ids: Vec<_> = map.hexes().keys().collect();
+        hex_ids.sort();
+        for id in hex_ids {
+            self.add_hex(layout, &map.hexes()[id]);
+        }
+        self.add_rivers(layout, map);
+
+        self.revision = map.revision();
+        self.built_layout = Some(layout);
+        self
+    }
+
+    fn add_hex(&mut self, layout: hexagons::Layout, site: &map::HexSite) {
+        self.hex_slots.insert(*site.coord(), self.slot_hexes.len());
+        self.slot_hexes.push(*site.coord());
+        self.append_fill(layout, site);
+        append_hex_outline(
+            &mut self.outlines,
+            layout,
+            *site.coord(),
+            OUTLINE_THICKNESS,
+            Color::BLACK,
+        );
+    }
+
+    fn append_fill(&mut self, layout: hexagons::Layout, site: &map::HexSite) {
+        //textured hexes are drawn white, so the texture keeps its own colors
+        let tile = self.theme.hex_tile(site.kind());
+        let color = match tile {
+            Some(_) => Color::WHITE,
+            None => self.theme.hex_color(site.kind()),
+        };
+        append_textured_hex(&mut self.fills, layout, *site.coord(), color, tile);
+    }
+
+    //the new vertices are appended past the end and then moved into the slot of the hex
+    fn repaint_hex(&mut self, layout: hexagons::Layout, site: &map::HexSite) {
+        if let Some(&slot) = self.hex_slots.get(site.coord()) {
+            self.append_fill(layout, site);
+            let count = self.slot_hexes.len();
+            move_slot(&mut self.fills, count, slot, HEX_VERTICES);
+            self.fills.resize(count * HEX_VERTICES);
         }
     }
 
-    pub fn draw_rivers(&self, target: &mut RenderTarget) {
-        for riv in self.rivers.iter() {
-            target.draw(riv.shape());
+    //the last hex is moved into the freed slot, so the arrays stay packed
+    fn remove_hex(&mut self, coord: &hexagons::HexCoordinates) {
+        let slot = match self.hex_slots.remove(coord) {
+            Some(slot) => slot,
+            None => return,
+        };
+        let last = self.slot_hexes.len() - 1;
+        if slot != last {
+            move_slot(&mut self.fills, last, slot, HEX_VERTICES);
+            move_slot(&mut self.outlines, last, slot, HEX_OUTLINE_VERTICES);
+            let moved = self.slot_hexes[last];
+            self.slot_hexes[slot] = moved;
+            self.hex_slots.insert(moved, slot);
         }
+        self.slot_hexes.pop();
+        self.fills.resize(last * HEX_VERTICES);
+        self.outlines.resize(last * HEX_OUTLINE_VERTICES);
+    }
+
+    fn add_rivers(&mut self, layout: hexagons::Layout, map: &map::Map) {
+        let mut river_ids: Vec<_> = map.rivers().keys().collect();
+        river_ids.sort();
+        for id in river_ids {
+            self.add_river(layout, &map.rivers()[id]);
+        }
+    }
+
+    fn add_river(&mut self, layout: hexagons::Layout, site: &map::RiverSite) {
+        let (side1, side2) = site.sides();
+        let tile = self.theme.river_tile(site.kind());
+        let color = match tile {
+            Some(_) => Color::WHITE,
+            None => self.theme.river_color(site.kind()),
+        };
+        append_edge(
+            &mut self.rivers,
+            layout,
+            *side1,
+            *side2,
+            RIVER_WIDTH,
+            color,
+            tile,
+        );
+    }
+
+    pub fn contains(&self, hex: &hexagons::HexCoordinates) -> bool {
+        self.hex_slots.contains_key(hex)
+    }
+
+    /// Bounding box of all hexes in world coordinates, None for an empty map.
+    pub fn bounds(&self) -> Option<FloatRect> {
+        let layout = *self.layout.borrow();
+        let mut points = self.slot_hexes.iter().flat_map(|coord| {
+            let center = hexagons::hex_to_world_point(*coord, layout);
+            (0..6).map(move |corner| Vector2f::from(center + layout.corner_offset(corner)))
+        });
+
+        let first = points.next()?;
+        let (min, max) = points.fold((first, first), |(min, max), p| {
+            (
+                Vector2f {
+                    x: min.x.min(p.x),
+                    y: min.y.min(p.y),
+                },
+                Vector2f {
+                    x: max.x.max(p.x),
+                    y: max.y.max(p.y),
+                },
+            )
+        });
+        Some(FloatRect::new(min.x, min.y, max.x - min.x, max.y - min.y))
     }
 
-    pub fn draw_outlines(&self, target: &mut RenderTarget) {
-        for hex in self.hexes.values() {
-            target.draw(hex.outline_shape());
+    //arrays are drawn with the atlas of the theme, if it has one
+    fn draw_textured(&self, target: &mut dyn RenderTarget, array: &VertexArray) {
+        match self.theme.atlas() {
+            Some(atlas) => {
+                let states = RenderStates {
+                    texture: Some(atlas),
+                    ..Default::default()
+                };
+                target.draw_with_renderstates(array, states);
+            }
+            None => target.draw(array),
         }
     }
 
-    pub fn draw_coords(&self, target: &mut RenderTarget, font: &Font) {
-        for (coord, hex) in self.hexes.iter() {
+    pub fn draw_hexes(&self, target: &mut dyn RenderTarget) {
+        self.draw_textured(target, &self.fills);
+    }
+
+    pub fn draw_rivers(&self, target: &mut dyn RenderTarget) {
+        self.draw_textured(target, &self.rivers);
+    }
+
+    pub fn draw_outlines(&self, target: &mut dyn RenderTarget) {
+        target.draw(&self.outlines);
+    }
+
+    /// Draws coordinates of the visible hexes, unless they would be too small to read.
+    pub fn draw_coords(&self, target: &mut dyn RenderTarget, font: &Font) {
+        let layout = *self.layout.borrow();
+        let view_size = target.view().size();
+        let pixels_per_unit = target.size().x as f32 / view_size.x;
+        let char_size = layout.size.x * 0.5;
+        if char_size * pixels_per_unit < MIN_COORDS_SIZE {
+            return;
+        }
+
+        let margin: Vector2f = layout.size.into();
+        let corner = target.view().center() - view_size / 2.0 - margin;
+        let visible = FloatRect::new(
+            corner.x,
+            corner.y,
+            view_size.x + 2.0 * layout.size.x,
+            view_size.y + 2.0 * layout.size.y,
+        );
+
+        for coord in self.slot_hexes.iter() {
+            let position = hexagons::hex_to_world_point(*coord, layout).into();
+            if !visible.contains(position) {
+                continue;
+            }
+
             let mut text = Text::new(
-                &format!("{}  {}", coord.q(), coord.p()),
+                &coord.to_offset(self.coords_kind).to_string(),
                 font,
-                (self.layout.borrow().size.x * 0.5) as u32,
+                char_size as u32,
             );
 
             text.set_fill_color(&Color::MAGENTA);
             text.set_outline_color(&Color::MAGENTA);
             text.set_style(TextStyle::BOLD);
             text.set_origin((text.local_bounds().width / 2.0, text.local_bounds().height));
-            text.set_position(hex.fill_shape().position());
+            text.set_position(position);
             target.draw(&mut text);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebuilding_large_map() {
+        let mut map = shapes::rectangle_pointy(0..=99, 0..=99, types::Field::Plain);
+        let layout = hexagons::Layout {
+            orientation: hexagons::Orientation::POINTY,
+            size: (10.0, 10.0).into(),
+            origin: (0.0, 0.0).into(),
+        };
+        let mut gfx = Map::new(&map, layout);
+        assert_eq!(gfx.fills.vertex_count(), 10_000 * 4 * 3);
+        assert_eq!(gfx.outlines.vertex_count(), 10_000 * 12 * 3);
+        assert!(gfx.is_up_to_date(&map));
+
+        map.set_hex_kind(hexagons::HexCoordinates::origin(), types::Field::Hill)
+            .unwrap();
+        assert!(!gfx.is_up_to_date(&map));
+        gfx.update(&map);
+        assert!(gfx.is_up_to_date(&map));
+
+        gfx.layout.borrow_mut().size.y *= 1.05;
+        assert!(!gfx.is_up_to_date(&map));
+        gfx.update(&map);
+        assert!(gfx.is_up_to_date(&map));
+        assert_eq!(gfx.fills.vertex_count(), 10_000 * 4 * 3);
+    }
+
+    //vertex colors of every hex, compared between incremental updates and full rebuilds
+    fn hex_colors(gfx: &Map) -> HashMap<hexagons::HexCoordinates, Vec<Color>> {
+        gfx.hex_slots
+            .iter()
+            .map(|(hex, slot)| {
+                let colors = (0..HEX_VERTICES)
+                    .map(|i| gfx.fills[slot * HEX_VERTICES + i].color)
+                    .collect();
+                (*hex, colors)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn incremental_updates() {
+        let mut map = map::Map::create_test_map();
+        let layout = hexagons::Layout {
+            orientation: hexagons::Orientation::POINTY,
+            size: (10.0, 10.0).into(),
+            origin: (0.0, 0.0).into(),
+        };
+        let mut gfx = Map::new(&map, layout);
+        let rivers = gfx.rivers.vertex_count();
+
+        map.set_hex_kind(
+            hexagons::HexCoordinates::new_axial(3, 3),
+            types::Field::Hill,
+        )
+        .unwrap();
+        map.remove_river(
+            hexagons::HexCoordinates::new_axial(-9, 7),
+            hexagons::HexCoordinates::new_axial(-8, 7),
+        )
+        .unwrap();
+        map.remove_hex(hexagons::HexCoordinates::new_axial(-10, 7))
+            .unwrap();
+        map.remove_hex(hexagons::HexCoordinates::new_axial(5, 0))
+            .unwrap();
+        map.insert_hex(map::HexSite::new(
+            hexagons::HexCoordinates::new_axial(20, 20),
+            types::Field::Forest,
+        ))
+        .unwrap();
+        gfx.update(&map);
+        assert!(gfx.is_up_to_date(&map));
+
+        assert!(!gfx.contains(&hexagons::HexCoordinates::new_axial(5, 0)));
+        assert!(gfx.contains(&hexagons::HexCoordinates::new_axial(20, 20)));
+        assert_eq!(gfx.fills.vertex_count(), 440 * HEX_VERTICES);
+        assert_eq!(gfx.outlines.vertex_count(), 440 * HEX_OUTLINE_VERTICES);
+        assert!(gfx.rivers.vertex_count() < rivers);
+
+        let rebuilt = Map::new(&map, layout);
+        assert_eq!(hex_colors(&gfx), hex_colors(&rebuilt));
+        assert_eq!(gfx.rivers.vertex_count(), rebuilt.rivers.vertex_count());
+    }
+
+    #[test]
+    fn changing_themes() {
+        let mut map = map::Map::create_test_map();
+        let layout = hexagons::Layout {
+            orientation: hexagons::Orientation::POINTY,
+            size: (10.0, 10.0).into(),
+            origin: (0.0, 0.0).into(),
+        };
+        let mut gfx = Map::new(&map, layout);
+        let hill = hexagons::HexCoordinates::new_axial(3, 3);
+        let theme = Theme::parse(
+            "plain.color = 1, 2, 3\n\
+             hill.texture = resources/textures/hill.png\n",
+        )
+        .unwrap();
+        let tile = theme.hex_tile(types::Field::Hill).unwrap();
+
+        gfx.set_theme(Rc::new(theme));
+        assert!(!gfx.is_up_to_date(&map));
+        gfx.update(&map);
+        let slot = gfx.hex_slots[&hexagons::HexCoordinates::origin()];
+        assert_eq!(gfx.fills[slot * HEX_VERTICES].color, Color::rgb(1, 2, 3));
+        assert_eq!(
+            gfx.fills[slot * HEX_VERTICES].tex_coords,
+            Vector2f::default()
+        );
+
+        map.set_hex_kind(hill, types::Field::Hill).unwrap();
+        gfx.update(&map);
+        let slot = gfx.hex_slots[&hill];
+        for i in 0..HEX_VERTICES {
+            let vertex = gfx.fills[slot * HEX_VERTICES + i];
+            assert_eq!(vertex.color, Color::WHITE);
+            let t = vertex.tex_coords;
+            assert!(t.x >= tile.left && t.x <= tile.left + tile.width);
+            assert!(t.y >= tile.top && t.y <= tile.top + tile.height);
+        }
+        assert_eq!(gfx.fills.vertex_count(), 441 * HEX_VERTICES);
+    }
+}
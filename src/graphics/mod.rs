@@ -1,3 +1,6 @@
+pub mod camera;
+pub mod hud;
 pub mod map;
 pub mod shapes;
-pub mod tokens;
\ No newline at end of file
+pub mod theme;
+pub mod tokens;
@@ -1,132 +1,244 @@
 extern crate sfml;
 
 use sfml::graphics::{
-    Color, ConvexShape, Drawable, RenderStates, RenderTarget, Shape, Transformable,
+    CircleShape, Color, ConvexShape, Drawable, FloatRect, PrimitiveType, RenderStates,
+    RenderTarget, Shape, Transformable, Vertex, VertexArray,
 };
 
 use sfml::system::Vector2f;
 
-use std::cell::RefCell;
-use std::rc::Rc;
+use crate::maps::hexagons;
+use crate::render::scene;
 
-use crate::maps::{hexagons, map, types};
+pub const HIGHLIGHT_COLOR: Color = Color {
+    r: 255,
+    g: 0,
+    b: 0,
+    a: 120,
+};
+
+/// Covers hexes never seen by the player.
+pub const UNEXPLORED_COLOR: Color = Color {
+    r: 60,
+    g: 60,
+    b: 60,
+    a: 255,
+};
 
-pub fn hex_color(kind: types::Field) -> Color {
-    match kind {
-        types::Field::Plain => Color::GREEN,
-        types::Field::Forest => Color::rgb(100, 140, 20),
+/// Dims hexes seen before, but out of sight now.
+pub const OUT_OF_SIGHT_COLOR: Color = Color {
+    r: 0,
+    g: 0,
+    b: 0,
+    a: 80,
+};
+
+/// Tints hexes the enemy can attack next turn, the stronger the threat the darker.
+pub fn threat_color(threat: i32) -> Color {
+    Color {
+        r: 200,
+        g: 0,
+        b: 0,
+        a: (threat.max(0) * 12).min(160) as u8,
     }
 }
 
-pub fn river_color(kind: types::River) -> Color {
-    match kind {
-        types::River::Small => Color::BLUE,
-        types::River::Stream => Color::CYAN,
+fn append_triangle(array: &mut VertexArray, points: [Vector2f; 3], color: Color) {
+    for point in points.iter() {
+        array.append(&Vertex::with_pos_color(*point, color));
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct HexShape<'a> {
-    layout: Rc<RefCell<hexagons::Layout>>,
-    shape: ConvexShape<'a>,
-    highlighting_shape: ConvexShape<'a>,
-    outline_shape: ConvexShape<'a>,
+/// Corners of the hex in world coordinates.
+pub fn hex_corners(layout: hexagons::Layout, hex: hexagons::HexCoordinates) -> [Vector2f; 6] {
+    let mut corners = [Vector2f::default(); 6];
+    for (corner, point) in corners
+        .iter_mut()
+        .zip(scene::hex_corners(layout, hex).iter())
+    {
+        *corner = (*point).into();
+    }
+    corners
 }
 
-impl<'a> HexShape<'a> {
-    pub fn new(layout: Rc<RefCell<hexagons::Layout>>, site: map::HexSite) -> Self {
-        let mut hs = HexShape {
-            layout,
-            shape: ConvexShape::new(6),
-            highlighting_shape: ConvexShape::new(6),
-            outline_shape: ConvexShape::new(6),
-        };
-        hs.update(site);
-        hs
-    }
+/// Number of vertices appended by append_hex.
+pub const HEX_VERTICES: usize = 12;
+/// Number of vertices appended by append_hex_outline.
+pub const HEX_OUTLINE_VERTICES: usize = 36;
+
+/// Appends the hex to the array of triangles.
+pub fn append_hex(
+    array: &mut VertexArray,
+    layout: hexagons::Layout,
+    hex: hexagons::HexCoordinates,
+    color: Color,
+) {
+    append_textured_hex(array, layout, hex, color, None);
+}
 
-    pub fn update(&mut self, site: map::HexSite) {
-        let layout = *self.layout.borrow();
-        for i in 0..6 {
-            self.shape.set_point(i, layout.corner_offset(i));
+/// Appends the hex with the tile of a texture stretched over it.
+/// Without the tile all texture coordinates are zero.
+pub fn append_textured_hex(
+    array: &mut VertexArray,
+    layout: hexagons::Layout,
+    hex: hexagons::HexCoordinates,
+    color: Color,
+    tile: Option<FloatRect>,
+) {
+    let center: Vector2f = hexagons::hex_to_world_point(hex, layout).into();
+    let tex_coords = |point: Vector2f| match tile {
+        Some(tile) => Vector2f {
+            x: tile.left + tile.width * (0.5 + (point.x - center.x) / (2.0 * layout.size.x)),
+            y: tile.top + tile.height * (0.5 + (point.y - center.y) / (2.0 * layout.size.y)),
+        },
+        None => Vector2f::default(),
+    };
+
+    let c = hex_corners(layout, hex);
+    for i in 1..5 {
+        for point in [c[0], c[i], c[i + 1]].iter() {
+            array.append(&Vertex::new(*point, color, tex_coords(*point)));
         }
-        self.shape
-            .set_position(hexagons::hex_to_world_point(*site.coord(), layout));
-        self.highlighting_shape = self.shape.clone();
-        self.outline_shape = self.shape.clone();
-
-        let thickness = -layout.size.x.min(layout.size.y) * 0.04;
-        self.outline_shape.set_outline_thickness(thickness);
-        self.outline_shape.set_outline_color(&Color::BLACK);
-        self.outline_shape.set_fill_color(&Color::TRANSPARENT);
-
-        self.highlighting_shape.set_outline_thickness(0.0);
-        self.highlighting_shape
-            .set_fill_color(&Color::rgba(255, 0, 0, 120));
-
-        self.shape.set_fill_color(&hex_color(site.kind()));
-        self.shape.set_outline_thickness(0.0);
     }
+}
 
-    pub fn fill_shape(&self) -> &ConvexShape<'a> {
-        &self.shape
+/// Appends a border running along the inner side of the hex edges,
+/// thickness is given as a fraction of the hex size.
+pub fn append_hex_outline(
+    array: &mut VertexArray,
+    layout: hexagons::Layout,
+    hex: hexagons::HexCoordinates,
+    thickness: f32,
+    color: Color,
+) {
+    for quad in scene::outline_quads(layout, hex, thickness).iter() {
+        let p: Vec<Vector2f> = quad.iter().map(|p| (*p).into()).collect();
+        append_triangle(array, [p[0], p[1], p[2]], color);
+        append_triangle(array, [p[0], p[2], p[3]], color);
     }
+}
 
-    pub fn outline_shape(&self) -> &ConvexShape<'a> {
-        &self.outline_shape
+/// Appends a quad lying along the hexside shared by the two hexes,
+/// optionally with the tile of a texture stretched along it.
+pub fn append_edge(
+    array: &mut VertexArray,
+    layout: hexagons::Layout,
+    side1: hexagons::HexCoordinates,
+    side2: hexagons::HexCoordinates,
+    width: f32,
+    color: Color,
+    tile: Option<FloatRect>,
+) {
+    let p = edge_points(layout, side1, side2, width);
+    let t = match tile {
+        Some(t) => [
+            Vector2f {
+                x: t.left,
+                y: t.top,
+            },
+            Vector2f {
+                x: t.left,
+                y: t.top + t.height,
+            },
+            Vector2f {
+                x: t.left + t.width,
+                y: t.top + t.height,
+            },
+            Vector2f {
+                x: t.left + t.width,
+                y: t.top,
+            },
+        ],
+        None => [Vector2f::default(); 4],
+    };
+    for &i in [0, 1, 2, 0, 2, 3].iter() {
+        array.append(&Vertex::new(p[i], color, t[i]));
     }
+}
 
-    pub fn highlight_shape(&self) -> &ConvexShape<'a> {
-        &self.highlighting_shape
+/// Triangles covering all the given hexes, e.g. to highlight them.
+pub fn hexes_shape<I: IntoIterator<Item = hexagons::HexCoordinates>>(
+    layout: hexagons::Layout,
+    hexes: I,
+    color: Color,
+) -> VertexArray {
+    let mut array = VertexArray::new(PrimitiveType::Triangles, 0);
+    for hex in hexes {
+        append_hex(&mut array, layout, hex, color);
     }
+    array
 }
 
 #[derive(Debug, Clone)]
-pub struct RiverShape<'a> {
-    layout: Rc<RefCell<hexagons::Layout>>,
+pub struct EdgeShape<'a> {
     shape: ConvexShape<'a>,
 }
 
-impl<'a> RiverShape<'a> {
-    pub fn new(layout: Rc<RefCell<hexagons::Layout>>, site: map::RiverSite) -> Self {
-        let shape = ConvexShape::new(4);
-        let mut rs = RiverShape { layout, shape };
-        rs.update(site);
-        rs
+impl<'a> EdgeShape<'a> {
+    pub fn new(
+        layout: hexagons::Layout,
+        side1: hexagons::HexCoordinates,
+        side2: hexagons::HexCoordinates,
+    ) -> Self {
+        let mut shape = ConvexShape::new(4);
+        for (i, point) in edge_points(layout, side1, side2, 0.15).iter().enumerate() {
+            shape.set_point(i as u32, *point);
+        }
+        shape.set_outline_thickness(0.0);
+        shape.set_fill_color(&Color::rgba(255, 0, 0, 200));
+        EdgeShape { shape }
     }
 
-    pub fn update(&mut self, site: map::RiverSite) {
-        let coordinate1 = *site.sides().0;
-        let coordinate2 = *site.sides().1;
-
-        let layout = *self.layout.borrow();
-
-        let vec1 = hexagons::hex_to_world_point(coordinate1, layout);
-        let vec2 = hexagons::hex_to_world_point(coordinate2, layout);
-
-        let center = (vec1 + vec2) / 2.0;
-        let connecter = (vec1 - vec2) / 2.0;
-        let connecter_orth = Vector2f {
-            x: -connecter.y * layout.size.x / layout.size.y,
-            y: connecter.x * layout.size.y / layout.size.x,
-        };
-
-        self.shape
-            .set_point(0, 1.05 * connecter_orth / 2.0 + 0.1 * connecter);
-        self.shape
-            .set_point(1, 1.05 * connecter_orth / 2.0 - 0.1 * connecter);
-        self.shape
-            .set_point(2, -1.05 * connecter_orth / 2.0 - 0.1 * connecter);
-        self.shape
-            .set_point(3, -1.05 * connecter_orth / 2.0 + 0.1 * connecter);
+    pub fn highlight_shape(&self) -> &ConvexShape<'a> {
+        &self.shape
+    }
+}
 
-        self.shape.set_position(center);
-        self.shape.set_outline_thickness(0.0);
+/// Dot on the corner where the three hexes meet, e.g. to show where a river would start.
+pub fn corner_shape<'a>(
+    layout: hexagons::Layout,
+    corner: (
+        hexagons::HexCoordinates,
+        hexagons::HexCoordinates,
+        hexagons::HexCoordinates,
+    ),
+) -> CircleShape<'a> {
+    let (a, b, c) = corner;
+    let center = (hexagons::hex_to_world_point(a, layout)
+        + hexagons::hex_to_world_point(b, layout)
+        + hexagons::hex_to_world_point(c, layout))
+        / 3.0;
+    let radius = 0.1 * layout.size.x;
+    let mut shape = CircleShape::new(radius, 12);
+    shape.set_position(Vector2f::from(center) - Vector2f::new(radius, radius));
+    shape.set_fill_color(&HIGHLIGHT_COLOR);
+    shape
+}
 
-        self.shape.set_fill_color(&river_color(site.kind()));
+/// Line connecting centers of the consecutive hexes of the path.
+pub fn path_shape(layout: hexagons::Layout, path: &[hexagons::HexCoordinates]) -> VertexArray {
+    let mut shape = VertexArray::new(PrimitiveType::LineStrip, 0);
+    for hex in path {
+        let center: Vector2f = hexagons::hex_to_world_point(*hex, layout).into();
+        shape.append(&Vertex::with_pos_color(center, Color::RED));
     }
+    shape
+}
 
-    pub fn shape(&self) -> &ConvexShape<'a> {
-        &self.shape
+/// Corners of a quad along the hexside shared by side1 and side2,
+/// width is given as a fraction of the distance between the hex centers.
+pub fn edge_points(
+    layout: hexagons::Layout,
+    side1: hexagons::HexCoordinates,
+    side2: hexagons::HexCoordinates,
+    width: f32,
+) -> [Vector2f; 4] {
+    let mut points = [Vector2f::default(); 4];
+    for (point, p) in points
+        .iter_mut()
+        .zip(scene::edge_points(layout, side1, side2, width).iter())
+    {
+        *point = (*p).into();
     }
+    points
 }
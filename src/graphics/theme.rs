@@ -0,0 +1,338 @@
+extern crate log;
+extern crate sfml;
+
+use sfml::graphics::{
+    Color, FloatRect, RectangleShape, RenderTarget, RenderTexture, Shape, Sprite, Texture,
+    TextureRef, Transformable,
+};
+use sfml::system::Vector2f;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::rc::Rc;
+
+use crate::maps::types::{Field, River};
+use crate::render::palette::{self, Palette};
+use crate::units::unit::UnitType;
+
+//size of a single tile in the terrain atlas
+const TILE_SIZE: u32 = 128;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum Item {
+    Field(Field),
+    River(River),
+    Unit(UnitType),
+}
+
+const ITEM_NAMES: [(&str, Item); 8] = [
+    ("plain", Item::Field(Field::Plain)),
+    ("forest", Item::Field(Field::Forest)),
+    ("hill", Item::Field(Field::Hill)),
+    ("small_river", Item::River(River::Small)),
+    ("stream", Item::River(River::Stream)),
+    ("infantry", Item::Unit(UnitType::Infantry)),
+    ("mechanized", Item::Unit(UnitType::Mechanized)),
+    ("armoured", Item::Unit(UnitType::Armoured)),
+];
+
+//units keep the colors of their sides, only the art of counters can be changed
+fn is_unit(item: Item) -> bool {
+    matches!(item, Item::Unit(_))
+}
+
+fn parse_color(text: &str) -> Option<palette::Color> {
+    let values: Vec<u8> = text
+        .split(',')
+        .map(|v| v.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    match values[..] {
+        [r, g, b] => Some(palette::Color::rgb(r, g, b)),
+        [r, g, b, a] => Some(palette::Color::rgba(r, g, b, a)),
+        _ => None,
+    }
+}
+
+/// Colors and textures used to draw the map, and art of unit counters.
+/// Terrain and river textures are packed into a single atlas, so the whole map
+/// is still drawn with one texture. The first tile of the atlas is plain white,
+/// vertices with default texture coordinates are drawn with their flat color.
+/// Anything the theme doesn't define falls back to the builtin colors.
+#[derive(Default)]
+pub struct Theme {
+    pub name: String,
+    /// Flat colors, also used to export the map without textures.
+    pub palette: Palette,
+    tiles: HashMap<Item, FloatRect>,
+    atlas: Option<RenderTexture>,
+    unit_textures: HashMap<UnitType, Texture>,
+}
+
+impl fmt::Debug for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Theme {{ name: {:?} }}", self.name)
+    }
+}
+
+impl Theme {
+    /// Builtin theme with flat colors only.
+    pub fn new() -> Self {
+        Theme {
+            name: "builtin".to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// Parses lines in the form "item.color = r, g, b[, a]" or "item.texture = path",
+    /// "#" starts a comment. Textures that fail to load are skipped with a warning.
+    pub fn parse(text: &str) -> Result<Self, &'static str> {
+        let mut theme = Theme::new();
+        let mut textures = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap().trim();
+            let value = parts
+                .next()
+                .ok_or_else(|| {
+                    error!("Missing '=' in theme, line {}.", number + 1);
+                    "Invalid theme."
+                })?
+                .trim();
+            if key == "name" {
+                theme.name = value.to_owned();
+                continue;
+            }
+
+            let mut key_parts = key.splitn(2, '.');
+            let item_name = key_parts.next().unwrap();
+            let item = ITEM_NAMES
+                .iter()
+                .find(|(n, _)| *n == item_name)
+                .map(|(_, i)| *i)
+                .ok_or_else(|| {
+                    error!("Unknown item {} in theme, line {}.", item_name, number + 1);
+                    "Invalid theme."
+                })?;
+
+            match key_parts.next() {
+                Some("color") if !is_unit(item) => {
+                    let color = parse_color(value).ok_or_else(|| {
+                        error!("Invalid color {} in theme, line {}.", value, number + 1);
+                        "Invalid theme."
+                    })?;
+                    match item {
+                        Item::Field(kind) => theme.palette.set_hex_color(kind, color),
+                        Item::River(kind) => theme.palette.set_river_color(kind, color),
+                        Item::Unit(_) => unreachable!(),
+                    };
+                }
+                Some("texture") => match Texture::from_file(value) {
+                    Some(texture) => textures.push((item, texture)),
+                    None => warn!(
+                        "Failed to load texture {}, {} will be drawn with a flat color.",
+                        value, item_name
+                    ),
+                },
+                _ => {
+                    error!("Unknown property {} in theme, line {}.", key, number + 1);
+                    return Err("Invalid theme.");
+                }
+            }
+        }
+
+        let mut terrain = Vec::new();
+        for (item, texture) in textures {
+            match item {
+                Item::Unit(kind) => {
+                    theme.unit_textures.insert(kind, texture);
+                }
+                _ => terrain.push((item, texture)),
+            }
+        }
+        theme.build_atlas(&terrain)?;
+        Ok(theme)
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, &'static str> {
+        let text = fs::read_to_string(path).map_err(|_| "Failed to read theme file.")?;
+        Self::parse(&text)
+    }
+
+    fn build_atlas(&mut self, textures: &[(Item, Texture)]) -> Result<(), &'static str> {
+        if textures.is_empty() {
+            return Ok(());
+        }
+
+        let count = textures.len() as u32 + 1;
+        let mut atlas = RenderTexture::new(TILE_SIZE * count, TILE_SIZE, false)
+            .ok_or("Failed to create texture atlas.")?;
+        let mut white = RectangleShape::new();
+        white.set_size(Vector2f {
+            x: TILE_SIZE as f32,
+            y: TILE_SIZE as f32,
+        });
+        white.set_fill_color(&Color::WHITE);
+        atlas.draw(&white);
+
+        let tile = TILE_SIZE as f32;
+        for (i, (item, texture)) in textures.iter().enumerate() {
+            let left = tile * (i + 1) as f32;
+            let size = texture.size();
+            let mut sprite = Sprite::with_texture(texture);
+            sprite.set_scale((tile / size.x.max(1) as f32, tile / size.y.max(1) as f32));
+            sprite.set_position((left, 0.0));
+            atlas.draw(&sprite);
+            self.tiles
+                .insert(*item, FloatRect::new(left, 0.0, tile, tile));
+        }
+        atlas.display();
+        self.atlas = Some(atlas);
+        Ok(())
+    }
+
+    pub fn hex_color(&self, kind: Field) -> Color {
+        self.palette.hex_color(kind).into()
+    }
+
+    pub fn river_color(&self, kind: River) -> Color {
+        self.palette.river_color(kind).into()
+    }
+
+    /// Area of the atlas covered by the texture of the field, if it has one.
+    pub fn hex_tile(&self, kind: Field) -> Option<FloatRect> {
+        self.tiles.get(&Item::Field(kind)).cloned()
+    }
+
+    pub fn river_tile(&self, kind: River) -> Option<FloatRect> {
+        self.tiles.get(&Item::River(kind)).cloned()
+    }
+
+    pub fn atlas(&self) -> Option<&TextureRef> {
+        self.atlas.as_ref().map(|a| a.texture())
+    }
+
+    pub fn unit_texture(&self, kind: UnitType) -> Option<&TextureRef> {
+        self.unit_textures.get(&kind).map(|t| &**t)
+    }
+}
+
+/// Themes available at runtime, one of them being used at a time.
+#[derive(Debug)]
+pub struct Themes {
+    themes: Vec<Rc<Theme>>,
+    current: usize,
+}
+
+impl Default for Themes {
+    fn default() -> Self {
+        Themes {
+            themes: vec![Rc::new(Theme::new())],
+            current: 0,
+        }
+    }
+}
+
+impl Themes {
+    /// Loads all ".theme" files from the directory, ordered by file name.
+    /// Invalid files are skipped, the builtin theme is used when none is loaded.
+    pub fn load_dir(path: &str) -> Self {
+        let mut paths: Vec<_> = match fs::read_dir(path) {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|e| e == "theme"))
+                .collect(),
+            Err(_) => {
+                warn!("Failed to read themes directory {}.", path);
+                Vec::new()
+            }
+        };
+        paths.sort();
+
+        let mut themes = Vec::new();
+        for path in paths {
+            match Theme::from_file(&path.to_string_lossy()) {
+                Ok(theme) => {
+                    info!("Loaded theme {} from {:?}.", theme.name, path);
+                    themes.push(Rc::new(theme));
+                }
+                Err(err) => warn!("{} Skipping {:?}.", err, path),
+            }
+        }
+
+        if themes.is_empty() {
+            return Themes::default();
+        }
+        Themes { themes, current: 0 }
+    }
+
+    pub fn current(&self) -> Rc<Theme> {
+        self.themes[self.current].clone()
+    }
+
+    /// Switches to the next theme, wrapping around after the last one.
+    pub fn next(&mut self) -> Rc<Theme> {
+        self.current = (self.current + 1) % self.themes.len();
+        info!("Switched to theme {}.", self.themes[self.current].name);
+        self.current()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_themes() {
+        let theme = Theme::parse(
+            "name = Test # comment\n\
+             \n\
+             forest.color = 10, 20, 30\n\
+             stream.color = 1, 2, 3, 4\n\
+             hill.texture = resources/textures/hill.png\n\
+             plain.texture = resources/textures/missing.png\n",
+        )
+        .unwrap();
+        assert_eq!(theme.name, "Test");
+        assert_eq!(theme.hex_color(Field::Forest), Color::rgb(10, 20, 30));
+        assert_eq!(
+            theme.hex_color(Field::Plain),
+            palette::hex_color(Field::Plain).into()
+        );
+        assert_eq!(theme.river_color(River::Stream), Color::rgba(1, 2, 3, 4));
+        assert!(theme.atlas().is_some());
+        assert_eq!(
+            theme.hex_tile(Field::Hill),
+            Some(FloatRect::new(128.0, 0.0, 128.0, 128.0))
+        );
+        assert_eq!(theme.hex_tile(Field::Plain), None);
+
+        assert!(Theme::parse("forest.color = 10, 20").is_err());
+        assert!(Theme::parse("road.color = 10, 20, 30").is_err());
+        assert!(Theme::parse("forest.size = 10").is_err());
+        assert!(Theme::parse("infantry.color = 10, 20, 30").is_err());
+        assert!(Theme::parse("forest").is_err());
+        assert!(Theme::new().atlas().is_none());
+    }
+
+    #[test]
+    fn switching_themes() {
+        let mut themes = Themes::load_dir("resources/themes");
+        let first = themes.current();
+        assert!(themes.themes.len() > 1);
+        assert!(!Rc::ptr_eq(&first, &themes.next()));
+        for _ in 1..themes.themes.len() {
+            themes.next();
+        }
+        assert!(Rc::ptr_eq(&first, &themes.current()));
+
+        let themes = Themes::load_dir("resources/no_such_directory");
+        assert_eq!(themes.current().name, "builtin");
+    }
+}
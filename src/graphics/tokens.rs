@@ -2,57 +2,191 @@ extern crate log;
 extern crate sfml;
 
 use sfml::graphics::{
-    Color, Drawable, RectangleShape, RenderStates, RenderTarget, Shape, Transformable,
+    Color, ConvexShape, Font, PrimitiveType, RectangleShape, RenderTarget, Shape, Sprite, Text,
+    Transformable, Vertex, VertexArray,
 };
 
 use sfml::system::Vector2f;
 
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
 
-use crate::maps::{hexagons, map};
+use super::theme::Theme;
+use crate::maps::hexagons;
+use crate::render::palette;
+use crate::systems::components::MovingComponent;
+use crate::systems::moving::MovingSystem;
+use crate::units::unit::{Unit, UnitType};
+use crate::units::unit_set::UnitSet;
 
-use crate::units::unit::Unit;
-
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct Token<'a> {
     layout: Rc<RefCell<hexagons::Layout>>,
     shape: RectangleShape<'a>,
     highlighting_shape: RectangleShape<'a>,
+    frame: RectangleShape<'a>,
+    oval: Option<ConvexShape<'a>>,
+    cross: VertexArray,
+    kind: UnitType,
+    name: String,
+    moving_pts: String,
 }
 
 impl<'a> Token<'a> {
-    pub fn new(layout: Rc<RefCell<hexagons::Layout>>, unit: &Unit) -> Self {
+    pub fn new(
+        layout: Rc<RefCell<hexagons::Layout>>,
+        unit: &Unit,
+        mc: &MovingComponent,
+        stack_level: usize,
+    ) -> Self {
         let mut token = Self {
             layout,
             shape: RectangleShape::default(),
             highlighting_shape: RectangleShape::default(),
+            frame: RectangleShape::default(),
+            oval: None,
+            cross: VertexArray::new(PrimitiveType::Lines, 0),
+            kind: unit.kind(),
+            name: String::new(),
+            moving_pts: String::new(),
         };
-        token.update(unit);
+        token.update(unit, mc, stack_level);
         token
     }
 
-    pub fn update(&mut self, unit: &Unit) {
-        info!("Updating token, for unit: {}", unit.name());
+    pub fn update(&mut self, unit: &Unit, mc: &MovingComponent, stack_level: usize) {
+        trace!("Updating token, for unit: {}", unit.name());
         let layout = *self.layout.borrow();
-        let size = layout.size;
+        let size: Vector2f = layout.size.into();
+
+        //units stacked on one hex are drawn as layers shifted up and right
+        let position = mc
+            .occupation
+            .map(|occ| Vector2f::from(hexagons::hex_to_world_point(occ, layout)))
+            .unwrap_or_default()
+            + Vector2f {
+                x: size.x * 0.12,
+                y: -size.y * 0.12,
+            } * stack_level as f32;
+
         self.shape.set_size(size);
         self.shape.set_origin(size / 2.0);
- //       if let Some(occ) = unit.get_occupation() {
- //           self.shape
- //               .set_position(hexagons::hex_to_world_point(occ, layout));
- //       }
+        self.shape.set_position(position);
         self.highlighting_shape = self.shape.clone();
 
         let thickness = -layout.size.x.min(layout.size.y) * 0.04;
         self.shape.set_outline_thickness(thickness);
         self.shape.set_outline_color(&Color::BLACK);
-        self.shape.set_fill_color(&Color::YELLOW);
-        self.shape.set_outline_thickness(0.0);
+        self.shape
+            .set_fill_color(&palette::side_color(unit.side()).into());
 
         self.highlighting_shape.set_outline_thickness(0.0);
         self.highlighting_shape
             .set_fill_color(&Color::rgba(255, 0, 0, 120));
+
+        //NATO symbol frame, placed slightly above the center of the counter
+        let frame_size = Vector2f {
+            x: size.x * 0.6,
+            y: size.y * 0.36,
+        };
+        let frame_center = position
+            + Vector2f {
+                x: 0.0,
+                y: -size.y * 0.06,
+            };
+        self.frame.set_size(frame_size);
+        self.frame.set_origin(frame_size / 2.0);
+        self.frame.set_position(frame_center);
+        self.frame.set_fill_color(&Color::TRANSPARENT);
+        self.frame.set_outline_color(&Color::BLACK);
+        self.frame.set_outline_thickness(thickness / 2.0);
+
+        let half = frame_size / 2.0;
+        self.cross.clear();
+        if unit.kind() == UnitType::Infantry || unit.kind() == UnitType::Mechanized {
+            let corners = [
+                (-half.x, -half.y),
+                (half.x, half.y),
+                (half.x, -half.y),
+                (-half.x, half.y),
+            ];
+            for (x, y) in corners.iter() {
+                self.cross.append(&Vertex::with_pos_color(
+                    frame_center + Vector2f { x: *x, y: *y },
+                    Color::BLACK,
+                ));
+            }
+        }
+
+        self.oval = if unit.kind() == UnitType::Armoured || unit.kind() == UnitType::Mechanized {
+            const POINTS: u32 = 24;
+            let mut oval = ConvexShape::new(POINTS);
+            for i in 0..POINTS {
+                let angle = 2.0 * std::f32::consts::PI * i as f32 / POINTS as f32;
+                oval.set_point(
+                    i,
+                    Vector2f {
+                        x: half.x * 0.7 * angle.cos(),
+                        y: half.y * 0.6 * angle.sin(),
+                    },
+                );
+            }
+            oval.set_position(frame_center);
+            oval.set_fill_color(&Color::TRANSPARENT);
+            oval.set_outline_color(&Color::BLACK);
+            oval.set_outline_thickness(thickness / 2.0);
+            Some(oval)
+        } else {
+            None
+        };
+
+        self.kind = unit.kind();
+        self.name = unit.name().to_owned();
+        self.moving_pts = format!("{}/{}", mc.moving_pts(), mc.default_moving_pts());
+    }
+
+    /// Draws the counter, with the art from the theme in place of the symbol if it has one.
+    pub fn draw(&self, target: &mut dyn RenderTarget, font: &Font, theme: &Theme) {
+        target.draw(&self.shape);
+        match theme.unit_texture(self.kind) {
+            Some(texture) => {
+                let frame = self.frame.global_bounds();
+                let size = texture.size();
+                let mut sprite = Sprite::with_texture(texture);
+                sprite.set_scale((
+                    frame.width / size.x.max(1) as f32,
+                    frame.height / size.y.max(1) as f32,
+                ));
+                sprite.set_position((frame.left, frame.top));
+                target.draw(&sprite);
+            }
+            None => {
+                target.draw(&self.frame);
+                if let Some(oval) = &self.oval {
+                    target.draw(oval);
+                }
+                target.draw(&self.cross);
+            }
+        }
+
+        let size = self.layout.borrow().size;
+        let position = self.shape.position();
+        let labels = [
+            (&self.name, -size.y * 0.36),
+            (&self.moving_pts, size.y * 0.3),
+        ];
+        for (label, offset) in labels.iter() {
+            let mut text = Text::new(label, font, (size.y * 0.16) as u32);
+            text.set_fill_color(&Color::BLACK);
+            let bounds = text.local_bounds();
+            text.set_origin((
+                bounds.left + bounds.width / 2.0,
+                bounds.top + bounds.height / 2.0,
+            ));
+            text.set_position(position + Vector2f { x: 0.0, y: *offset });
+            target.draw(&text);
+        }
     }
 
     pub fn fill_shape(&self) -> &RectangleShape<'a> {
@@ -67,3 +201,76 @@ impl<'a> Token<'a> {
         self.shape.global_bounds().contains(position)
     }
 }
+
+#[derive(Debug, Default)]
+pub struct Tokens<'a> {
+    pub layout: Rc<RefCell<hexagons::Layout>>,
+    pub tokens: HashMap<i32, Token<'a>>,
+    //unit ids, bottom of the stacks first
+    draw_order: Vec<i32>,
+}
+
+impl<'a> Tokens<'a> {
+    pub fn new(
+        layout: Rc<RefCell<hexagons::Layout>>,
+        units: &UnitSet,
+        moving: &MovingSystem,
+    ) -> Self {
+        let mut res = Tokens {
+            layout,
+            tokens: HashMap::new(),
+            draw_order: Vec::new(),
+        };
+        res.update(units, moving);
+        res
+    }
+
+    pub fn update(&mut self, units: &UnitSet, moving: &MovingSystem) -> &mut Self {
+        let mut stacks: HashMap<hexagons::HexCoordinates, Vec<i32>> = HashMap::new();
+        for (id, mc) in moving.components.iter() {
+            if let Some(occ) = mc.occupation {
+                stacks.entry(occ).or_default().push(*id);
+            }
+        }
+
+        self.tokens.clear();
+        let mut order = Vec::new();
+        for stack in stacks.values_mut() {
+            stack.sort();
+            for (level, id) in stack.iter().enumerate() {
+                if let Some(unit) = units.units().get(id) {
+                    let token =
+                        Token::new(self.layout.clone(), unit, &moving.components[id], level);
+                    self.tokens.insert(*id, token);
+                    order.push((level, *id));
+                }
+            }
+        }
+        order.sort();
+        self.draw_order = order.into_iter().map(|(_, id)| id).collect();
+        self
+    }
+
+    pub fn draw(&self, target: &mut dyn RenderTarget, font: &Font, theme: &Theme) {
+        for id in self.draw_order.iter() {
+            self.tokens[id].draw(target, font, theme);
+        }
+    }
+
+    /// Keeps only the tokens of the units the player is allowed to see.
+    pub fn retain<F: Fn(i32) -> bool>(&mut self, shown: F) -> &mut Self {
+        self.tokens.retain(|id, _| shown(*id));
+        let tokens = &self.tokens;
+        self.draw_order.retain(|id| tokens.contains_key(id));
+        self
+    }
+
+    /// Returns the id of the topmost unit, whose token contains the point.
+    pub fn unit_at(&self, position: Vector2f) -> Option<i32> {
+        self.draw_order
+            .iter()
+            .rev()
+            .find(|id| self.tokens[id].contains(position))
+            .cloned()
+    }
+}
@@ -0,0 +1,129 @@
+/// Action applied to a target, that knows how to take itself back.
+pub trait Command: Clone {
+    type Target;
+    /// Whatever is needed to restore the target to the state before the command.
+    type Undo;
+
+    fn apply(&self, target: &mut Self::Target) -> Result<Self::Undo, &'static str>;
+
+    fn undo(&self, target: &mut Self::Target, undo: Self::Undo);
+
+    /// Commands that reveal something new, like results of combat, can't be taken back.
+    /// Executing them clears the history. What the command did is told by its undo.
+    fn is_checkpoint(&self, _undo: &Self::Undo) -> bool {
+        false
+    }
+}
+
+/// Commands executed so far, that can be undone and redone in order.
+pub struct History<C: Command> {
+    done: Vec<(C, C::Undo)>,
+    undone: Vec<C>,
+}
+
+impl<C: Command> History<C> {
+    pub fn new() -> Self {
+        History {
+            done: Vec::new(),
+            undone: Vec::new(),
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Applies the command, a new command can't be followed by the ones undone before it.
+    pub fn execute(&mut self, command: C, target: &mut C::Target) -> Result<(), &'static str> {
+        let undo = command.apply(target)?;
+        self.undone.clear();
+        if command.is_checkpoint(&undo) {
+            self.done.clear();
+        } else {
+            self.done.push((command, undo));
+        }
+        Ok(())
+    }
+
+    /// Takes back the last command, returning it.
+    pub fn undo(&mut self, target: &mut C::Target) -> Result<C, &'static str> {
+        let (command, undo) = self.done.pop().ok_or("Nothing to undo.")?;
+        command.undo(target, undo);
+        self.undone.push(command.clone());
+        Ok(command)
+    }
+
+    /// Applies the last undone command again, returning it.
+    pub fn redo(&mut self, target: &mut C::Target) -> Result<C, &'static str> {
+        let command = self.undone.pop().ok_or("Nothing to redo.")?;
+        match command.apply(target) {
+            Ok(undo) => {
+                self.done.push((command.clone(), undo));
+                Ok(command)
+            }
+            Err(err) => {
+                self.undone.push(command);
+                Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //adds the number to the total, zero can't be taken back
+    #[derive(Debug, Clone)]
+    struct Add(i32);
+
+    impl Command for Add {
+        type Target = i32;
+        type Undo = i32;
+
+        fn apply(&self, total: &mut i32) -> Result<i32, &'static str> {
+            if self.0 < 0 {
+                return Err("Negative number.");
+            }
+            let old = *total;
+            *total += self.0;
+            Ok(old)
+        }
+
+        fn undo(&self, total: &mut i32, old: i32) {
+            *total = old;
+        }
+
+        fn is_checkpoint(&self, _old: &i32) -> bool {
+            self.0 == 0
+        }
+    }
+
+    #[test]
+    fn undoing_and_redoing() {
+        let mut total = 0;
+        let mut history = History::new();
+        assert!(history.undo(&mut total).is_err());
+        history.execute(Add(1), &mut total).unwrap();
+        history.execute(Add(2), &mut total).unwrap();
+        assert!(history.execute(Add(-1), &mut total).is_err());
+        assert_eq!(total, 3);
+
+        assert_eq!(history.undo(&mut total).unwrap().0, 2);
+        assert_eq!(history.undo(&mut total).unwrap().0, 1);
+        assert_eq!(total, 0);
+        assert!(!history.can_undo());
+        assert_eq!(history.redo(&mut total).unwrap().0, 1);
+        assert_eq!(total, 1);
+
+        history.execute(Add(4), &mut total).unwrap();
+        assert!(!history.can_redo());
+        history.execute(Add(0), &mut total).unwrap();
+        assert!(!history.can_undo());
+        assert_eq!(total, 5);
+    }
+}
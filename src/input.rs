@@ -0,0 +1,595 @@
+extern crate log;
+extern crate sfml;
+
+use sfml::system::Vector2i;
+use sfml::window::{mouse, Event, Key};
+
+use std::collections::HashSet;
+use std::fs;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    ZoomIn,
+    ZoomOut,
+    DragPan,
+    CenterOnSelection,
+    FlattenHexes,
+    StretchHexes,
+    NextTheme,
+    Snapshot,
+    DangerZones,
+    BrushPlain,
+    BrushForest,
+    BrushHill,
+    ToggleHex,
+    ToggleRiver,
+    RotateMap,
+    MirrorMap,
+    Select,
+    Deselect,
+    EndTurn,
+    Undo,
+    Redo,
+    Quit,
+}
+
+const ACTION_NAMES: [(&str, Action); 26] = [
+    ("pan_left", Action::PanLeft),
+    ("pan_right", Action::PanRight),
+    ("pan_up", Action::PanUp),
+    ("pan_down", Action::PanDown),
+    ("zoom_in", Action::ZoomIn),
+    ("zoom_out", Action::ZoomOut),
+    ("drag_pan", Action::DragPan),
+    ("center_on_selection", Action::CenterOnSelection),
+    ("flatten_hexes", Action::FlattenHexes),
+    ("stretch_hexes", Action::StretchHexes),
+    ("next_theme", Action::NextTheme),
+    ("snapshot", Action::Snapshot),
+    ("danger_zones", Action::DangerZones),
+    ("brush_plain", Action::BrushPlain),
+    ("brush_forest", Action::BrushForest),
+    ("brush_hill", Action::BrushHill),
+    ("toggle_hex", Action::ToggleHex),
+    ("toggle_river", Action::ToggleRiver),
+    ("rotate_map", Action::RotateMap),
+    ("mirror_map", Action::MirrorMap),
+    ("select", Action::Select),
+    ("deselect", Action::Deselect),
+    ("end_turn", Action::EndTurn),
+    ("undo", Action::Undo),
+    ("redo", Action::Redo),
+    ("quit", Action::Quit),
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Trigger {
+    Key {
+        code: Key,
+        ctrl: bool,
+        shift: bool,
+        alt: bool,
+    },
+    Button(mouse::Button),
+    WheelUp,
+    WheelDown,
+}
+
+impl Trigger {
+    pub fn key(code: Key) -> Self {
+        Trigger::Key {
+            code,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        }
+    }
+
+    /// Parses names like "A", "Ctrl+Z", "MouseLeft" or "WheelUp".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "MouseLeft" => return Some(Trigger::Button(mouse::Button::Left)),
+            "MouseRight" => return Some(Trigger::Button(mouse::Button::Right)),
+            "MouseMiddle" => return Some(Trigger::Button(mouse::Button::Middle)),
+            "WheelUp" => return Some(Trigger::WheelUp),
+            "WheelDown" => return Some(Trigger::WheelDown),
+            _ => {}
+        }
+
+        let (mut ctrl, mut shift, mut alt) = (false, false, false);
+        let mut parts: Vec<_> = name.split('+').map(str::trim).collect();
+        let key_name = parts.pop()?;
+        for modifier in parts {
+            match modifier {
+                "Ctrl" => ctrl = true,
+                "Shift" => shift = true,
+                "Alt" => alt = true,
+                _ => return None,
+            }
+        }
+
+        let code = KEY_NAMES
+            .iter()
+            .find(|(n, _)| *n == key_name)
+            .map(|(_, k)| *k)?;
+        Some(Trigger::Key {
+            code,
+            ctrl,
+            shift,
+            alt,
+        })
+    }
+
+    /// Returns the trigger of the event, if the event can be bound to an action.
+    pub fn from_event(event: &Event) -> Option<Self> {
+        match *event {
+            Event::KeyPressed {
+                code,
+                ctrl,
+                shift,
+                alt,
+                ..
+            } => Some(Trigger::Key {
+                code,
+                ctrl,
+                shift,
+                alt,
+            }),
+            Event::MouseButtonPressed { button, .. } => Some(Trigger::Button(button)),
+            Event::MouseWheelScrolled { delta, .. } if delta > 0.0 => Some(Trigger::WheelUp),
+            Event::MouseWheelScrolled { delta, .. } if delta < 0.0 => Some(Trigger::WheelDown),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bindings {
+    bindings: Vec<(Trigger, Action)>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let mut bindings = Bindings::new();
+        bindings
+            .bind(Trigger::key(Key::Left), Action::PanLeft)
+            .bind(Trigger::key(Key::Right), Action::PanRight)
+            .bind(Trigger::key(Key::Up), Action::PanUp)
+            .bind(Trigger::key(Key::Down), Action::PanDown)
+            .bind(Trigger::WheelUp, Action::ZoomIn)
+            .bind(Trigger::WheelDown, Action::ZoomOut)
+            .bind(Trigger::Button(mouse::Button::Middle), Action::DragPan)
+            .bind(Trigger::key(Key::C), Action::CenterOnSelection)
+            .bind(Trigger::key(Key::W), Action::FlattenHexes)
+            .bind(Trigger::key(Key::S), Action::StretchHexes)
+            .bind(Trigger::key(Key::T), Action::NextTheme)
+            .bind(Trigger::key(Key::F12), Action::Snapshot)
+            .bind(Trigger::key(Key::X), Action::DangerZones)
+            .bind(Trigger::key(Key::Num1), Action::BrushPlain)
+            .bind(Trigger::key(Key::Num2), Action::BrushForest)
+            .bind(Trigger::key(Key::Num3), Action::BrushHill)
+            .bind(Trigger::key(Key::Delete), Action::ToggleHex)
+            .bind(Trigger::key(Key::R), Action::ToggleRiver)
+            .bind(Trigger::key(Key::Q), Action::RotateMap)
+            .bind(Trigger::key(Key::M), Action::MirrorMap)
+            .bind(Trigger::Button(mouse::Button::Left), Action::Select)
+            .bind(Trigger::Button(mouse::Button::Right), Action::Deselect)
+            .bind(Trigger::key(Key::Return), Action::EndTurn)
+            .bind(
+                Trigger::Key {
+                    code: Key::Z,
+                    ctrl: true,
+                    shift: false,
+                    alt: false,
+                },
+                Action::Undo,
+            )
+            .bind(
+                Trigger::Key {
+                    code: Key::Y,
+                    ctrl: true,
+                    shift: false,
+                    alt: false,
+                },
+                Action::Redo,
+            )
+            .bind(Trigger::key(Key::Escape), Action::Quit);
+        bindings
+    }
+}
+
+impl Bindings {
+    pub fn new() -> Self {
+        Bindings {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Binds the trigger to the action, replacing the previous binding of the trigger.
+    pub fn bind(&mut self, trigger: Trigger, action: Action) -> &mut Self {
+        self.bindings.retain(|(t, _)| *t != trigger);
+        self.bindings.push((trigger, action));
+        self
+    }
+
+    pub fn action(&self, trigger: &Trigger) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(t, _)| t == trigger)
+            .map(|(_, a)| *a)
+    }
+
+    /// Parses lines in the form "action = trigger, trigger", "#" starts a comment.
+    pub fn parse(text: &str) -> Result<Self, &'static str> {
+        let mut bindings = Bindings::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let action_name = parts.next().unwrap().trim();
+            let triggers = parts.next().ok_or_else(|| {
+                error!("Missing '=' in key bindings, line {}.", number + 1);
+                "Invalid key bindings."
+            })?;
+
+            let action = ACTION_NAMES
+                .iter()
+                .find(|(n, _)| *n == action_name)
+                .map(|(_, a)| *a)
+                .ok_or_else(|| {
+                    error!(
+                        "Unknown action {} in key bindings, line {}.",
+                        action_name,
+                        number + 1
+                    );
+                    "Invalid key bindings."
+                })?;
+
+            for trigger_name in triggers.split(',').map(str::trim) {
+                let trigger = Trigger::from_name(trigger_name).ok_or_else(|| {
+                    error!(
+                        "Unknown trigger {} in key bindings, line {}.",
+                        trigger_name,
+                        number + 1
+                    );
+                    "Invalid key bindings."
+                })?;
+                bindings.bind(trigger, action);
+            }
+        }
+        Ok(bindings)
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, &'static str> {
+        let text = fs::read_to_string(path).map_err(|_| "Failed to read key bindings file.")?;
+        Self::parse(&text)
+    }
+}
+
+/// Translates raw window events into actions and keeps track of the mouse.
+#[derive(Debug, Clone)]
+pub struct Input {
+    pub bindings: Bindings,
+    mouse_position: Vector2i,
+    mouse_inside: bool,
+    //actions whose keys or buttons are being held down
+    held: HashSet<Action>,
+}
+
+impl Input {
+    pub fn new(bindings: Bindings) -> Self {
+        Input {
+            bindings,
+            mouse_position: Vector2i::default(),
+            mouse_inside: true,
+            held: HashSet::new(),
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> Option<Action> {
+        match *event {
+            Event::MouseMoved { x, y }
+            | Event::MouseButtonPressed { x, y, .. }
+            | Event::MouseWheelScrolled { x, y, .. } => {
+                self.mouse_position = Vector2i { x, y };
+            }
+            Event::MouseEntered => self.mouse_inside = true,
+            Event::MouseLeft => self.mouse_inside = false,
+            Event::LostFocus => self.held.clear(),
+            Event::KeyReleased { code, .. } => {
+                //modifiers may be released first, so they are not compared
+                let bindings = &self.bindings.bindings;
+                self.held.retain(|held| {
+                    !bindings.iter().any(|(t, a)| match t {
+                        Trigger::Key { code: c, .. } => *c == code && a == held,
+                        _ => false,
+                    })
+                });
+            }
+            Event::MouseButtonReleased { button, .. } => {
+                if let Some(action) = self.bindings.action(&Trigger::Button(button)) {
+                    self.held.remove(&action);
+                }
+            }
+            _ => {}
+        }
+
+        let trigger = Trigger::from_event(event)?;
+        let action = self.bindings.action(&trigger);
+        if let Some(action) = action {
+            trace!("Input action: {:?}.", action);
+            if trigger != Trigger::WheelUp && trigger != Trigger::WheelDown {
+                self.held.insert(action);
+            }
+        }
+        action
+    }
+
+    /// Whether the key or button bound to the action is still pressed.
+    pub fn is_held(&self, action: Action) -> bool {
+        self.held.contains(&action)
+    }
+
+    pub fn is_mouse_inside(&self) -> bool {
+        self.mouse_inside
+    }
+
+    /// Last known position of the mouse cursor, in window pixels.
+    pub fn mouse_position(&self) -> Vector2i {
+        self.mouse_position
+    }
+}
+
+const KEY_NAMES: [(&str, Key); 82] = [
+    ("A", Key::A),
+    ("B", Key::B),
+    ("C", Key::C),
+    ("D", Key::D),
+    ("E", Key::E),
+    ("F", Key::F),
+    ("G", Key::G),
+    ("H", Key::H),
+    ("I", Key::I),
+    ("J", Key::J),
+    ("K", Key::K),
+    ("L", Key::L),
+    ("M", Key::M),
+    ("N", Key::N),
+    ("O", Key::O),
+    ("P", Key::P),
+    ("Q", Key::Q),
+    ("R", Key::R),
+    ("S", Key::S),
+    ("T", Key::T),
+    ("U", Key::U),
+    ("V", Key::V),
+    ("W", Key::W),
+    ("X", Key::X),
+    ("Y", Key::Y),
+    ("Z", Key::Z),
+    ("Num0", Key::Num0),
+    ("Num1", Key::Num1),
+    ("Num2", Key::Num2),
+    ("Num3", Key::Num3),
+    ("Num4", Key::Num4),
+    ("Num5", Key::Num5),
+    ("Num6", Key::Num6),
+    ("Num7", Key::Num7),
+    ("Num8", Key::Num8),
+    ("Num9", Key::Num9),
+    ("Escape", Key::Escape),
+    ("Space", Key::Space),
+    ("Return", Key::Return),
+    ("BackSpace", Key::BackSpace),
+    ("Tab", Key::Tab),
+    ("PageUp", Key::PageUp),
+    ("PageDown", Key::PageDown),
+    ("End", Key::End),
+    ("Home", Key::Home),
+    ("Insert", Key::Insert),
+    ("Delete", Key::Delete),
+    ("Add", Key::Add),
+    ("Subtract", Key::Subtract),
+    ("Multiply", Key::Multiply),
+    ("Divide", Key::Divide),
+    ("Left", Key::Left),
+    ("Right", Key::Right),
+    ("Up", Key::Up),
+    ("Down", Key::Down),
+    ("Comma", Key::Comma),
+    ("Period", Key::Period),
+    ("Equal", Key::Equal),
+    ("Dash", Key::Dash),
+    ("F1", Key::F1),
+    ("F2", Key::F2),
+    ("F3", Key::F3),
+    ("F4", Key::F4),
+    ("F5", Key::F5),
+    ("F6", Key::F6),
+    ("F7", Key::F7),
+    ("F8", Key::F8),
+    ("F9", Key::F9),
+    ("F10", Key::F10),
+    ("F11", Key::F11),
+    ("F12", Key::F12),
+    ("Pause", Key::Pause),
+    ("Numpad0", Key::Numpad0),
+    ("Numpad1", Key::Numpad1),
+    ("Numpad2", Key::Numpad2),
+    ("Numpad3", Key::Numpad3),
+    ("Numpad4", Key::Numpad4),
+    ("Numpad5", Key::Numpad5),
+    ("Numpad6", Key::Numpad6),
+    ("Numpad7", Key::Numpad7),
+    ("Numpad8", Key::Numpad8),
+    ("Numpad9", Key::Numpad9),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_event(code: Key, ctrl: bool) -> Event {
+        Event::KeyPressed {
+            code,
+            alt: false,
+            ctrl,
+            shift: false,
+            system: false,
+        }
+    }
+
+    #[test]
+    fn default_bindings() {
+        let mut input = Input::new(Bindings::default());
+        assert_eq!(
+            input.handle_event(&key_event(Key::Left, false)),
+            Some(Action::PanLeft)
+        );
+        assert_eq!(input.handle_event(&key_event(Key::Z, false)), None);
+        assert_eq!(
+            input.handle_event(&key_event(Key::Z, true)),
+            Some(Action::Undo)
+        );
+        assert_eq!(
+            input.handle_event(&key_event(Key::Y, true)),
+            Some(Action::Redo)
+        );
+        assert_eq!(
+            input.handle_event(&key_event(Key::X, false)),
+            Some(Action::DangerZones)
+        );
+        assert_eq!(input.handle_event(&Event::Closed), None);
+
+        let wheel = Event::MouseWheelScrolled {
+            wheel: mouse::Wheel::Vertical,
+            delta: -1.0,
+            x: 3,
+            y: 4,
+        };
+        assert_eq!(input.handle_event(&wheel), Some(Action::ZoomOut));
+        assert_eq!(input.mouse_position(), Vector2i { x: 3, y: 4 });
+    }
+
+    #[test]
+    fn mouse_tracking() {
+        let mut input = Input::new(Bindings::default());
+        assert_eq!(
+            input.handle_event(&Event::MouseMoved { x: 10, y: 20 }),
+            None
+        );
+        assert_eq!(input.mouse_position(), Vector2i { x: 10, y: 20 });
+
+        let click = Event::MouseButtonPressed {
+            button: mouse::Button::Left,
+            x: 15,
+            y: 25,
+        };
+        assert_eq!(input.handle_event(&click), Some(Action::Select));
+        assert_eq!(input.mouse_position(), Vector2i { x: 15, y: 25 });
+
+        assert!(input.is_mouse_inside());
+        input.handle_event(&Event::MouseLeft);
+        assert!(!input.is_mouse_inside());
+    }
+
+    #[test]
+    fn held_actions() {
+        let mut input = Input::new(Bindings::default());
+        input.handle_event(&key_event(Key::Left, false));
+        input.handle_event(&Event::MouseButtonPressed {
+            button: mouse::Button::Middle,
+            x: 0,
+            y: 0,
+        });
+        assert!(input.is_held(Action::PanLeft));
+        assert!(input.is_held(Action::DragPan));
+        assert!(!input.is_held(Action::PanRight));
+
+        input.handle_event(&Event::KeyReleased {
+            code: Key::Left,
+            alt: false,
+            ctrl: true,
+            shift: false,
+            system: false,
+        });
+        assert!(!input.is_held(Action::PanLeft));
+
+        input.handle_event(&Event::MouseButtonReleased {
+            button: mouse::Button::Middle,
+            x: 0,
+            y: 0,
+        });
+        assert!(!input.is_held(Action::DragPan));
+
+        input.handle_event(&key_event(Key::Up, false));
+        input.handle_event(&Event::LostFocus);
+        assert!(!input.is_held(Action::PanUp));
+    }
+
+    #[test]
+    fn parsing_bindings() {
+        let text = "
+            # comment
+            pan_left = A, Left
+            undo = Ctrl+Shift+U # trailing comment
+            select = MouseMiddle
+            zoom_in = WheelDown
+        ";
+        let bindings = Bindings::parse(text).unwrap();
+        let mut input = Input::new(bindings);
+
+        assert_eq!(
+            input.handle_event(&key_event(Key::A, false)),
+            Some(Action::PanLeft)
+        );
+        assert_eq!(
+            input.handle_event(&key_event(Key::Left, false)),
+            Some(Action::PanLeft)
+        );
+        assert_eq!(
+            input.bindings.action(&Trigger::Key {
+                code: Key::U,
+                ctrl: true,
+                shift: true,
+                alt: false
+            }),
+            Some(Action::Undo)
+        );
+        assert_eq!(
+            input
+                .bindings
+                .action(&Trigger::Button(mouse::Button::Middle)),
+            Some(Action::Select)
+        );
+        assert_eq!(
+            input.bindings.action(&Trigger::WheelDown),
+            Some(Action::ZoomIn)
+        );
+        assert_eq!(input.handle_event(&key_event(Key::Right, false)), None);
+
+        assert!(Bindings::parse("pan_left").is_err());
+        assert!(Bindings::parse("jump = Space").is_err());
+        assert!(Bindings::parse("quit = Hyper+Q").is_err());
+    }
+
+    #[test]
+    fn bindings_file() {
+        let bindings = Bindings::from_file("resources/config/bindings.cfg").unwrap();
+        assert_eq!(
+            bindings.action(&Trigger::key(Key::Escape)),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            bindings.action(&Trigger::key(Key::Delete)),
+            Some(Action::ToggleHex)
+        );
+    }
+}
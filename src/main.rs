@@ -1,21 +1,209 @@
 #[macro_use]
 extern crate log;
 extern crate chrono;
+extern crate getrandom;
+extern crate hmac;
+extern crate sha2;
 extern crate simplelog;
 
 use chrono::*;
 use simplelog::*;
 
+mod ai;
 mod game;
+mod geometry;
+#[cfg(feature = "sfml")]
+mod graphics;
+mod history;
+#[cfg(feature = "sfml")]
+mod input;
 mod maps;
 mod messaging;
-mod units;
-mod graphics;
+mod net;
+mod players;
+mod render;
+mod replay;
+mod rng;
+#[cfg(feature = "sfml")]
+mod states;
 mod systems;
+mod tui;
+mod turn_file;
+mod units;
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    //log messages would get mixed with the text of the terminal frontend
+    let level = match args.get(1).map(String::as_str) {
+        Some("--tui") | Some("--replay") | Some("--join") | Some("--mail") | Some("--ai-game") => {
+            LevelFilter::Warn
+        }
+        _ => LevelFilter::Trace,
+    };
     let mut log_config = Config::default();
     log_config.offset = *Local::now().offset();
-    TermLogger::init(LevelFilter::Trace, log_config, TerminalMode::Stdout).unwrap();
-    game::Game::new().run().unwrap();
+    TermLogger::init(level, log_config, TerminalMode::Stdout).unwrap();
+
+    match args.get(1).map(String::as_str) {
+        //renders a generated scenario without opening a window: --preview [path] [seed],
+        //--thumbnail renders the map alone, --svg exports the scenario to SVG
+        Some(option @ "--preview") | Some(option @ "--thumbnail") | Some(option @ "--svg") => {
+            let path = args.get(2).map_or("preview.png", String::as_str);
+            let seed = args.get(3).and_then(|s| s.parse().ok()).unwrap_or(0);
+            let scenario = game::Scenario::generated(seed);
+            match option {
+                "--preview" => render::scenario_preview(&scenario, 800).save_png(path),
+                "--thumbnail" => render::map_thumbnail(&scenario.map, 800).save_png(path),
+                _ => render::svg::SvgExport::new(Default::default(), &Default::default())
+                    .map(&scenario.map)
+                    .units(&scenario)
+                    .save(path),
+            }
+            .unwrap();
+        }
+        //plays a generated scenario in the terminal:
+        //--tui [seed] [--unicode] [--color] [--hotseat] [--ai side] [--difficulty level]
+        //[--record path]
+        Some("--tui") => {
+            let seed = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(0);
+            let scenario = game::Scenario::generated(seed);
+            let mut tui = if args.iter().any(|a| a == "--hotseat") {
+                tui::Tui::hot_seat(scenario, text_options(&args))
+            } else if let Some(side) = option_value(&args, "--ai") {
+                let ai = ai::Ai::new(net::parse_side(side).unwrap(), difficulty(&args));
+                tui::Tui::against(scenario, text_options(&args), ai)
+            } else {
+                tui::Tui::new(scenario, text_options(&args))
+            };
+            let stdin = std::io::stdin();
+            tui.run(stdin.lock(), &mut std::io::stdout()).unwrap();
+            if let Some(path) = option_value(&args, "--record") {
+                tui.replay().save(path).unwrap();
+            }
+        }
+        //watches a recorded game in the terminal: --replay path [--unicode] [--color]
+        Some("--replay") => {
+            let path = args.get(2).map_or("game.replay", String::as_str);
+            let mut playback = replay::Playback::new(replay::Replay::load(path).unwrap());
+            let stdin = std::io::stdin();
+            tui::playback::watch(
+                &mut playback,
+                text_options(&args),
+                stdin.lock(),
+                &mut std::io::stdout(),
+            )
+            .unwrap();
+        }
+        //lets the computer play against itself: --ai-game [seed] [--turns n]
+        //[--blue level] [--red level] [--record path]
+        Some("--ai-game") => {
+            let seed = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(0);
+            let turns = option_value(&args, "--turns").map_or(50, |n| n.parse().unwrap());
+            let level = |option| option_value(&args, option).unwrap_or("normal").parse().unwrap();
+            let blue = ai::Ai::new(units::unit::Side::Blue, level("--blue"));
+            let red = ai::Ai::new(units::unit::Side::Red, level("--red"));
+            let mut scenario = game::Scenario::generated(seed);
+            let replay = ai::play_game(&mut scenario, blue, red, turns).unwrap();
+            match scenario.winner() {
+                Some(side) => println!("{:?} side wins on turn {}.", side, scenario.turn.number()),
+                None => println!("No winner after {} turns.", turns),
+            }
+            if let Some(path) = option_value(&args, "--record") {
+                replay.save(path).unwrap();
+            }
+        }
+        //plays a turn of a game by mail, the game is kept in the save file:
+        //--mail save [seed] --key key [--import turn] [--export turn] [--unicode] [--color]
+        Some("--mail") => {
+            let path = args.get(2).map_or("game.replay", String::as_str);
+            let key = match option_value(&args, "--key") {
+                Some(key) if !key.is_empty() => key,
+                _ => {
+                    println!("Agree on a key with the other player and pass it with --key.");
+                    return;
+                }
+            };
+            let mut game = if std::path::Path::new(path).exists() {
+                replay::Replay::load(path).unwrap()
+            } else {
+                let seed = args.get(3).and_then(|s| s.parse().ok()).unwrap_or(0);
+                replay::Replay::new(&game::Scenario::generated(seed))
+            };
+            if let Some(turn_path) = option_value(&args, "--import") {
+                let turn = turn_file::TurnFile::load(turn_path, key).unwrap();
+                turn.import(&mut game).unwrap();
+                game.save(path).unwrap();
+                println!(
+                    "Imported the turn of the {:?} side, {} commands.",
+                    turn.side(),
+                    turn.commands().len()
+                );
+            }
+            let played = game.commands().len();
+            let mut tui = tui::Tui::by_mail(&game, text_options(&args)).unwrap();
+            let stdin = std::io::stdin();
+            tui.run(stdin.lock(), &mut std::io::stdout()).unwrap();
+            tui.replay().save(path).unwrap();
+            if tui.replay().commands().len() > played {
+                let turn_path = option_value(&args, "--export").unwrap_or("turn.txt");
+                match turn_file::TurnFile::export(tui.replay()) {
+                    Ok(turn) => {
+                        turn.save(turn_path, key).unwrap();
+                        println!("Send {} to the other player.", turn_path);
+                    }
+                    Err(err) => println!("{} The game is saved, continue it later.", err),
+                }
+            }
+        }
+        //serves a game to two players over TCP: --host port [seed] [--bind address],
+        //without a seed the test scenario is played, only local players join unless
+        //another address is given, e.g. --bind 0.0.0.0
+        Some("--host") => {
+            let port = args.get(2).map_or("7878", String::as_str);
+            let address = option_value(&args, "--bind").unwrap_or("127.0.0.1");
+            let scenario = match args.get(3).and_then(|s| s.parse().ok()) {
+                Some(seed) => game::Scenario::generated(seed),
+                None => game::Scenario::test(),
+            };
+            let host = net::host::Host::bind((address, port.parse().unwrap()), scenario).unwrap();
+            info!("Hosting game on {}.", host.local_addr().unwrap());
+            host.run().unwrap();
+        }
+        //plays a side of a hosted game in the terminal:
+        //--join address side [--unicode] [--color], e.g. --join 127.0.0.1:7878 red
+        Some("--join") => {
+            let address = args.get(2).map_or("127.0.0.1:7878", String::as_str);
+            let side = net::parse_side(args.get(3).map_or("blue", String::as_str)).unwrap();
+            let client = net::client::Client::connect(address, side).unwrap();
+            let stdin = std::io::stdin();
+            tui::remote::Remote::new(client, text_options(&args))
+                .run(stdin.lock(), &mut std::io::stdout())
+                .unwrap();
+        }
+        #[cfg(feature = "sfml")]
+        _ => game::Game::new().run().unwrap(),
+        #[cfg(not(feature = "sfml"))]
+        _ => error!(
+            "Built without the sfml feature, use --tui, --replay, --mail, --host, --join, \
+             --ai-game, --preview, --thumbnail or --svg."
+        ),
+    }
+}
+
+fn text_options(args: &[String]) -> tui::map_text::TextOptions {
+    tui::map_text::TextOptions {
+        unicode: args.iter().any(|a| a == "--unicode"),
+        color: args.iter().any(|a| a == "--color"),
+    }
+}
+
+//level of the computer player, "--difficulty easy|normal|hard"
+fn difficulty(args: &[String]) -> ai::Difficulty {
+    option_value(args, "--difficulty").map_or(Default::default(), |level| level.parse().unwrap())
+}
+
+//value following the option, e.g. the path in "--record path"
+fn option_value<'a>(args: &'a [String], option: &str) -> Option<&'a str> {
+    let index = args.iter().position(|a| a == option)?;
+    args.get(index + 1).map(String::as_str)
 }
@@ -1,10 +1,10 @@
 //Based on: https://www.redblobgames.com/grids/hexagons/
 
-extern crate sfml;
-
+use std::fmt;
 use std::ops::{Add, Neg, Sub};
+use std::str::FromStr;
 
-use sfml::system::Vector2f;
+use crate::geometry::Vector2f;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Hash)]
 pub struct HexCoordinates {
@@ -63,6 +63,59 @@ impl HexCoordinates {
     pub fn q(&self) -> i32 {
         self.x
     }
+
+    /// Rotates around the origin by steps of 60 degrees, positive steps follow
+    /// the order of DIRECTIONS.
+    pub fn rotated(&self, steps: i32) -> Self {
+        let mut res = *self;
+        for _ in 0..steps.rem_euclid(6) {
+            res = Self {
+                x: -res.y,
+                y: -res.z,
+                z: -res.x,
+            };
+        }
+        res
+    }
+
+    pub fn rotated_around(&self, center: Self, steps: i32) -> Self {
+        (*self - center).rotated(steps) + center
+    }
+
+    /// Mirrors across a line through the origin, keeping the given coordinate of every hex,
+    /// e.g. Axis::R flips the rows of pointy hexes sideways.
+    pub fn reflected(&self, axis: Axis) -> Self {
+        let (x, y, z) = (self.x, self.y, self.z);
+        match axis {
+            Axis::Q => Self { x, y: z, z: y },
+            Axis::R => Self { x: y, y: x, z },
+            Axis::S => Self { x: z, y, z: x },
+        }
+    }
+
+    pub fn reflected_around(&self, center: Self, axis: Axis) -> Self {
+        (*self - center).reflected(axis) + center
+    }
+
+    pub fn from_offset(offset: OffsetCoordinates, kind: OffsetKind) -> Self {
+        let OffsetCoordinates { col, row } = offset;
+        match kind {
+            OffsetKind::OddR => Self::new_axial(row, col - (row - (row & 1)) / 2),
+            OffsetKind::EvenR => Self::new_axial(row, col - (row + (row & 1)) / 2),
+            OffsetKind::OddQ => Self::new_axial(row - (col - (col & 1)) / 2, col),
+            OffsetKind::EvenQ => Self::new_axial(row - (col + (col & 1)) / 2, col),
+        }
+    }
+
+    pub fn to_offset(self, kind: OffsetKind) -> OffsetCoordinates {
+        let (p, q) = (self.p(), self.q());
+        match kind {
+            OffsetKind::OddR => OffsetCoordinates::new(q + (p - (p & 1)) / 2, p),
+            OffsetKind::EvenR => OffsetCoordinates::new(q + (p + (p & 1)) / 2, p),
+            OffsetKind::OddQ => OffsetCoordinates::new(q, p + (q - (q & 1)) / 2),
+            OffsetKind::EvenQ => OffsetCoordinates::new(q, p + (q + (q & 1)) / 2),
+        }
+    }
 }
 
 impl Add for HexCoordinates {
@@ -98,7 +151,105 @@ impl Sub for HexCoordinates {
     }
 }
 
-#[derive(Debug, Copy, Clone, Default)]
+/// Cube coordinate axes, q and r match the axial ones.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Axis {
+    Q,
+    R,
+    S,
+}
+
+/// Offset layouts shove every odd or even row (pointy hexes) or column (flat hexes).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum OffsetKind {
+    #[default]
+    OddR,
+    EvenR,
+    OddQ,
+    EvenQ,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Hash)]
+pub struct OffsetCoordinates {
+    pub col: i32,
+    pub row: i32,
+}
+
+impl OffsetCoordinates {
+    pub fn new(col: i32, row: i32) -> Self {
+        OffsetCoordinates { col, row }
+    }
+}
+
+fn column_letters(col: i32) -> String {
+    let mut n = col + 1;
+    let mut letters = Vec::new();
+    while n > 0 {
+        n -= 1;
+        letters.push((b'A' + (n % 26) as u8) as char);
+        n /= 26;
+    }
+    letters.iter().rev().collect()
+}
+
+/// Formats as "CCRR" (e.g. "0304") when both coordinates fit in two digits.
+/// The alternate form ("{:#}") uses a column letter starting from "A" = 0 (e.g. "C7").
+/// Coordinates that do not fit these forms are written as "col,row".
+impl fmt::Display for OffsetCoordinates {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.col < 0 || self.row < 0 {
+            write!(f, "{},{}", self.col, self.row)
+        } else if f.alternate() {
+            write!(f, "{}{}", column_letters(self.col), self.row)
+        } else if self.col < 100 && self.row < 100 {
+            write!(f, "{:02}{:02}", self.col, self.row)
+        } else {
+            write!(f, "{},{}", self.col, self.row)
+        }
+    }
+}
+
+impl FromStr for OffsetCoordinates {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const ERR: &str = "Invalid hex label.";
+        let s = s.trim();
+
+        if let Some(pos) = s.find(',') {
+            let col = s[..pos].trim().parse().map_err(|_| ERR)?;
+            let row = s[pos + 1..].trim().parse().map_err(|_| ERR)?;
+            return Ok(Self::new(col, row));
+        }
+
+        let letters_len = s.chars().take_while(|c| c.is_ascii_alphabetic()).count();
+        if letters_len > 0 {
+            let (letters, digits) = s.split_at(letters_len);
+            if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+                return Err(ERR);
+            }
+            let col = letters
+                .to_ascii_uppercase()
+                .bytes()
+                .try_fold(0i32, |acc, c| {
+                    acc.checked_mul(26)?.checked_add((c - b'A') as i32 + 1)
+                })
+                .ok_or(ERR)?;
+            return Ok(Self::new(col - 1, digits.parse().map_err(|_| ERR)?));
+        }
+
+        if s.len() < 2 || s.len() % 2 == 1 || !s.chars().all(|c| c.is_ascii_digit()) {
+            return Err(ERR);
+        }
+        let (col, row) = s.split_at(s.len() / 2);
+        Ok(Self::new(
+            col.parse().map_err(|_| ERR)?,
+            row.parse().map_err(|_| ERR)?,
+        ))
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
 pub struct Orientation {
     //orientation matrix (used in the conversion to pixel point), row major
     m: [f32; 4],
@@ -123,7 +274,7 @@ impl Orientation {
     };
 }
 
-#[derive(Debug, Copy, Clone, Default)]
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
 pub struct Layout {
     pub orientation: Orientation,
     pub size: Vector2f,
@@ -174,6 +325,53 @@ pub fn world_point_to_hex(point: Vector2f, layout: Layout) -> HexCoordinates {
     HexCoordinates::new_cube(rx as i32, ry as i32, rz as i32)
 }
 
+//squared distance between points, measured in units of the layout size
+fn layout_distance_sq(a: Vector2f, b: Vector2f, layout: Layout) -> f32 {
+    let d = (a - b) / layout.size;
+    d.x * d.x + d.y * d.y
+}
+
+fn nearest_hexes(
+    candidates: &[HexCoordinates],
+    point: Vector2f,
+    layout: Layout,
+) -> Vec<HexCoordinates> {
+    let mut sorted = candidates.to_vec();
+    sorted.sort_by(|a, b| {
+        let da = layout_distance_sq(hex_to_world_point(*a, layout), point, layout);
+        let db = layout_distance_sq(hex_to_world_point(*b, layout), point, layout);
+        da.total_cmp(&db)
+    });
+    sorted
+}
+
+/// Returns the pair of hexes sharing the hexside closest to the point.
+/// The first one is the hex containing the point.
+pub fn world_point_to_edge(point: Vector2f, layout: Layout) -> (HexCoordinates, HexCoordinates) {
+    let hex = world_point_to_hex(point, layout);
+    let neighbor = nearest_hexes(&hex.neighbors(), point, layout)[0];
+    (hex, neighbor)
+}
+
+/// Returns the three hexes meeting at the corner closest to the point.
+/// The first one is the hex containing the point.
+pub fn world_point_to_corner(
+    point: Vector2f,
+    layout: Layout,
+) -> (HexCoordinates, HexCoordinates, HexCoordinates) {
+    let hex = world_point_to_hex(point, layout);
+    let center = hex_to_world_point(hex, layout);
+    let corner = (0..6)
+        .map(|i| center + layout.corner_offset(i))
+        .min_by(|a, b| {
+            layout_distance_sq(*a, point, layout).total_cmp(&layout_distance_sq(*b, point, layout))
+        })
+        .unwrap();
+
+    let neighbors = nearest_hexes(&hex.neighbors(), corner, layout);
+    (hex, neighbors[0], neighbors[1])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -216,4 +414,126 @@ mod tests {
         assert_eq!(c1.distance_to(&c2), 2);
     }
 
+    #[test]
+    fn rotations() {
+        for i in 0..6 {
+            let dir = HexCoordinates::DIRECTIONS[i];
+            assert_eq!(dir.rotated(1), HexCoordinates::DIRECTIONS[(i + 1) % 6]);
+            assert_eq!(dir.rotated(-1), HexCoordinates::DIRECTIONS[(i + 5) % 6]);
+            assert_eq!(dir.rotated(6), dir);
+        }
+
+        let center = HexCoordinates::new_axial(2, -1);
+        let hex = HexCoordinates::new_axial(4, 1);
+        let rotated = hex.rotated_around(center, 2);
+        assert_eq!(rotated.distance_to(&center), hex.distance_to(&center));
+        assert_eq!(rotated.rotated_around(center, 4), hex);
+        assert_eq!(center.rotated_around(center, 1), center);
+    }
+
+    #[test]
+    fn reflections() {
+        let hex = HexCoordinates::new_cube(1, 2, -3);
+        assert_eq!(hex.reflected(Axis::Q), HexCoordinates::new_cube(1, -3, 2));
+        assert_eq!(hex.reflected(Axis::R), HexCoordinates::new_cube(2, 1, -3));
+        assert_eq!(hex.reflected(Axis::S), HexCoordinates::new_cube(-3, 2, 1));
+
+        let center = HexCoordinates::new_axial(-1, 3);
+        for axis in [Axis::Q, Axis::R, Axis::S].iter() {
+            let reflected = hex.reflected_around(center, *axis);
+            assert_eq!(reflected.distance_to(&center), hex.distance_to(&center));
+            assert_eq!(reflected.reflected_around(center, *axis), hex);
+        }
+    }
+
+    #[test]
+    fn offset_conversions() {
+        let hex = HexCoordinates::new_axial(1, 1);
+        assert_eq!(
+            hex.to_offset(OffsetKind::OddR),
+            OffsetCoordinates::new(1, 1)
+        );
+        assert_eq!(
+            hex.to_offset(OffsetKind::EvenR),
+            OffsetCoordinates::new(2, 1)
+        );
+
+        let hex = HexCoordinates::new_axial(-3, -1);
+        assert_eq!(
+            hex.to_offset(OffsetKind::OddR),
+            OffsetCoordinates::new(-3, -3)
+        );
+
+        let kinds = [
+            OffsetKind::OddR,
+            OffsetKind::EvenR,
+            OffsetKind::OddQ,
+            OffsetKind::EvenQ,
+        ];
+        for p in -5..=5 {
+            for q in -5..=5 {
+                let hex = HexCoordinates::new_axial(p, q);
+                for kind in kinds.iter() {
+                    let offset = hex.to_offset(*kind);
+                    assert_eq!(HexCoordinates::from_offset(offset, *kind), hex);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn offset_labels() {
+        assert_eq!(OffsetCoordinates::new(3, 4).to_string(), "0304");
+        assert_eq!(format!("{:#}", OffsetCoordinates::new(2, 7)), "C7");
+        assert_eq!(format!("{:#}", OffsetCoordinates::new(27, 1)), "AB1");
+        assert_eq!(OffsetCoordinates::new(-3, 4).to_string(), "-3,4");
+        assert_eq!(OffsetCoordinates::new(120, 4).to_string(), "120,4");
+
+        assert_eq!("0304".parse(), Ok(OffsetCoordinates::new(3, 4)));
+        assert_eq!("C7".parse(), Ok(OffsetCoordinates::new(2, 7)));
+        assert_eq!("ab1".parse(), Ok(OffsetCoordinates::new(27, 1)));
+        assert_eq!("-3, 4".parse(), Ok(OffsetCoordinates::new(-3, 4)));
+        assert!("030".parse::<OffsetCoordinates>().is_err());
+        assert!("C".parse::<OffsetCoordinates>().is_err());
+        assert!("x,1".parse::<OffsetCoordinates>().is_err());
+
+        for col in -3..30 {
+            for row in -3..30 {
+                let offset = OffsetCoordinates::new(col, row);
+                assert_eq!(offset.to_string().parse(), Ok(offset));
+                assert_eq!(format!("{:#}", offset).parse(), Ok(offset));
+            }
+        }
+    }
+
+    fn test_layout() -> Layout {
+        Layout {
+            orientation: Orientation::POINTY,
+            size: Vector2f { x: 10.0, y: 10.0 },
+            origin: Vector2f { x: 0.0, y: 0.0 },
+        }
+    }
+
+    #[test]
+    fn edge_picking() {
+        let layout = test_layout();
+        let (hex, neighbor) = world_point_to_edge(Vector2f { x: 7.0, y: 0.5 }, layout);
+        assert_eq!(hex, HexCoordinates::origin());
+        assert_eq!(neighbor, HexCoordinates::new_axial(0, 1));
+
+        let (hex, neighbor) = world_point_to_edge(Vector2f { x: 10.0, y: 0.0 }, layout);
+        assert_eq!(hex, HexCoordinates::new_axial(0, 1));
+        assert_eq!(neighbor, HexCoordinates::origin());
+    }
+
+    #[test]
+    fn corner_picking() {
+        let layout = test_layout();
+        let (hex, n1, n2) = world_point_to_corner(Vector2f { x: 8.0, y: 4.5 }, layout);
+        assert_eq!(hex, HexCoordinates::origin());
+
+        let neighbors = [n1, n2];
+        assert!(neighbors.contains(&HexCoordinates::new_axial(0, 1)));
+        assert!(neighbors.contains(&HexCoordinates::new_axial(1, 0)));
+    }
 }
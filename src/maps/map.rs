@@ -1,13 +1,18 @@
 extern crate log;
-extern crate sfml;
 
 use super::graph::*;
 use super::hexagons::*;
+use super::shapes;
 use super::types::*;
 
+use crate::history::Command;
+
 use std::collections::{HashMap, HashSet};
 
-#[derive(Debug, Clone, Copy)]
+//changes kept in the log of a map, enough for a long editing session
+const MAX_LOGGED_CHANGES: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct HexSite {
     coord: HexCoordinates,
     kind: Field,
@@ -27,7 +32,7 @@ impl HexSite {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct RiverSite {
     side1: HexCoordinates,
     side2: HexCoordinates,
@@ -56,13 +61,70 @@ impl RiverSite {
     }
 }
 
+/// Single modification of the map, recorded in its change log.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MapChange {
+    HexAdded(HexSite),
+    HexRemoved(HexSite),
+    HexChanged { old: HexSite, new: HexSite },
+    RiverAdded(RiverSite),
+    RiverRemoved(RiverSite),
+}
+
+/// Edit made in the editor, undone by reverting the changes it made to the map.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MapEdit {
+    Paint(HexCoordinates, Field),
+    /// Removes the hex, or adds it with the given terrain.
+    ToggleHex(HexCoordinates, Field),
+    /// Removes the river, or adds it with the given kind.
+    ToggleRiver(HexCoordinates, HexCoordinates, River),
+}
+
+impl Command for MapEdit {
+    type Target = Map;
+    type Undo = Vec<MapChange>;
+
+    fn apply(&self, map: &mut Map) -> Result<Vec<MapChange>, &'static str> {
+        let revision = map.revision();
+        match *self {
+            MapEdit::Paint(hex, kind) => map.set_hex_kind(hex, kind).map(|_| ())?,
+            MapEdit::ToggleHex(hex, _) if map.hex_at(hex).is_some() => {
+                map.remove_hex(hex).map(|_| ())?
+            }
+            MapEdit::ToggleHex(hex, kind) => map.insert_hex(HexSite::new(hex, kind)).map(|_| ())?,
+            MapEdit::ToggleRiver(side1, side2, _) if map.river_between(side1, side2).is_some() => {
+                map.remove_river(side1, side2).map(|_| ())?
+            }
+            MapEdit::ToggleRiver(side1, side2, kind) => {
+                let river = RiverSite::new(side1, side2, kind)?;
+                map.insert_river(river).map(|_| ())?
+            }
+        }
+        Ok(map.changes_since(revision).unwrap_or(&[]).to_vec())
+    }
+
+    fn undo(&self, map: &mut Map, changes: Vec<MapChange>) {
+        for change in changes.iter().rev() {
+            if let Err(err) = map.revert(change) {
+                warn!("Cannot revert {:?}: {}", change, err);
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct Map {
     graph: BidirectionalGraph<i32>,
     hexes: HashMap<i32, HexSite>,
     rivers: HashMap<i32, RiverSite>,
+    hex_ids: HashMap<HexCoordinates, i32>,
 
     current_free_id: i32,
+    //the revision is the number of changes made to the map so far,
+    //the log keeps the changes made since first_logged revision
+    changes: Vec<MapChange>,
+    first_logged: u64,
 }
 
 impl Map {
@@ -71,8 +133,51 @@ impl Map {
             graph: BidirectionalGraph::default(),
             hexes: HashMap::default(),
             rivers: HashMap::default(),
+            hex_ids: HashMap::default(),
             current_free_id: 0,
+            changes: Vec::new(),
+            first_logged: 0,
+        }
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.first_logged + self.changes.len() as u64
+    }
+
+    /// Returns changes made after the given revision, or None if they are no longer logged.
+    pub fn changes_since(&self, revision: u64) -> Option<&[MapChange]> {
+        if revision < self.first_logged || revision > self.revision() {
+            return None;
+        }
+        Some(&self.changes[(revision - self.first_logged) as usize..])
+    }
+
+    /// Clears the change log, the revision stays as it is.
+    pub fn forget_changes(&mut self) {
+        self.first_logged = self.revision();
+        self.changes.clear();
+    }
+
+    /// Makes the opposite change, which is recorded in the log like any other.
+    pub fn revert(&mut self, change: &MapChange) -> Result<&mut Self, &'static str> {
+        match *change {
+            MapChange::HexAdded(hex) => self.remove_hex(hex.coord),
+            MapChange::HexRemoved(hex) => self.insert_hex(hex),
+            MapChange::HexChanged { old, .. } => self.set_hex_kind(old.coord, old.kind),
+            MapChange::RiverAdded(river) => self.remove_river(river.side1, river.side2),
+            MapChange::RiverRemoved(river) => self.insert_river(river),
+        }
+    }
+
+    //consumers that fall behind the log rebuild from the whole map,
+    //so the oldest half is dropped once the log is full
+    fn log_change(&mut self, change: MapChange) {
+        if self.changes.len() >= MAX_LOGGED_CHANGES {
+            let dropped = MAX_LOGGED_CHANGES / 2;
+            self.changes.drain(..dropped);
+            self.first_logged += dropped as u64;
         }
+        self.changes.push(change);
     }
 
     pub fn hexes(&self) -> &HashMap<i32, HexSite> {
@@ -83,66 +188,97 @@ impl Map {
         &self.rivers
     }
 
+    pub fn hex_at(&self, coord: HexCoordinates) -> Option<&HexSite> {
+        self.hexes.get(self.hex_ids.get(&coord)?)
+    }
+
+    /// Returns the river flowing along the hexside shared by the two hexes, if there is any.
+    pub fn river_between(
+        &self,
+        side1: HexCoordinates,
+        side2: HexCoordinates,
+    ) -> Option<&RiverSite> {
+        let graph = self.graph.get_map();
+        let neighbors1 = graph.get(self.hex_ids.get(&side1)?)?;
+        let neighbors2 = graph.get(self.hex_ids.get(&side2)?)?;
+        neighbors1
+            .intersection(neighbors2)
+            .find_map(|id| self.rivers.get(id))
+    }
+
     pub fn insert_hex(&mut self, hex: HexSite) -> Result<&mut Self, &'static str> {
-        if self
-            .hexes
-            .iter()
-            .find(|&(_, h)| {
-                if h.coord == hex.coord {
-                    return true;
-                } else {
-                    return false;
-                }
-            })
-            .is_some()
-        {
+        if self.hex_ids.contains_key(&hex.coord) {
             return Err("Map already contains such hex.");
         }
 
-        let neighbours = hex.coord.neighbors();
-
-        let found_neighbors: HashMap<_, _> = self
-            .hexes
-            .clone()
-            .into_iter()
-            .filter(|(id, h)| {
-                if neighbours.contains(&h.coord) {
-                    return true;
-                } else {
-                    return false;
-                }
-            })
+        let found_neighbors: HashSet<_> = hex
+            .coord
+            .neighbors()
+            .iter()
+            .filter_map(|n| self.hex_ids.get(n))
+            .cloned()
             .collect();
 
         self.hexes.insert(self.current_free_id, hex);
+        self.hex_ids.insert(hex.coord, self.current_free_id);
 
-        self.graph.insert_node(
-            self.current_free_id,
-            found_neighbors.keys().cloned().collect(),
-        )?;
+        self.graph
+            .insert_node(self.current_free_id, found_neighbors)?;
         self.current_free_id += 1;
+        self.log_change(MapChange::HexAdded(hex));
         Ok(self)
     }
 
-    pub fn insert_river(&mut self, river: RiverSite) -> Result<&mut Self, &'static str> {
-        let found_hexes: HashMap<_, _> = self
-            .hexes
+    pub fn set_hex_kind(
+        &mut self,
+        coord: HexCoordinates,
+        kind: Field,
+    ) -> Result<&mut Self, &'static str> {
+        let id = self
+            .hex_ids
+            .get(&coord)
+            .ok_or("Map doesn't contain such hex.")?;
+        let site = self.hexes.get_mut(id).unwrap();
+        let old = *site;
+        site.kind = kind;
+        let new = *site;
+        self.log_change(MapChange::HexChanged { old, new });
+        Ok(self)
+    }
+
+    /// Removes the hex together with the rivers flowing along its sides.
+    pub fn remove_hex(&mut self, coord: HexCoordinates) -> Result<&mut Self, &'static str> {
+        let id = *self
+            .hex_ids
+            .get(&coord)
+            .ok_or("Map doesn't contain such hex.")?;
+
+        let mut river_ids: Vec<_> = self.graph.get_map()[&id]
             .iter()
-            .filter(|&(_, hex)| {
-                if hex.coord == river.side1 || hex.coord == river.side2 {
-                    return true;
-                } else {
-                    return false;
-                }
-            })
+            .filter(|n| self.rivers.contains_key(n))
+            .cloned()
             .collect();
-
-        if found_hexes.len() < 2 {
-            return Err("Map doesn't contains such hexes.");
-        } else if found_hexes.len() > 2 {
-            return Err("Map contains too much such hexes - they must double somehow.");
+        river_ids.sort();
+        for river_id in river_ids {
+            self.remove_river_node(river_id)?;
         }
 
+        self.graph.remove_node(id)?;
+        self.hex_ids.remove(&coord);
+        let hex = self.hexes.remove(&id).unwrap();
+        self.log_change(MapChange::HexRemoved(hex));
+        Ok(self)
+    }
+
+    pub fn insert_river(&mut self, river: RiverSite) -> Result<&mut Self, &'static str> {
+        let found_hexes: HashSet<_> = match (
+            self.hex_ids.get(&river.side1),
+            self.hex_ids.get(&river.side2),
+        ) {
+            (Some(id1), Some(id2)) => [*id1, *id2].iter().cloned().collect(),
+            _ => return Err("Map doesn't contains such hexes."),
+        };
+
         let found_rivs = self
             .rivers
             .values()
@@ -162,37 +298,78 @@ impl Map {
         }
 
         self.rivers.insert(self.current_free_id, river);
-        self.graph.insert_node(
-            self.current_free_id,
-            found_hexes.keys().cloned().cloned().collect(),
-        )?;
+        self.graph.insert_node(self.current_free_id, found_hexes)?;
         self.current_free_id += 1;
+        self.log_change(MapChange::RiverAdded(river));
         Ok(self)
     }
 
+    pub fn remove_river(
+        &mut self,
+        side1: HexCoordinates,
+        side2: HexCoordinates,
+    ) -> Result<&mut Self, &'static str> {
+        let graph = self.graph.get_map();
+        let id = match (self.hex_ids.get(&side1), self.hex_ids.get(&side2)) {
+            (Some(id1), Some(id2)) => graph[id1]
+                .intersection(&graph[id2])
+                .find(|id| self.rivers.contains_key(id))
+                .cloned(),
+            _ => None,
+        };
+        self.remove_river_node(id.ok_or("Map doesn't contain such river.")?)
+    }
+
+    fn remove_river_node(&mut self, id: i32) -> Result<&mut Self, &'static str> {
+        self.graph.remove_node(id)?;
+        let river = self.rivers.remove(&id).unwrap();
+        self.log_change(MapChange::RiverRemoved(river));
+        Ok(self)
+    }
+
+    /// Creates a copy of the map with every hex and river moved by the transformation.
+    /// Fails if the transformation doesn't keep neighboring hexes together.
+    pub fn transformed<F: Fn(HexCoordinates) -> HexCoordinates>(
+        &self,
+        transformation: F,
+    ) -> Result<Map, &'static str> {
+        let mut map = Map::new();
+
+        let mut hex_ids: Vec<_> = self.hexes.keys().collect();
+        hex_ids.sort();
+        for id in hex_ids {
+            let hex = self.hexes[id];
+            map.insert_hex(HexSite::new(transformation(hex.coord), hex.kind))?;
+        }
+
+        let mut river_ids: Vec<_> = self.rivers.keys().collect();
+        river_ids.sort();
+        for id in river_ids {
+            let river = self.rivers[id];
+            map.insert_river(RiverSite::new(
+                transformation(river.side1),
+                transformation(river.side2),
+                river.kind,
+            )?)?;
+        }
+        Ok(map)
+    }
+
+    pub fn rotated(&self, center: HexCoordinates, steps: i32) -> Map {
+        self.transformed(|hex| hex.rotated_around(center, steps))
+            .expect("Rotation must preserve the map.")
+    }
+
+    pub fn mirrored(&self, center: HexCoordinates, axis: Axis) -> Map {
+        self.transformed(|hex| hex.reflected_around(center, axis))
+            .expect("Reflection must preserve the map.")
+    }
+
     pub fn create_test_map() -> Self {
         debug!("Creating test map.");
-        let mut map = Map::new();
-        let mut offset_q = 6;
-        for p in -10..=10 {
-            if p % 2 == 0 {
-                offset_q -= 1;
-            }
-            for q in -10..=10 {
-                if q < 0 && p < 0 {
-                    map.insert_hex(HexSite {
-                        coord: HexCoordinates::new_axial(p, q + offset_q),
-                        kind: Field::Forest,
-                    })
-                    .unwrap();
-                } else {
-                    map.insert_hex(HexSite {
-                        coord: HexCoordinates::new_axial(p, q + offset_q),
-                        kind: Field::Plain,
-                    })
-                    .unwrap();
-                }
-            }
+        let mut map = shapes::rectangle_pointy(-10..=10, -10..=10, Field::Plain);
+        for coord in shapes::rectangle_pointy_coords(-10..=-1, -10..=-1) {
+            map.set_hex_kind(coord, Field::Forest).unwrap();
         }
 
         map.insert_river(
@@ -338,6 +515,7 @@ impl Map {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::history::History;
 
     #[test]
     fn test_simple_map_insertions() {
@@ -420,4 +598,174 @@ mod tests {
         assert_eq!(map.graph, graph);
     }
 
+    type Sites = (
+        HashSet<(HexCoordinates, Field)>,
+        HashSet<(HexCoordinates, HexCoordinates)>,
+    );
+
+    fn sites(map: &Map) -> Sites {
+        let hexes = map.hexes().values().map(|h| (h.coord, h.kind)).collect();
+        let rivers = map
+            .rivers()
+            .values()
+            .flat_map(|r| vec![(r.side1, r.side2), (r.side2, r.side1)])
+            .collect();
+        (hexes, rivers)
+    }
+
+    #[test]
+    fn test_map_transformations() {
+        let map = Map::create_test_map();
+        let center = HexCoordinates::new_axial(1, -2);
+
+        let rotated = map.rotated(center, 2);
+        assert_eq!(rotated.hexes().len(), map.hexes().len());
+        assert_eq!(rotated.rivers().len(), map.rivers().len());
+        assert_ne!(sites(&rotated), sites(&map));
+        assert_eq!(sites(&rotated.rotated(center, 4)), sites(&map));
+
+        let river = map.rivers().values().next().unwrap();
+        let rotated_sides = (
+            river.side1.rotated_around(center, 2),
+            river.side2.rotated_around(center, 2),
+        );
+        assert!(sites(&rotated).1.contains(&rotated_sides));
+
+        for axis in [Axis::Q, Axis::R, Axis::S].iter() {
+            let mirrored = map.mirrored(center, *axis);
+            assert_eq!(mirrored.rivers().len(), map.rivers().len());
+            assert_eq!(sites(&mirrored.mirrored(center, *axis)), sites(&map));
+        }
+
+        assert!(map.transformed(|hex| hex + hex).is_err());
+    }
+
+    #[test]
+    fn test_map_creation() {
+        let map = Map::create_test_map();
+        assert_eq!(map.hexes().len(), 21 * 21);
+        assert_eq!(map.rivers().len(), 15);
+    }
+
+    #[test]
+    fn test_map_queries() {
+        let map = Map::create_test_map();
+        let hex = HexCoordinates::new_axial(-5, -5);
+        assert_eq!(map.hex_at(hex).unwrap().kind(), Field::Forest);
+        assert!(map.hex_at(HexCoordinates::new_axial(-50, 0)).is_none());
+
+        let (side1, side2) = (
+            HexCoordinates::new_axial(0, 0),
+            HexCoordinates::new_axial(1, -1),
+        );
+        assert_eq!(
+            map.river_between(side1, side2).unwrap().kind(),
+            River::Stream
+        );
+        assert!(map.river_between(side2, side1).is_some());
+        assert!(map
+            .river_between(side1, HexCoordinates::new_axial(-1, 0))
+            .is_none());
+    }
+
+    #[test]
+    fn test_map_editing() {
+        let mut map = Map::create_test_map();
+        let hex = HexCoordinates::new_axial(2, 2);
+        let revision = map.revision();
+        map.set_hex_kind(hex, Field::Hill).unwrap();
+        assert_eq!(map.hex_at(hex).unwrap().kind(), Field::Hill);
+        assert_eq!(
+            map.changes_since(revision).unwrap(),
+            &[MapChange::HexChanged {
+                old: HexSite::new(hex, Field::Plain),
+                new: HexSite::new(hex, Field::Hill),
+            }]
+        );
+        assert!(map
+            .set_hex_kind(HexCoordinates::new_axial(-50, 0), Field::Hill)
+            .is_err());
+
+        let (side1, side2) = (
+            HexCoordinates::new_axial(0, 0),
+            HexCoordinates::new_axial(1, -1),
+        );
+        let revision = map.revision();
+        map.remove_river(side2, side1).unwrap();
+        assert!(map.river_between(side1, side2).is_none());
+        assert!(map.remove_river(side1, side2).is_err());
+
+        let rivers = map.rivers().len();
+        let river = RiverSite::new(side1, HexCoordinates::new_axial(0, -1), River::Stream).unwrap();
+        map.insert_river(river).unwrap();
+        map.remove_hex(side1).unwrap();
+        assert!(map.hex_at(side1).is_none());
+        assert_eq!(map.rivers().len(), rivers - 1);
+        assert_eq!(map.hexes().len(), 21 * 21 - 1);
+        assert_eq!(map.changes_since(revision).unwrap().len(), 5);
+        assert_eq!(
+            map.changes_since(revision).unwrap().last(),
+            Some(&MapChange::HexRemoved(HexSite::new(side1, Field::Plain)))
+        );
+
+        map.forget_changes();
+        assert!(map.changes_since(revision).is_none());
+        assert_eq!(map.changes_since(map.revision()).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_change_log_limit() {
+        let mut map = Map::new();
+        let hex = HexCoordinates::origin();
+        map.insert_hex(HexSite::new(hex, Field::Plain)).unwrap();
+        for i in 0..MAX_LOGGED_CHANGES {
+            let kind = if i % 2 == 0 {
+                Field::Forest
+            } else {
+                Field::Plain
+            };
+            map.set_hex_kind(hex, kind).unwrap();
+        }
+
+        assert_eq!(map.revision(), MAX_LOGGED_CHANGES as u64 + 1);
+        assert!(map.changes_since(0).is_none());
+        let recent = map.revision() - 10;
+        assert_eq!(map.changes_since(recent).unwrap().len(), 10);
+    }
+
+    #[test]
+    fn test_undoing_edits() {
+        let mut map = Map::create_test_map();
+        let hex = HexCoordinates::new_axial(-10, 8);
+        let next = HexCoordinates::new_axial(-9, 8);
+        let rivers = map.rivers().len();
+        let mut history = History::new();
+
+        history
+            .execute(MapEdit::Paint(hex, Field::Hill), &mut map)
+            .unwrap();
+        history
+            .execute(MapEdit::ToggleRiver(hex, next, River::Small), &mut map)
+            .unwrap();
+        history
+            .execute(MapEdit::ToggleHex(hex, Field::Plain), &mut map)
+            .unwrap();
+        assert!(map.hex_at(hex).is_none());
+        assert!(map.rivers().len() < rivers);
+        assert!(history
+            .execute(MapEdit::Paint(hex, Field::Forest), &mut map)
+            .is_err());
+
+        history.undo(&mut map).unwrap();
+        assert_eq!(map.hex_at(hex).unwrap().kind(), Field::Hill);
+        assert_eq!(map.rivers().len(), rivers + 1);
+        history.undo(&mut map).unwrap();
+        history.undo(&mut map).unwrap();
+        assert_eq!(map.hex_at(hex).unwrap().kind(), Field::Plain);
+        assert_eq!(map.rivers().len(), rivers);
+        assert!(map.river_between(hex, next).is_none());
+
+        history.redo(&mut map).unwrap();
+        assert_eq!(map.hex_at(hex).unwrap().kind(), Field::Hill);
+    }
 }
@@ -1,4 +1,6 @@
 pub mod graph;
 pub mod hexagons;
 pub mod map;
+pub mod shapes;
+pub mod terrain;
 pub mod types;
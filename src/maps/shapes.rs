@@ -0,0 +1,76 @@
+use super::hexagons::*;
+use super::map::*;
+use super::types::*;
+
+use std::ops::RangeInclusive;
+
+/// Rectangle of pointy hexes spanned by the given odd-r offset columns and rows.
+pub fn rectangle_pointy_coords(
+    cols: RangeInclusive<i32>,
+    rows: RangeInclusive<i32>,
+) -> Vec<HexCoordinates> {
+    rows.flat_map(|row| {
+        cols.clone().map(move |col| {
+            HexCoordinates::from_offset(OffsetCoordinates::new(col, row), OffsetKind::OddR)
+        })
+    })
+    .collect()
+}
+
+/// All hexes within the radius from the center.
+pub fn hexagon_coords(center: HexCoordinates, radius: i32) -> Vec<HexCoordinates> {
+    (-radius..=radius)
+        .flat_map(|p| {
+            let q_min = (-radius).max(-p - radius);
+            let q_max = radius.min(-p + radius);
+            (q_min..=q_max).map(move |q| center + HexCoordinates::new_axial(p, q))
+        })
+        .collect()
+}
+
+pub fn fill<I: IntoIterator<Item = HexCoordinates>>(coords: I, kind: Field) -> Map {
+    let mut map = Map::new();
+    for coord in coords {
+        map.insert_hex(HexSite::new(coord, kind))
+            .expect("Shape generator produced a duplicated hex.");
+    }
+    map
+}
+
+pub fn rectangle_pointy(cols: RangeInclusive<i32>, rows: RangeInclusive<i32>, kind: Field) -> Map {
+    fill(rectangle_pointy_coords(cols, rows), kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+
+    fn coords_of(map: &Map) -> HashSet<HexCoordinates> {
+        map.hexes().values().map(|h| *h.coord()).collect()
+    }
+
+    #[test]
+    fn rectangles() {
+        let map = rectangle_pointy(0..=4, -1..=2, Field::Plain);
+        assert_eq!(map.hexes().len(), 20);
+        for coord in coords_of(&map) {
+            let offset = coord.to_offset(OffsetKind::OddR);
+            assert!((0..=4).contains(&offset.col));
+            assert!((-1..=2).contains(&offset.row));
+        }
+    }
+
+    #[test]
+    fn hexagons() {
+        let center = HexCoordinates::new_axial(2, -3);
+        for radius in 0..5 {
+            let map = fill(hexagon_coords(center, radius), Field::Plain);
+            assert_eq!(map.hexes().len() as i32, 3 * radius * (radius + 1) + 1);
+            assert!(coords_of(&map)
+                .iter()
+                .all(|c| c.distance_to(&center) <= radius));
+        }
+    }
+}
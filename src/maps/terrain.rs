@@ -0,0 +1,295 @@
+//Procedural terrain based on seeded value noise, so the same seed always gives the same map.
+
+use super::hexagons::*;
+use super::map::*;
+use super::types::*;
+use crate::rng::{splitmix64, Rng};
+
+use std::collections::{HashMap, HashSet};
+
+const ELEVATION_CHANNEL: u64 = 1;
+const VEGETATION_CHANNEL: u64 = 2;
+
+//pseudorandom value from [0, 1) assigned to the lattice point
+fn lattice_value(seed: u64, channel: u64, x: i32, y: i32) -> f32 {
+    let point = (u64::from(x as u32) << 32) | u64::from(y as u32);
+    let hash = splitmix64(splitmix64(seed ^ channel.wrapping_mul(0xA24B_AED4_963E_E407)) ^ point);
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn value_noise(seed: u64, channel: u64, x: f32, y: f32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (smoothstep(x - x0), smoothstep(y - y0));
+    let (ix, iy) = (x0 as i32, y0 as i32);
+
+    let v00 = lattice_value(seed, channel, ix, iy);
+    let v10 = lattice_value(seed, channel, ix + 1, iy);
+    let v01 = lattice_value(seed, channel, ix, iy + 1);
+    let v11 = lattice_value(seed, channel, ix + 1, iy + 1);
+
+    let top = v00 + (v10 - v00) * tx;
+    let bottom = v01 + (v11 - v01) * tx;
+    top + (bottom - top) * ty
+}
+
+type Corner = (HexCoordinates, HexCoordinates, HexCoordinates);
+
+/// Parameters of the procedural map generator.
+#[derive(Debug, Clone, Copy)]
+pub struct TerrainGenerator {
+    pub seed: u64,
+    /// Approximate size of hills and forests, in hexes.
+    pub feature_size: f32,
+    /// Elevation above which hexes become hills, in the range [0, 1].
+    pub hill_level: f32,
+    /// Vegetation above which hexes become forests, in the range [0, 1].
+    pub forest_level: f32,
+    pub rivers: usize,
+    pub max_river_length: usize,
+}
+
+impl Default for TerrainGenerator {
+    fn default() -> Self {
+        TerrainGenerator {
+            seed: 0,
+            feature_size: 6.0,
+            hill_level: 0.68,
+            forest_level: 0.6,
+            rivers: 3,
+            max_river_length: 40,
+        }
+    }
+}
+
+impl TerrainGenerator {
+    pub fn new(seed: u64) -> Self {
+        TerrainGenerator {
+            seed,
+            ..Default::default()
+        }
+    }
+
+    fn noise(&self, channel: u64, hex: HexCoordinates) -> f32 {
+        //hex centers in units of the distance between neighbors
+        let x = (hex.q() as f32 + hex.p() as f32 / 2.0) / self.feature_size;
+        let y = (hex.p() as f32 * 3f32.sqrt() / 2.0) / self.feature_size;
+
+        let coarse = value_noise(self.seed, channel, x, y);
+        let fine = value_noise(self.seed, channel, 2.0 * x + 17.0, 2.0 * y + 31.0);
+        (2.0 * coarse + fine) / 3.0
+    }
+
+    pub fn elevation(&self, hex: HexCoordinates) -> f32 {
+        self.noise(ELEVATION_CHANNEL, hex)
+    }
+
+    pub fn field(&self, hex: HexCoordinates) -> Field {
+        if self.elevation(hex) > self.hill_level {
+            Field::Hill
+        } else if self.noise(VEGETATION_CHANNEL, hex) > self.forest_level {
+            Field::Forest
+        } else {
+            Field::Plain
+        }
+    }
+
+    /// Creates a map from the given hexes, e.g. generated by one of maps::shapes functions.
+    pub fn generate<I: IntoIterator<Item = HexCoordinates>>(&self, coords: I) -> Map {
+        let coords: Vec<_> = coords.into_iter().collect();
+        let mut map = Map::new();
+        for coord in coords.iter() {
+            map.insert_hex(HexSite::new(*coord, self.field(*coord)))
+                .expect("Generating map from duplicated hexes.");
+        }
+
+        for river in self.trace_rivers(&coords) {
+            for (side1, side2) in river {
+                map.insert_river(RiverSite::new(side1, side2, River::Stream).unwrap())
+                    .unwrap();
+            }
+        }
+        map
+    }
+
+    /// Returns the rivers as chains of adjacent hexsides, each flowing downhill
+    /// until it reaches a local minimum, another river or the border of the map.
+    pub fn trace_rivers(
+        &self,
+        coords: &[HexCoordinates],
+    ) -> Vec<Vec<(HexCoordinates, HexCoordinates)>> {
+        if coords.is_empty() {
+            return Vec::new();
+        }
+
+        let elevations: HashMap<_, _> = coords.iter().map(|c| (*c, self.elevation(*c))).collect();
+
+        //sources are picked from the highest quarter of the map
+        let mut sources = coords.to_vec();
+        sources.sort_by(|a, b| elevations[b].total_cmp(&elevations[a]));
+        sources.truncate((sources.len() / 4).max(1));
+
+        let mut rng = Rng::new(self.seed);
+        let mut used_edges = HashSet::new();
+        let mut rivers = Vec::new();
+
+        for _ in 0..self.rivers {
+            let source = sources[rng.below(sources.len())];
+            let direction = rng.below(6);
+            let corner = (
+                source,
+                source.neighbor(direction),
+                source.neighbor((direction + 1) % 6),
+            );
+
+            let river = self.trace_river(corner, &elevations, &mut used_edges);
+            if !river.is_empty() {
+                rivers.push(river);
+            }
+        }
+        rivers
+    }
+
+    fn trace_river(
+        &self,
+        mut corner: Corner,
+        elevations: &HashMap<HexCoordinates, f32>,
+        used_edges: &mut HashSet<(HexCoordinates, HexCoordinates)>,
+    ) -> Vec<(HexCoordinates, HexCoordinates)> {
+        //hexes outside of the map are lower than any other, so rivers flow out of the map
+        let corner_elevation = |c: &Corner| {
+            [c.0, c.1, c.2]
+                .iter()
+                .map(|h| elevations.get(h).cloned().unwrap_or(-1.0))
+                .sum::<f32>()
+                / 3.0
+        };
+
+        let mut river = Vec::new();
+        while river.len() < self.max_river_length {
+            let (a, b, c) = corner;
+            //moving along the hexside between two hexes of the corner
+            //leads to the corner shared with their other common neighbor
+            let next = [(a, b, c), (b, c, a), (c, a, b)]
+                .iter()
+                .filter(|(s1, s2, _)| elevations.contains_key(s1) && elevations.contains_key(s2))
+                .map(|&(s1, s2, other)| (s1, s2, s1 + s2 - other))
+                .min_by(|x, y| corner_elevation(x).total_cmp(&corner_elevation(y)));
+
+            let next = match next {
+                Some(n) if corner_elevation(&n) < corner_elevation(&corner) => n,
+                _ => break,
+            };
+
+            if used_edges.contains(&(next.0, next.1)) {
+                break;
+            }
+            used_edges.insert((next.0, next.1));
+            used_edges.insert((next.1, next.0));
+            river.push((next.0, next.1));
+
+            if !elevations.contains_key(&next.2) {
+                break;
+            }
+            corner = next;
+        }
+        river
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maps::shapes;
+
+    fn fields(map: &Map) -> HashMap<HexCoordinates, Field> {
+        map.hexes()
+            .values()
+            .map(|h| (*h.coord(), h.kind()))
+            .collect()
+    }
+
+    fn rivers(map: &Map) -> HashSet<(HexCoordinates, HexCoordinates)> {
+        map.rivers()
+            .values()
+            .map(|r| (*r.sides().0, *r.sides().1))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_same_map() {
+        let coords = shapes::rectangle_pointy_coords(0..=29, 0..=29);
+        let map1 = TerrainGenerator::new(42).generate(coords.clone());
+        let map2 = TerrainGenerator::new(42).generate(coords.clone());
+        let map3 = TerrainGenerator::new(43).generate(coords);
+
+        assert_eq!(fields(&map1), fields(&map2));
+        assert_eq!(rivers(&map1), rivers(&map2));
+        assert_ne!(fields(&map1), fields(&map3));
+    }
+
+    #[test]
+    fn varied_terrain() {
+        let map =
+            TerrainGenerator::new(7).generate(shapes::hexagon_coords(HexCoordinates::origin(), 20));
+        let kinds: HashSet<_> = fields(&map).values().cloned().collect();
+        assert!(kinds.contains(&Field::Plain));
+        assert!(kinds.contains(&Field::Forest));
+        assert!(kinds.contains(&Field::Hill));
+    }
+
+    #[test]
+    fn rivers_flow_downhill_along_hexsides() {
+        let generator = TerrainGenerator::new(1234);
+        let coords = shapes::rectangle_pointy_coords(0..=29, 0..=29);
+        let rivers = generator.trace_rivers(&coords);
+        assert!(!rivers.is_empty());
+
+        //same as in trace_river, hexes outside of the map are the lowest
+        let inside: HashSet<_> = coords.iter().cloned().collect();
+        let corner_elevation = |corner: &[HexCoordinates; 3]| {
+            corner
+                .iter()
+                .map(|h| {
+                    if inside.contains(h) {
+                        generator.elevation(*h)
+                    } else {
+                        -1.0
+                    }
+                })
+                .sum::<f32>()
+                / 3.0
+        };
+
+        for river in rivers.iter() {
+            let mut corners = Vec::new();
+            for (side1, side2) in river.iter() {
+                assert_eq!(side1.distance_to(side2), 1);
+            }
+            for pair in river.windows(2) {
+                let (a, b) = pair[0];
+                let (c, d) = pair[1];
+                let shared: Vec<_> = [a, b]
+                    .iter()
+                    .filter(|h| **h == c || **h == d)
+                    .cloned()
+                    .collect();
+                assert_eq!(shared.len(), 1);
+
+                let first_other = if a == shared[0] { b } else { a };
+                let second_other = if c == shared[0] { d } else { c };
+                assert_eq!(first_other.distance_to(&second_other), 1);
+
+                //the corner the river passes through between the two hexsides
+                corners.push([shared[0], first_other, second_other]);
+            }
+            let elevations: Vec<f32> = corners.iter().map(corner_elevation).collect();
+            for pair in elevations.windows(2) {
+                assert!(pair[1] <= pair[0], "River rises along {:?}.", river);
+            }
+        }
+    }
+}
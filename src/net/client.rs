@@ -0,0 +1,177 @@
+use std::io::{self, BufReader};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+
+use super::{receive, send, Packet};
+use crate::game::{GameCommand, Scenario};
+use crate::units::unit::Side;
+
+/// Player connected to a host, keeping a copy of the scenario updated by the host's deltas.
+pub struct Client {
+    address: SocketAddr,
+    side: Side,
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+    scenario: Scenario,
+    //number of deltas applied to the scenario
+    applied: usize,
+    //given by the host, lets the player rejoin its side
+    token: u64,
+}
+
+fn protocol_error(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason.to_owned())
+}
+
+type Connection = (TcpStream, BufReader<TcpStream>);
+
+//says hello and waits for the host to answer with the seed of the scenario and the token,
+//the reader is kept as it may have buffered the deltas following the welcome
+fn join(
+    address: SocketAddr,
+    side: Side,
+    applied: usize,
+    token: u64,
+) -> io::Result<(Connection, Option<u64>, u64)> {
+    let mut stream = TcpStream::connect(address)?;
+    send(
+        &mut stream,
+        &Packet::Hello {
+            side,
+            applied,
+            token,
+        },
+    )?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    match receive(&mut reader)? {
+        Packet::Welcome {
+            seed,
+            side: s,
+            token,
+        } if s == side => Ok(((stream, reader), seed, token)),
+        Packet::Rejected(reason) => Err(protocol_error(&reason)),
+        _ => Err(protocol_error("Unexpected answer to hello.")),
+    }
+}
+
+impl Client {
+    /// Joins the game hosted at the address, playing the side.
+    pub fn connect<A: ToSocketAddrs>(address: A, side: Side) -> io::Result<Self> {
+        let address = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| protocol_error("No address to connect to."))?;
+        let ((stream, reader), seed, token) = join(address, side, 0, 0)?;
+        let scenario = match seed {
+            Some(seed) => Scenario::generated(seed),
+            None => Scenario::test(),
+        };
+        Ok(Client {
+            address,
+            side,
+            stream,
+            reader,
+            scenario,
+            applied: 0,
+            token,
+        })
+    }
+
+    /// Connects again after the connection dropped, the host then sends the missed deltas.
+    pub fn reconnect(&mut self) -> io::Result<()> {
+        let ((stream, reader), seed, _) = join(self.address, self.side, self.applied, self.token)?;
+        if seed != self.scenario.seed {
+            return Err(protocol_error("Host plays another scenario."));
+        }
+        self.stream = stream;
+        self.reader = reader;
+        Ok(())
+    }
+
+    pub fn scenario(&self) -> &Scenario {
+        &self.scenario
+    }
+
+    pub fn side(&self) -> Side {
+        self.side
+    }
+
+    pub fn send(&mut self, command: GameCommand) -> io::Result<()> {
+        send(&mut self.stream, &Packet::Command(command))
+    }
+
+    /// Waits for the next packet from the host, deltas are applied to the scenario.
+    pub fn receive(&mut self) -> io::Result<Packet> {
+        loop {
+            let packet = receive(&mut self.reader)?;
+            if let Packet::Delta(ref delta) = packet {
+                if delta.seq <= self.applied {
+                    continue;
+                }
+                if delta.seq != self.applied + 1 {
+                    return Err(protocol_error("Missed a delta."));
+                }
+                delta.apply(&mut self.scenario).map_err(protocol_error)?;
+                self.applied = delta.seq;
+            }
+            return Ok(packet);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::host::Host;
+    use super::*;
+    use crate::maps::hexagons::HexCoordinates;
+    use std::net::Shutdown;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn playing_over_localhost() {
+        let host = Arc::new(Host::bind("127.0.0.1:0", Scenario::test()).unwrap());
+        let address = host.local_addr().unwrap();
+        let server = Arc::clone(&host);
+        thread::spawn(move || server.run());
+
+        let mut blue = Client::connect(address, Side::Blue).unwrap();
+        let mut red = Client::connect(address, Side::Red).unwrap();
+        let next_to_red = HexCoordinates::new_axial(-1, 3);
+
+        red.send(GameCommand::EndTurn).unwrap();
+        assert_eq!(
+            red.receive().unwrap(),
+            Packet::Rejected("Not your turn.".to_owned())
+        );
+
+        blue.send(GameCommand::Move(0, next_to_red)).unwrap();
+        assert!(matches!(blue.receive().unwrap(), Packet::Delta(_)));
+        assert!(matches!(red.receive().unwrap(), Packet::Delta(_)));
+        let occupation = |client: &Client| client.scenario().systems.moving.components[&0];
+        assert_eq!(occupation(&red).occupation, Some(next_to_red));
+
+        blue.send(GameCommand::Move(1, HexCoordinates::new_axial(9, 9)))
+            .unwrap();
+        assert!(matches!(blue.receive().unwrap(), Packet::Rejected(_)));
+
+        //nobody else takes a side being played
+        assert!(Client::connect(address, Side::Blue).is_err());
+
+        //red drops while blue plays on, and catches up after reconnecting
+        red.stream.shutdown(Shutdown::Both).unwrap();
+        assert!(Client::connect(address, Side::Red).is_err());
+        blue.send(GameCommand::NextPhase).unwrap();
+        blue.receive().unwrap();
+        blue.send(GameCommand::Attack(0, 2)).unwrap();
+        blue.receive().unwrap();
+        red.reconnect().unwrap();
+        red.receive().unwrap();
+        red.receive().unwrap();
+        assert_eq!(red.scenario().turn, blue.scenario().turn);
+        assert_eq!(
+            red.scenario().systems.moving.components,
+            blue.scenario().systems.moving.components
+        );
+        assert!(red.scenario().systems.combat.has_attacked(0));
+    }
+}
@@ -0,0 +1,220 @@
+extern crate log;
+
+use std::collections::HashMap;
+use std::io::{self, BufReader};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use super::{receive, send, Delta, Packet};
+use crate::game::{GameCommand, Scenario};
+use crate::history::Command;
+use crate::units::unit::Side;
+
+//sends to a stalled client fail after this long, so it can't hold up the game for the other
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+//token a player rejoins its side with, drawn from the OS random source so it can't be guessed
+fn new_token() -> Result<u64, &'static str> {
+    let mut bytes = [0; 8];
+    getrandom::getrandom(&mut bytes).map_err(|_| "Cannot draw a token for the side.")?;
+    Ok(u64::from_le_bytes(bytes).max(1))
+}
+
+type Recipients = Vec<(Side, TcpStream)>;
+
+struct HostState {
+    scenario: Scenario,
+    //every accepted command, so clients that join late or reconnect can catch up
+    deltas: Vec<Delta>,
+    //connections of the sides, numbered to tell a dropped one from the one replacing it
+    clients: HashMap<Side, (usize, TcpStream)>,
+    connections: usize,
+    tokens: HashMap<Side, u64>,
+}
+
+impl HostState {
+    //applies the command, the resulting delta is sent to the returned connections
+    fn play(
+        &mut self,
+        side: Side,
+        command: GameCommand,
+    ) -> Result<(Packet, Recipients), &'static str> {
+        if self.scenario.winner().is_some() {
+            return Err("The game is over.");
+        }
+        if self.scenario.turn.side() != side {
+            return Err("Not your turn.");
+        }
+        let before = self.scenario.systems.moving.components.clone();
+        command.apply(&mut self.scenario)?;
+        info!("{:?} side played {}.", side, command);
+
+        let delta = Delta::between(self.deltas.len() + 1, &before, &self.scenario);
+        let packet = Packet::Delta(delta.clone());
+        self.deltas.push(delta);
+        let streams = self
+            .clients
+            .iter()
+            .filter_map(|(side, (_, stream))| stream.try_clone().ok().map(|stream| (*side, stream)))
+            .collect();
+        Ok((packet, streams))
+    }
+
+    //the first player of a side gets a token, anyone else needs it to take the side over
+    fn admit(&mut self, side: Side, token: u64) -> Result<u64, &'static str> {
+        match self.tokens.get(&side) {
+            Some(known) if *known == token => Ok(token),
+            Some(_) => Err("The side is already played."),
+            None => {
+                let token = new_token()?;
+                self.tokens.insert(side, token);
+                Ok(token)
+            }
+        }
+    }
+}
+
+//the game state is locked only to change it, packets are sent under the second lock,
+//taken before the first is released, so every client gets the deltas in order
+struct Shared {
+    state: Mutex<HostState>,
+    sending: Mutex<()>,
+}
+
+/// Server running the authoritative copy of the scenario. Clients send their commands,
+/// the host checks them against the rules and sends the changes to both sides.
+pub struct Host {
+    listener: TcpListener,
+    shared: Arc<Shared>,
+}
+
+impl Host {
+    pub fn bind<A: ToSocketAddrs>(address: A, scenario: Scenario) -> io::Result<Self> {
+        Ok(Host {
+            listener: TcpListener::bind(address)?,
+            shared: Arc::new(Shared {
+                state: Mutex::new(HostState {
+                    scenario,
+                    deltas: Vec::new(),
+                    clients: HashMap::new(),
+                    connections: 0,
+                    tokens: HashMap::new(),
+                }),
+                sending: Mutex::new(()),
+            }),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts connections until the listener fails, each client is served by its own thread.
+    pub fn run(&self) -> io::Result<()> {
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            let shared = Arc::clone(&self.shared);
+            thread::spawn(move || {
+                if let Err(err) = serve(&shared, stream) {
+                    info!("Client disconnected: {}", err);
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+fn play(shared: &Shared, side: Side, command: GameCommand) -> Result<(), &'static str> {
+    let mut state = shared.state.lock().unwrap();
+    let (packet, streams) = state.play(side, command)?;
+    let _sending = shared.sending.lock().unwrap();
+    drop(state);
+    for (side, mut stream) in streams {
+        //the connection is dropped by its own thread, which fails to read too
+        if let Err(err) = send(&mut stream, &packet) {
+            warn!("Lost connection with the {:?} player: {}", side, err);
+        }
+    }
+    Ok(())
+}
+
+fn serve(shared: &Shared, mut stream: TcpStream) -> io::Result<()> {
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let (side, id) = match receive(&mut reader)? {
+        Packet::Hello {
+            side,
+            applied,
+            token,
+        } => {
+            let mut state = shared.state.lock().unwrap();
+            let token = match state.admit(side, token) {
+                Ok(token) => token,
+                Err(reason) => {
+                    drop(state);
+                    return send(&mut stream, &Packet::Rejected(reason.to_owned()));
+                }
+            };
+            let mut packets = vec![Packet::Welcome {
+                seed: state.scenario.seed,
+                side,
+                token,
+            }];
+            packets.extend(
+                state
+                    .deltas
+                    .iter()
+                    .skip(applied)
+                    .map(|delta| Packet::Delta(delta.clone())),
+            );
+            //a player who reconnects takes the place of the dropped connection
+            state.connections += 1;
+            let id = state.connections;
+            if let Some((_, old)) = state.clients.insert(side, (id, stream.try_clone()?)) {
+                let _ = old.shutdown(Shutdown::Both);
+            }
+            let _sending = shared.sending.lock().unwrap();
+            drop(state);
+            for packet in packets.iter() {
+                send(&mut stream, packet)?;
+            }
+            info!("{:?} player joined from {}.", side, stream.peer_addr()?);
+            (side, id)
+        }
+        _ => {
+            return send(
+                &mut stream,
+                &Packet::Rejected("Say hello first.".to_owned()),
+            )
+        }
+    };
+
+    let res = serve_commands(shared, side, &mut stream, &mut reader);
+    let mut state = shared.state.lock().unwrap();
+    if state.clients.get(&side).map(|(current, _)| *current) == Some(id) {
+        state.clients.remove(&side);
+    }
+    res
+}
+
+fn serve_commands(
+    shared: &Shared,
+    side: Side,
+    stream: &mut TcpStream,
+    reader: &mut BufReader<TcpStream>,
+) -> io::Result<()> {
+    loop {
+        let res = match receive(reader) {
+            Ok(Packet::Command(command)) => play(shared, side, command),
+            Ok(_) => Err("Only commands are expected."),
+            Err(ref err) if err.kind() == io::ErrorKind::InvalidData => Err("Damaged packet."),
+            Err(err) => return Err(err),
+        };
+        if let Err(reason) = res {
+            let _sending = shared.sending.lock().unwrap();
+            send(stream, &Packet::Rejected(reason.to_owned()))?;
+        }
+    }
+}
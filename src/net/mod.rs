@@ -0,0 +1,360 @@
+//! Games over TCP. The host applies the rules and sends the resulting changes,
+//! so the clients never have to agree on anything but the starting scenario.
+//! Every packet is a line of text "CT<version> <checksum> <payload>".
+
+pub mod client;
+pub mod host;
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+use crate::game::{GameCommand, Phase, Scenario, Turn};
+use crate::maps::hexagons::{HexCoordinates, OffsetKind};
+use crate::systems::components::MovingComponent;
+use crate::units::unit::Side;
+
+pub const VERSION: u32 = 2;
+
+const COORDS_KIND: OffsetKind = OffsetKind::OddR;
+
+/// State of a unit after a command, hex it occupies and moving points left.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UnitState {
+    pub id: i32,
+    pub occupation: Option<HexCoordinates>,
+    pub moving_pts: i32,
+}
+
+/// Changes made to the scenario by the command accepted by the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delta {
+    /// Number of the command, counted from 1.
+    pub seq: usize,
+    pub turn: Turn,
+    /// Units that attacked during the current side turn.
+    pub attacked: Vec<i32>,
+    pub units: Vec<UnitState>,
+}
+
+impl Delta {
+    /// Compares moving components before the command with the current scenario.
+    pub fn between(
+        seq: usize,
+        before: &HashMap<i32, MovingComponent>,
+        scenario: &Scenario,
+    ) -> Self {
+        let mut units: Vec<_> = scenario
+            .systems
+            .moving
+            .components
+            .iter()
+            .filter(|(id, mc)| before.get(id) != Some(mc))
+            .map(|(id, mc)| UnitState {
+                id: *id,
+                occupation: mc.occupation,
+                moving_pts: mc.moving_pts(),
+            })
+            .collect();
+        units.sort_by_key(|unit| unit.id);
+        Delta {
+            seq,
+            turn: scenario.turn,
+            attacked: scenario.systems.combat.attacked_units(),
+            units,
+        }
+    }
+
+    pub fn apply(&self, scenario: &mut Scenario) -> Result<(), &'static str> {
+        for unit in self.units.iter() {
+            let mc = scenario
+                .systems
+                .moving
+                .components
+                .get_mut(&unit.id)
+                .ok_or("Delta changes unknown unit.")?;
+            mc.occupation = unit.occupation;
+            mc.set_mv_pts(unit.moving_pts);
+        }
+        scenario.turn = self.turn;
+        scenario.systems.combat.reset();
+        for id in self.attacked.iter() {
+            scenario.systems.combat.mark_attacked(*id);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Packet {
+    /// Client playing the side joins, or rejoins having applied the given number of deltas.
+    /// The token is 0 when joining for the first time.
+    Hello {
+        side: Side,
+        applied: usize,
+        token: u64,
+    },
+    /// Host accepts the client, telling the seed of the scenario, None for the test one,
+    /// and the token needed to rejoin the side.
+    Welcome {
+        seed: Option<u64>,
+        side: Side,
+        token: u64,
+    },
+    Command(GameCommand),
+    Delta(Delta),
+    Rejected(String),
+}
+
+pub fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Blue => "blue",
+        Side::Red => "red",
+    }
+}
+
+pub fn parse_side(word: &str) -> Result<Side, &'static str> {
+    match word {
+        "blue" => Ok(Side::Blue),
+        "red" => Ok(Side::Red),
+        _ => Err("Invalid side."),
+    }
+}
+
+fn parse_phase(word: &str) -> Result<Phase, &'static str> {
+    match word {
+        "movement" => Ok(Phase::Movement),
+        "combat" => Ok(Phase::Combat),
+        _ => Err("Invalid phase."),
+    }
+}
+
+//"id:hex:points", with "-" for units off the map
+fn unit_text(unit: &UnitState) -> String {
+    let hex = unit
+        .occupation
+        .map_or("-".to_owned(), |hex| hex.to_offset(COORDS_KIND).to_string());
+    format!("{}:{}:{}", unit.id, hex, unit.moving_pts)
+}
+
+fn parse_unit(word: &str) -> Result<UnitState, &'static str> {
+    const ERR: &str = "Invalid unit state.";
+    match word.split(':').collect::<Vec<_>>()[..] {
+        [id, hex, moving_pts] => Ok(UnitState {
+            id: id.parse().map_err(|_| ERR)?,
+            occupation: match hex {
+                "-" => None,
+                _ => Some(HexCoordinates::from_offset(hex.parse()?, COORDS_KIND)),
+            },
+            moving_pts: moving_pts.parse().map_err(|_| ERR)?,
+        }),
+        _ => Err(ERR),
+    }
+}
+
+impl Packet {
+    fn payload(&self) -> String {
+        match self {
+            Packet::Hello {
+                side,
+                applied,
+                token,
+            } => format!("hello {} {} {:016x}", side_name(*side), applied, token),
+            Packet::Welcome { seed, side, token } => match seed {
+                Some(seed) => format!(
+                    "welcome generated {} {} {:016x}",
+                    seed,
+                    side_name(*side),
+                    token
+                ),
+                None => format!("welcome test {} {:016x}", side_name(*side), token),
+            },
+            Packet::Command(command) => format!("command {}", command),
+            Packet::Delta(delta) => {
+                let turn = delta.turn;
+                let attacked: Vec<_> = delta.attacked.iter().map(i32::to_string).collect();
+                let mut text = format!(
+                    "delta {} {} {} {:?} {}",
+                    delta.seq,
+                    turn.number(),
+                    side_name(turn.side()),
+                    turn.phase(),
+                    if attacked.is_empty() {
+                        "-".to_owned()
+                    } else {
+                        attacked.join(",")
+                    }
+                );
+                for unit in delta.units.iter() {
+                    text.push(' ');
+                    text.push_str(&unit_text(unit));
+                }
+                text.to_lowercase()
+            }
+            Packet::Rejected(reason) => format!("rejected {}", reason),
+        }
+    }
+
+    fn from_payload(payload: &str) -> Result<Self, &'static str> {
+        fn number<T: FromStr>(word: &str) -> Result<T, &'static str> {
+            word.parse().map_err(|_| "Invalid packet.")
+        }
+        let (kind, rest) = match payload.find(' ') {
+            Some(pos) => (&payload[..pos], &payload[pos + 1..]),
+            None => (payload, ""),
+        };
+        let token = |word| u64::from_str_radix(word, 16).map_err(|_| "Invalid packet.");
+        let words: Vec<_> = rest.split_whitespace().collect();
+        match (kind, &words[..]) {
+            ("hello", [side, applied, tok]) => Ok(Packet::Hello {
+                side: parse_side(side)?,
+                applied: number(applied)?,
+                token: token(tok)?,
+            }),
+            ("welcome", ["test", side, tok]) => Ok(Packet::Welcome {
+                seed: None,
+                side: parse_side(side)?,
+                token: token(tok)?,
+            }),
+            ("welcome", ["generated", seed, side, tok]) => Ok(Packet::Welcome {
+                seed: Some(number(seed)?),
+                side: parse_side(side)?,
+                token: token(tok)?,
+            }),
+            ("command", _) => Ok(Packet::Command(rest.parse()?)),
+            ("delta", [seq, turn, side, phase, attacked, units @ ..]) => Ok(Packet::Delta(Delta {
+                seq: number(seq)?,
+                turn: Turn::new(number(turn)?, parse_side(side)?, parse_phase(phase)?),
+                attacked: match *attacked {
+                    "-" => Vec::new(),
+                    _ => attacked.split(',').map(number).collect::<Result<_, _>>()?,
+                },
+                units: units
+                    .iter()
+                    .map(|word| parse_unit(word))
+                    .collect::<Result<_, _>>()?,
+            })),
+            ("rejected", _) => Ok(Packet::Rejected(rest.to_owned())),
+            _ => Err("Invalid packet."),
+        }
+    }
+
+    /// Line sent over the connection, with the protocol version and the checksum of the payload.
+    pub fn encode(&self) -> String {
+        let payload = self.payload();
+        format!("CT{} {:016x} {}\n", VERSION, checksum(&payload), payload)
+    }
+
+    pub fn decode(line: &str) -> Result<Self, &'static str> {
+        let mut parts = line.trim_end().splitn(3, ' ');
+        let version = parts.next().unwrap();
+        if version != format!("CT{}", VERSION) {
+            return Err("Unsupported protocol version.");
+        }
+        let sum = parts.next().ok_or("Missing checksum.")?;
+        let payload = parts.next().ok_or("Missing payload.")?;
+        if u64::from_str_radix(sum, 16) != Ok(checksum(payload)) {
+            return Err("Checksum mismatch.");
+        }
+        Self::from_payload(payload)
+    }
+}
+
+/// FNV-1a hash of the text.
+pub fn checksum(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+pub fn send<W: Write>(output: &mut W, packet: &Packet) -> io::Result<()> {
+    output.write_all(packet.encode().as_bytes())?;
+    output.flush()
+}
+
+/// Reads the next packet, failing when the connection is closed or the packet is damaged.
+pub fn receive<R: BufRead>(input: &mut R) -> io::Result<Packet> {
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Connection closed.",
+        ));
+    }
+    Packet::decode(&line).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding_packets() {
+        let packets = [
+            Packet::Hello {
+                side: Side::Red,
+                applied: 3,
+                token: 0,
+            },
+            Packet::Welcome {
+                seed: Some(42),
+                side: Side::Blue,
+                token: 0x1234_5678_9abc_def0,
+            },
+            Packet::Welcome {
+                seed: None,
+                side: Side::Red,
+                token: 7,
+            },
+            Packet::Command(GameCommand::Attack(0, 2)),
+            Packet::Delta(Delta {
+                seq: 7,
+                turn: Turn::new(2, Side::Red, Phase::Combat),
+                attacked: vec![2, 5],
+                units: vec![
+                    UnitState {
+                        id: 0,
+                        occupation: Some(HexCoordinates::new_axial(-1, 3)),
+                        moving_pts: 4,
+                    },
+                    UnitState {
+                        id: 1,
+                        occupation: None,
+                        moving_pts: 8,
+                    },
+                ],
+            }),
+            Packet::Rejected("Not your turn.".to_owned()),
+        ];
+        for packet in packets.iter() {
+            let line = packet.encode();
+            assert!(line.starts_with("CT2 "));
+            assert_eq!(Packet::decode(&line).as_ref(), Ok(packet));
+        }
+
+        let line = packets[3].encode();
+        assert!(Packet::decode(&line.replace("attack 0 2", "attack 0 1")).is_err());
+        assert!(Packet::decode(&line.replace("CT2", "CT9")).is_err());
+        assert!(Packet::decode("CT2 0 nonsense").is_err());
+    }
+
+    #[test]
+    fn applying_deltas() {
+        let mut host = Scenario::test();
+        let mut client = Scenario::test();
+        let before = host.systems.moving.components.clone();
+        host.move_unit(0, HexCoordinates::new_axial(-1, 3)).unwrap();
+        host.next_phase();
+        host.attack(0, 2).unwrap();
+
+        let delta = Delta::between(1, &before, &host);
+        assert!(delta.units.iter().all(|unit| unit.id != 1));
+        delta.apply(&mut client).unwrap();
+        assert_eq!(client.turn, host.turn);
+        assert_eq!(
+            client.systems.moving.components,
+            host.systems.moving.components
+        );
+        assert!(client.systems.combat.has_attacked(0));
+    }
+}
@@ -0,0 +1,154 @@
+use crate::game::{GameCommand, Scenario};
+use crate::history::History;
+use crate::systems::vision::FogOfWar;
+use crate::units::unit::Side;
+
+/// What a side keeps to itself: the commands it can take back and what its units have seen.
+pub struct Player {
+    pub history: History<GameCommand>,
+    pub fog: FogOfWar,
+}
+
+/// Sides sharing one machine. In a hot-seat game each of them sees the map through
+/// its own fog of war and the board is hidden while the players change places,
+/// otherwise one player commands both sides and sees everything.
+pub struct Players {
+    blue: Player,
+    red: Player,
+    hot_seat: bool,
+}
+
+impl Players {
+    pub fn new(scenario: &Scenario, hot_seat: bool) -> Self {
+        let player = |side| Player {
+            history: History::new(),
+            fog: FogOfWar::new(side),
+        };
+        let mut players = Players {
+            blue: player(Side::Blue),
+            red: player(Side::Red),
+            hot_seat,
+        };
+        players.update_fog(scenario);
+        players
+    }
+
+    pub fn is_hot_seat(&self) -> bool {
+        self.hot_seat
+    }
+
+    pub fn player(&self, side: Side) -> &Player {
+        match side {
+            Side::Blue => &self.blue,
+            Side::Red => &self.red,
+        }
+    }
+
+    fn player_mut(&mut self, side: Side) -> &mut Player {
+        match side {
+            Side::Blue => &mut self.blue,
+            Side::Red => &mut self.red,
+        }
+    }
+
+    /// Fog of war the side sees the map through, None if it sees everything.
+    pub fn fog(&self, side: Side) -> Option<&FogOfWar> {
+        if self.hot_seat {
+            Some(&self.player(side).fog)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the unit can be seen by the side playing now.
+    pub fn shows_unit(&self, unit_id: i32, scenario: &Scenario) -> bool {
+        self.fog(scenario.turn.side())
+            .is_none_or(|fog| fog.shows_unit(unit_id, &scenario.units, &scenario.systems.moving))
+    }
+
+    //eliminated units stop seeing, so both sides look around after every change
+    fn update_fog(&mut self, scenario: &Scenario) {
+        for player in [&mut self.blue, &mut self.red].iter_mut() {
+            player
+                .fog
+                .update(&scenario.units, &scenario.systems.moving, &scenario.map);
+        }
+    }
+
+    /// Executes the command of the side playing now, with its history.
+    /// Returns true, if the turn passed to the other side.
+    pub fn execute(
+        &mut self,
+        command: GameCommand,
+        scenario: &mut Scenario,
+    ) -> Result<bool, &'static str> {
+        let side = scenario.turn.side();
+        self.player_mut(side).history.execute(command, scenario)?;
+        self.update_fog(scenario);
+        Ok(scenario.turn.side() != side)
+    }
+
+    pub fn undo(&mut self, scenario: &mut Scenario) -> Result<GameCommand, &'static str> {
+        let command = self
+            .player_mut(scenario.turn.side())
+            .history
+            .undo(scenario)?;
+        self.update_fog(scenario);
+        Ok(command)
+    }
+
+    pub fn redo(&mut self, scenario: &mut Scenario) -> Result<GameCommand, &'static str> {
+        let command = self
+            .player_mut(scenario.turn.side())
+            .history
+            .redo(scenario)?;
+        self.update_fog(scenario);
+        Ok(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maps::hexagons::HexCoordinates;
+
+    #[test]
+    fn sides_keep_to_themselves() {
+        let mut scenario = Scenario::test();
+        let mut players = Players::new(&scenario, true);
+        let next_to_red = HexCoordinates::new_axial(-1, 3);
+        assert!(!players.shows_unit(2, &scenario));
+
+        //a move that spots nothing can be taken back
+        players
+            .execute(
+                GameCommand::Move(1, HexCoordinates::new_axial(1, 0)),
+                &mut scenario,
+            )
+            .unwrap();
+        assert!(players.player(Side::Blue).history.can_undo());
+        players.undo(&mut scenario).unwrap();
+
+        //but blue can't scout the red unit and pretend it didn't
+        let moved = players
+            .execute(GameCommand::Move(0, next_to_red), &mut scenario)
+            .unwrap();
+        assert!(!moved);
+        assert!(players.shows_unit(2, &scenario));
+        assert!(!players.player(Side::Blue).history.can_undo());
+        assert!(players.undo(&mut scenario).is_err());
+        assert!(players.shows_unit(2, &scenario));
+
+        assert_eq!(
+            players.execute(GameCommand::EndTurn, &mut scenario),
+            Ok(true)
+        );
+        assert!(players.shows_unit(0, &scenario));
+        assert!(!players.shows_unit(1, &scenario));
+        assert!(players.undo(&mut scenario).is_err());
+
+        let players = Players::new(&scenario, false);
+        assert!(players.fog(Side::Red).is_none());
+        assert!(players.shows_unit(1, &scenario));
+    }
+}
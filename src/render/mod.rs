@@ -0,0 +1,182 @@
+//! Drawing of maps and scenarios without a window. Scenes are drawn through
+//! the Backend trait, so frontends only have to fill polygons and draw lines
+//! and text. The CPU canvas and the SVG export are such backends. The SFML window
+//! keeps its own textured vertex arrays, built from the same hex geometry in scene.
+
+pub mod palette;
+pub mod png;
+pub mod raster;
+pub mod scene;
+pub mod svg;
+
+extern crate log;
+
+use std::fs;
+
+use crate::game::Scenario;
+use crate::geometry::{FloatRect, Vector2f};
+use crate::maps::*;
+use palette::{Color, Palette};
+use raster::{Canvas, Transform};
+
+/// Target of scenes, all points are given in world coordinates.
+pub trait Backend {
+    /// Fills a convex polygon.
+    fn fill_polygon(&mut self, points: &[Vector2f], color: Color);
+    /// Draws connected line segments of the given width.
+    fn draw_polyline(&mut self, points: &[Vector2f], width: f32, color: Color);
+    /// Draws text centered at the position, backends without fonts may skip it.
+    fn draw_text(&mut self, position: Vector2f, size: f32, text: &str, color: Color);
+}
+
+const BACKGROUND: Color = Color::CYAN;
+//margin around the map, in pixels
+const MARGIN: f32 = 4.0;
+
+fn layout() -> hexagons::Layout {
+    hexagons::Layout {
+        orientation: hexagons::Orientation::POINTY,
+        size: Vector2f { x: 10.0, y: 10.0 },
+        origin: Vector2f { x: 0.0, y: 0.0 },
+    }
+}
+
+//canvas of the given width fitting the whole map
+fn fit(map: &map::Map, width: u32) -> Canvas {
+    let bounds =
+        scene::map_bounds(map, layout()).unwrap_or_else(|| FloatRect::new(0.0, 0.0, 1.0, 1.0));
+    let scale = (width as f32 - 2.0 * MARGIN).max(1.0) / bounds.width;
+    let height = (bounds.height * scale + 2.0 * MARGIN).ceil() as u32;
+    let mut canvas = Canvas::new(width, height, BACKGROUND);
+    canvas.set_transform(Transform {
+        scale,
+        offset: Vector2f {
+            x: MARGIN - bounds.left * scale,
+            y: MARGIN - bounds.top * scale,
+        },
+    });
+    canvas
+}
+
+/// Map alone, scaled to the given width.
+pub fn map_thumbnail(map: &map::Map, width: u32) -> Canvas {
+    let mut canvas = fit(map, width);
+    scene::draw_map(&mut canvas, map, layout(), &Palette::new(), None);
+    canvas
+}
+
+/// Map with the units placed on it, scaled to the given width.
+pub fn scenario_preview(scenario: &Scenario, width: u32) -> Canvas {
+    let mut canvas = fit(&scenario.map, width);
+    scene::draw_map(&mut canvas, &scenario.map, layout(), &Palette::new(), None);
+    //symbols would be a few pixels wide, counters only get a border at least a pixel thick
+    let scale = canvas.transform().scale;
+    let border = (layout().size.x * scale * 0.06).max(1.0) / scale;
+    scene::draw_plain_units(&mut canvas, scenario, layout(), border);
+    canvas
+}
+
+//path of the snapshot of the current turn, with the given extension
+fn snapshot_path(
+    scenario: &Scenario,
+    directory: &str,
+    extension: &str,
+) -> Result<String, &'static str> {
+    fs::create_dir_all(directory).map_err(|_| "Failed to create snapshots directory.")?;
+    let turn = scenario.turn;
+    let name = format!(
+        "turn_{:03}_{:?}_{:?}.{}",
+        turn.number(),
+        turn.side(),
+        turn.phase(),
+        extension
+    );
+    Ok(format!("{}/{}", directory, name.to_lowercase()))
+}
+
+/// Saves the preview of the current state of the scenario in the directory,
+/// named after the turn. Returns the path of the written file.
+pub fn save_turn_snapshot(
+    scenario: &Scenario,
+    directory: &str,
+    width: u32,
+) -> Result<String, &'static str> {
+    let path = snapshot_path(scenario, directory, "png")?;
+    scenario_preview(scenario, width).save_png(&path)?;
+    info!("Saved turn snapshot to {}.", path);
+    Ok(path)
+}
+
+/// Same as save_turn_snapshot, but exports the position to SVG.
+pub fn save_turn_svg(
+    scenario: &Scenario,
+    directory: &str,
+    palette: &Palette,
+) -> Result<String, &'static str> {
+    let path = snapshot_path(scenario, directory, "svg")?;
+    fs::write(&path, svg::scenario_svg(scenario, palette))
+        .map_err(|_| "Failed to write SVG file.")?;
+    info!("Saved turn position to {}.", path);
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    //compares the image with the golden one, or replaces it when UPDATE_GOLDEN is set
+    fn check_golden(canvas: &Canvas, name: &str) {
+        let path = format!("resources/golden/{}.png", name);
+        let png = canvas.to_png();
+        if env::var("UPDATE_GOLDEN").is_ok() {
+            fs::write(&path, &png).unwrap();
+            return;
+        }
+        let golden = fs::read(&path).expect("Missing golden image, run with UPDATE_GOLDEN=1.");
+        if png != golden {
+            let actual = format!("{}/{}.actual.png", env::temp_dir().display(), name);
+            fs::write(&actual, &png).unwrap();
+            panic!("{} differs from {}, see {}.", name, path, actual);
+        }
+    }
+
+    #[test]
+    fn golden_map_thumbnail() {
+        let canvas = map_thumbnail(&map::Map::create_test_map(), 200);
+        assert_eq!(canvas.width(), 200);
+        assert_eq!(canvas.pixel(0, 0), BACKGROUND);
+        check_golden(&canvas, "map_thumbnail");
+    }
+
+    #[test]
+    fn golden_scenario_preview() {
+        let mut scenario = Scenario::generated(7);
+        scenario
+            .map
+            .remove_hex(hexagons::HexCoordinates::new_axial(2, -2))
+            .unwrap();
+        check_golden(&scenario_preview(&scenario, 240), "scenario_preview");
+    }
+
+    #[test]
+    fn turn_snapshots() {
+        let directory = format!("{}/combat-theater-snapshots", env::temp_dir().display());
+        let scenario = Scenario::test();
+        let path = save_turn_snapshot(&scenario, &directory, 64).unwrap();
+        assert!(path.ends_with("turn_001_blue_movement.png"));
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            scenario_preview(&scenario, 64).to_png()
+        );
+
+        let palette = Palette::new();
+        let path = save_turn_svg(&scenario, &directory, &palette).unwrap();
+        assert!(path.ends_with("turn_001_blue_movement.svg"));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            svg::scenario_svg(&scenario, &palette)
+        );
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use crate::maps::types::{Field, River};
+use crate::units::unit::Side;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    pub const RED: Color = Color::rgb(255, 0, 0);
+    pub const GREEN: Color = Color::rgb(0, 255, 0);
+    pub const BLUE: Color = Color::rgb(0, 0, 255);
+    pub const CYAN: Color = Color::rgb(0, 255, 255);
+    pub const MAGENTA: Color = Color::rgb(255, 0, 255);
+    pub const TRANSPARENT: Color = Color::rgba(0, 0, 0, 0);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Color { r, g, b, a: 255 }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Color { r, g, b, a }
+    }
+}
+
+#[cfg(feature = "sfml")]
+impl From<Color> for sfml::graphics::Color {
+    fn from(c: Color) -> Self {
+        sfml::graphics::Color::rgba(c.r, c.g, c.b, c.a)
+    }
+}
+
+pub fn hex_color(kind: Field) -> Color {
+    match kind {
+        Field::Plain => Color::GREEN,
+        Field::Forest => Color::rgb(100, 140, 20),
+        Field::Hill => Color::rgb(170, 150, 90),
+    }
+}
+
+pub fn river_color(kind: River) -> Color {
+    match kind {
+        River::Small => Color::BLUE,
+        River::Stream => Color::CYAN,
+    }
+}
+
+pub fn side_color(side: Side) -> Color {
+    match side {
+        Side::Blue => Color::rgb(128, 224, 255),
+        Side::Red => Color::rgb(255, 128, 128),
+    }
+}
+
+/// Colors of terrain, overriding the builtin ones.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Palette {
+    hexes: HashMap<Field, Color>,
+    rivers: HashMap<River, Color>,
+}
+
+impl Palette {
+    pub fn new() -> Self {
+        Palette::default()
+    }
+
+    pub fn set_hex_color(&mut self, kind: Field, color: Color) -> &mut Self {
+        self.hexes.insert(kind, color);
+        self
+    }
+
+    pub fn set_river_color(&mut self, kind: River, color: Color) -> &mut Self {
+        self.rivers.insert(kind, color);
+        self
+    }
+
+    pub fn hex_color(&self, kind: Field) -> Color {
+        self.hexes
+            .get(&kind)
+            .cloned()
+            .unwrap_or_else(|| hex_color(kind))
+    }
+
+    pub fn river_color(&self, kind: River) -> Color {
+        self.rivers
+            .get(&kind)
+            .cloned()
+            .unwrap_or_else(|| river_color(kind))
+    }
+}
@@ -0,0 +1,199 @@
+//! Minimal PNG encoder for 8-bit RGBA images. Pixel data is compressed with fixed
+//! Huffman codes, matching only runs of the previous pixel, which is enough for
+//! the flat colors of rendered maps.
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+//distance of the previous pixel and its code
+const PIXEL_DISTANCE: usize = 4;
+const PIXEL_DISTANCE_CODE: u32 = 3;
+const MAX_MATCH: usize = 258;
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + u32::from(*byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/// Writes bits starting from the least significant one, as deflate expects.
+#[derive(Debug, Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    current: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, bits: u32) {
+        for i in 0..bits {
+            self.current |= ((value >> i) & 1) << self.count;
+            self.count += 1;
+            if self.count == 8 {
+                self.bytes.push(self.current as u8);
+                self.current = 0;
+                self.count = 0;
+            }
+        }
+    }
+
+    //huffman codes are stored starting from the most significant bit
+    fn write_code(&mut self, code: u32, bits: u32) {
+        let reversed = (0..bits).fold(0, |acc, i| acc | (((code >> i) & 1) << (bits - 1 - i)));
+        self.write(reversed, bits);
+    }
+
+    fn write_symbol(&mut self, symbol: u32) {
+        match symbol {
+            0..=143 => self.write_code(0x30 + symbol, 8),
+            144..=255 => self.write_code(0x190 + symbol - 144, 9),
+            256..=279 => self.write_code(symbol - 256, 7),
+            _ => self.write_code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn write_match(&mut self, length: usize) {
+        let index = LENGTH_BASES
+            .iter()
+            .rposition(|base| usize::from(*base) <= length)
+            .unwrap();
+        self.write_symbol(257 + index as u32);
+        self.write(
+            (length - usize::from(LENGTH_BASES[index])) as u32,
+            u32::from(LENGTH_EXTRA_BITS[index]),
+        );
+        self.write_code(PIXEL_DISTANCE_CODE, 5);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.current as u8);
+        }
+        self.bytes
+    }
+}
+
+/// Compresses data into a zlib stream with a single deflate block.
+pub fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    //final block with fixed huffman codes
+    writer.write(1, 1);
+    writer.write(1, 2);
+
+    let mut i = 0;
+    while i < data.len() {
+        let mut length = 0;
+        if i >= PIXEL_DISTANCE {
+            while i + length < data.len()
+                && length < MAX_MATCH
+                && data[i + length] == data[i + length - PIXEL_DISTANCE]
+            {
+                length += 1;
+            }
+        }
+        if length >= 3 {
+            writer.write_match(length);
+            i += length;
+        } else {
+            writer.write_symbol(u32::from(data[i]));
+            i += 1;
+        }
+    }
+    writer.write_symbol(256);
+
+    let mut res = vec![0x78, 0x01];
+    res.extend(writer.finish());
+    res.extend(&adler32(data).to_be_bytes());
+    res
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(&crc.to_be_bytes());
+}
+
+/// Encodes pixels given as consecutive RGBA bytes, row after row.
+pub fn encode(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    assert_eq!(rgba.len(), (width * height * 4) as usize);
+
+    let mut header = Vec::new();
+    header.extend(&width.to_be_bytes());
+    header.extend(&height.to_be_bytes());
+    //8 bits per channel, RGBA, default compression, filtering and no interlacing
+    header.extend(&[8, 6, 0, 0, 0]);
+
+    let row_size = (width * 4) as usize;
+    let mut raw = Vec::with_capacity(rgba.len() + height as usize);
+    for row in rgba.chunks(row_size.max(1)) {
+        raw.push(0);
+        raw.extend(row);
+    }
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_compress(&raw));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn encoding() {
+        let pixels: Vec<u8> = [255, 0, 0, 255]
+            .iter()
+            .cycle()
+            .take(4 * 16)
+            .cloned()
+            .collect();
+        let png = encode(4, 4, &pixels);
+        assert_eq!(png[..8], SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+        //runs of the same pixel are compressed
+        assert!(png.len() < 8 + 25 + 12 + pixels.len());
+
+        //single fixed huffman block of a literal followed by the end of block
+        assert_eq!(
+            zlib_compress(&[0]),
+            vec![0x78, 0x01, 0x63, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01]
+        );
+    }
+}
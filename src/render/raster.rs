@@ -0,0 +1,218 @@
+use std::fs;
+
+use super::palette::Color;
+use super::{png, Backend};
+use crate::geometry::Vector2f;
+
+/// Maps world coordinates to pixels, scaling first and translating afterwards.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    pub scale: f32,
+    pub offset: Vector2f,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform {
+            scale: 1.0,
+            offset: Vector2f::default(),
+        }
+    }
+}
+
+impl Transform {
+    pub fn apply(&self, point: Vector2f) -> Vector2f {
+        point * self.scale + self.offset
+    }
+}
+
+//source over destination, with integer rounding so the results don't depend on the platform
+fn blend(src: Color, dst: Color) -> Color {
+    let a = u32::from(src.a);
+    let mix = |s: u8, d: u8| ((u32::from(s) * a + u32::from(d) * (255 - a) + 127) / 255) as u8;
+    Color::rgba(
+        mix(src.r, dst.r),
+        mix(src.g, dst.g),
+        mix(src.b, dst.b),
+        (a + (u32::from(dst.a) * (255 - a) + 127) / 255) as u8,
+    )
+}
+
+fn edge(a: Vector2f, b: Vector2f, p: Vector2f) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+/// Image drawn on the CPU, so it can be rendered without a window or a GPU.
+/// As a backend it draws scenes with its transform, text is skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<Color>,
+    transform: Transform,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32, background: Color) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: vec![background; (width * height) as usize],
+            transform: Transform::default(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[cfg(test)]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    #[cfg(test)]
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Fills pixels whose centers lie inside the triangle, given in pixel coordinates.
+    pub fn fill_triangle(&mut self, points: [Vector2f; 3], color: Color) {
+        let [a, b, c] = points;
+        let area = edge(a, b, c);
+        if area == 0.0 {
+            return;
+        }
+
+        let min_x = a.x.min(b.x).min(c.x).floor().max(0.0) as u32;
+        let min_y = a.y.min(b.y).min(c.y).floor().max(0.0) as u32;
+        let max_x = (a.x.max(b.x).max(c.x).ceil().max(0.0) as u32).min(self.width);
+        let max_y = (a.y.max(b.y).max(c.y).ceil().max(0.0) as u32).min(self.height);
+        let sign = area.signum();
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let p = Vector2f {
+                    x: x as f32 + 0.5,
+                    y: y as f32 + 0.5,
+                };
+                if edge(a, b, p) * sign >= 0.0
+                    && edge(b, c, p) * sign >= 0.0
+                    && edge(c, a, p) * sign >= 0.0
+                {
+                    let pixel = &mut self.pixels[(y * self.width + x) as usize];
+                    *pixel = blend(color, *pixel);
+                }
+            }
+        }
+    }
+
+    pub fn to_png(&self) -> Vec<u8> {
+        let rgba: Vec<u8> = self
+            .pixels
+            .iter()
+            .flat_map(|c| vec![c.r, c.g, c.b, c.a])
+            .collect();
+        png::encode(self.width, self.height, &rgba)
+    }
+
+    pub fn save_png(&self, path: &str) -> Result<(), &'static str> {
+        fs::write(path, self.to_png()).map_err(|_| "Failed to write PNG file.")
+    }
+}
+
+impl Backend for Canvas {
+    //split into a fan of triangles, the same way vertex arrays of the map are built
+    fn fill_polygon(&mut self, points: &[Vector2f], color: Color) {
+        let t = self.transform;
+        for i in 1..points.len().saturating_sub(1) {
+            self.fill_triangle(
+                [
+                    t.apply(points[0]),
+                    t.apply(points[i]),
+                    t.apply(points[i + 1]),
+                ],
+                color,
+            );
+        }
+    }
+
+    fn draw_polyline(&mut self, points: &[Vector2f], width: f32, color: Color) {
+        for segment in points.windows(2) {
+            let direction = segment[1] - segment[0];
+            let length = (direction.x * direction.x + direction.y * direction.y).sqrt();
+            if length == 0.0 {
+                continue;
+            }
+            let normal = Vector2f::new(-direction.y, direction.x) * (width / 2.0 / length);
+            self.fill_polygon(
+                &[
+                    segment[0] + normal,
+                    segment[1] + normal,
+                    segment[1] - normal,
+                    segment[0] - normal,
+                ],
+                color,
+            );
+        }
+    }
+
+    fn draw_text(&mut self, _position: Vector2f, _size: f32, _text: &str, _color: Color) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rasterizing() {
+        let mut canvas = Canvas::new(10, 10, Color::WHITE);
+        assert_eq!((canvas.width(), canvas.height()), (10, 10));
+        canvas.fill_triangle(
+            [
+                Vector2f { x: 0.0, y: 0.0 },
+                Vector2f { x: 10.0, y: 0.0 },
+                Vector2f { x: 0.0, y: 10.0 },
+            ],
+            Color::RED,
+        );
+        assert_eq!(canvas.pixel(0, 0), Color::RED);
+        assert_eq!(canvas.pixel(4, 4), Color::RED);
+        assert_eq!(canvas.pixel(5, 5), Color::WHITE);
+        assert_eq!(canvas.pixel(9, 9), Color::WHITE);
+
+        //outside of the canvas and with the opposite winding
+        canvas.set_transform(Transform {
+            scale: 2.0,
+            offset: Vector2f::new(0.0, 4.0),
+        });
+        canvas.fill_polygon(
+            &[
+                Vector2f::new(4.0, 4.0),
+                Vector2f::new(2.0, 4.0),
+                Vector2f::new(2.0, 2.0),
+                Vector2f::new(4.0, 2.0),
+            ],
+            Color::rgba(0, 0, 0, 128),
+        );
+        assert_eq!(canvas.pixel(6, 9), Color::rgb(127, 127, 127));
+        assert_eq!(canvas.pixel(3, 9), Color::WHITE);
+
+        canvas.draw_polyline(
+            &[Vector2f::new(0.0, -1.0), Vector2f::new(2.0, -1.0)],
+            1.0,
+            Color::BLUE,
+        );
+        assert_eq!(canvas.pixel(1, 1), Color::BLUE);
+        assert_eq!(canvas.pixel(1, 3), Color::RED);
+    }
+}
@@ -0,0 +1,335 @@
+//! Geometry of the map and the counters, shared by all frontends, and scenes
+//! drawn through a backend.
+
+use super::palette::{side_color, Color, Palette};
+use super::Backend;
+use crate::game::Scenario;
+use crate::geometry::{FloatRect, Vector2f};
+use crate::maps::*;
+use crate::units::unit::UnitType;
+
+/// Thickness of hex outlines, as a fraction of the hex size.
+pub const OUTLINE_THICKNESS: f32 = 0.05;
+/// Width of rivers, as a fraction of the distance between hex centers.
+pub const RIVER_WIDTH: f32 = 0.1;
+
+//points of ellipses drawn by backends without curves
+const ELLIPSE_POINTS: usize = 24;
+
+/// Corners of the hex in world coordinates.
+pub fn hex_corners(layout: hexagons::Layout, hex: hexagons::HexCoordinates) -> [Vector2f; 6] {
+    let center = hexagons::hex_to_world_point(hex, layout);
+    let mut corners = [center; 6];
+    for (i, corner) in corners.iter_mut().enumerate() {
+        *corner += layout.corner_offset(i as u32);
+    }
+    corners
+}
+
+/// Quads of a border running along the inner side of the hex edges,
+/// thickness is given as a fraction of the hex size.
+pub fn outline_quads(
+    layout: hexagons::Layout,
+    hex: hexagons::HexCoordinates,
+    thickness: f32,
+) -> [[Vector2f; 4]; 6] {
+    let center = hexagons::hex_to_world_point(hex, layout);
+    let outer = hex_corners(layout, hex);
+    let mut inner = outer;
+    for corner in inner.iter_mut() {
+        *corner = center + (*corner - center) * (1.0 - thickness);
+    }
+    let mut quads = [[center; 4]; 6];
+    for (i, quad) in quads.iter_mut().enumerate() {
+        let j = (i + 1) % 6;
+        *quad = [outer[i], outer[j], inner[j], inner[i]];
+    }
+    quads
+}
+
+/// Corners of a quad along the hexside shared by side1 and side2,
+/// width is given as a fraction of the distance between the hex centers.
+pub fn edge_points(
+    layout: hexagons::Layout,
+    side1: hexagons::HexCoordinates,
+    side2: hexagons::HexCoordinates,
+    width: f32,
+) -> [Vector2f; 4] {
+    let vec1 = hexagons::hex_to_world_point(side1, layout);
+    let vec2 = hexagons::hex_to_world_point(side2, layout);
+
+    let center = (vec1 + vec2) / 2.0;
+    let connecter = (vec1 - vec2) / 2.0;
+    let connecter_orth = Vector2f {
+        x: -connecter.y * layout.size.x / layout.size.y,
+        y: connecter.x * layout.size.y / layout.size.x,
+    };
+
+    [
+        center + 1.05 * connecter_orth / 2.0 + width * connecter,
+        center + 1.05 * connecter_orth / 2.0 - width * connecter,
+        center - 1.05 * connecter_orth / 2.0 - width * connecter,
+        center - 1.05 * connecter_orth / 2.0 + width * connecter,
+    ]
+}
+
+/// Bounding box of all hexes in world coordinates, None for an empty map.
+pub fn map_bounds(map: &map::Map, layout: hexagons::Layout) -> Option<FloatRect> {
+    let mut points = map
+        .hexes()
+        .values()
+        .flat_map(|site| hex_corners(layout, *site.coord()).to_vec());
+
+    let first = points.next()?;
+    let (min, max) = points.fold((first, first), |(min, max), p| {
+        (
+            Vector2f::new(min.x.min(p.x), min.y.min(p.y)),
+            Vector2f::new(max.x.max(p.x), max.y.max(p.y)),
+        )
+    });
+    Some(FloatRect::new(min.x, min.y, max.x - min.x, max.y - min.y))
+}
+
+/// Units with their levels in stacks and positions, in the order they are drawn.
+pub fn stacked_units(scenario: &Scenario) -> Vec<(usize, i32, hexagons::HexCoordinates)> {
+    let mut ids: Vec<_> = scenario.units.units().keys().cloned().collect();
+    ids.sort();
+
+    let mut stacks: Vec<(hexagons::HexCoordinates, usize)> = Vec::new();
+    let mut counters = Vec::new();
+    for id in ids {
+        let occupation = match scenario
+            .systems
+            .moving
+            .components
+            .get(&id)
+            .and_then(|mc| mc.occupation)
+        {
+            Some(hex) => hex,
+            None => continue,
+        };
+        let level = match stacks.iter_mut().find(|(hex, _)| *hex == occupation) {
+            Some((_, level)) => {
+                *level += 1;
+                *level
+            }
+            None => {
+                stacks.push((occupation, 0));
+                0
+            }
+        };
+        counters.push((level, id, occupation));
+    }
+    counters.sort_by_key(|(level, id, _)| (*level, *id));
+    counters
+}
+
+/// Center of the counter, shifted up and right with each level of the stack.
+pub fn counter_center(
+    layout: hexagons::Layout,
+    hex: hexagons::HexCoordinates,
+    level: usize,
+) -> Vector2f {
+    hexagons::hex_to_world_point(hex, layout)
+        + Vector2f {
+            x: layout.size.x * 0.12,
+            y: -layout.size.y * 0.12,
+        } * level as f32
+}
+
+fn rect_corners(center: Vector2f, half: Vector2f) -> [Vector2f; 4] {
+    [
+        center - half,
+        center + Vector2f::new(half.x, -half.y),
+        center + half,
+        center + Vector2f::new(-half.x, half.y),
+    ]
+}
+
+/// Fills of the hexes, rivers and outlines, in this order, optionally with
+/// offset coordinates written over the hexes.
+pub fn draw_map<B: Backend + ?Sized>(
+    backend: &mut B,
+    map: &map::Map,
+    layout: hexagons::Layout,
+    palette: &Palette,
+    coords: Option<hexagons::OffsetKind>,
+) {
+    //sorted, so the output doesn't depend on the order of hash maps
+    let mut hex_ids: Vec<_> = map.hexes().keys().collect();
+    hex_ids.sort();
+    let hexes: Vec<_> = hex_ids.into_iter().map(|id| map.hexes()[id]).collect();
+    let mut river_ids: Vec<_> = map.rivers().keys().collect();
+    river_ids.sort();
+
+    for site in hexes.iter() {
+        backend.fill_polygon(
+            &hex_corners(layout, *site.coord()),
+            palette.hex_color(site.kind()),
+        );
+    }
+    for id in river_ids {
+        let site = &map.rivers()[id];
+        let (side1, side2) = site.sides();
+        backend.fill_polygon(
+            &edge_points(layout, *side1, *side2, RIVER_WIDTH),
+            palette.river_color(site.kind()),
+        );
+    }
+    for site in hexes.iter() {
+        for quad in outline_quads(layout, *site.coord(), OUTLINE_THICKNESS).iter() {
+            backend.fill_polygon(quad, Color::BLACK);
+        }
+    }
+
+    if let Some(kind) = coords {
+        for site in hexes.iter() {
+            backend.draw_text(
+                hexagons::hex_to_world_point(*site.coord(), layout),
+                layout.size.x * 0.5,
+                &site.coord().to_offset(kind).to_string(),
+                Color::MAGENTA,
+            );
+        }
+    }
+}
+
+/// Counters filled with the side colors for tiny previews, the border is in world units.
+pub fn draw_plain_units<B: Backend + ?Sized>(
+    backend: &mut B,
+    scenario: &Scenario,
+    layout: hexagons::Layout,
+    border: f32,
+) {
+    let half = layout.size / 2.0;
+    for (level, id, hex) in stacked_units(scenario) {
+        let side = scenario.units.units()[&id].side();
+        let center = counter_center(layout, hex, level);
+        backend.fill_polygon(&rect_corners(center, half), Color::BLACK);
+        backend.fill_polygon(
+            &rect_corners(center, half - Vector2f::new(border, border)),
+            side_color(side),
+        );
+    }
+}
+
+/// Counters of the units placed on the map, with their NATO symbols and labels.
+pub fn draw_units<B: Backend + ?Sized>(
+    backend: &mut B,
+    scenario: &Scenario,
+    layout: hexagons::Layout,
+) {
+    let size = layout.size;
+    let thickness = size.x.min(size.y) * 0.04;
+
+    for (level, id, hex) in stacked_units(scenario) {
+        let unit = &scenario.units.units()[&id];
+        let center = counter_center(layout, hex, level);
+        let half = size / 2.0;
+        backend.fill_polygon(&rect_corners(center, half), Color::BLACK);
+        backend.fill_polygon(
+            &rect_corners(center, half - Vector2f::new(thickness, thickness)),
+            side_color(unit.side()),
+        );
+
+        //NATO symbol frame, placed slightly above the center of the counter
+        let half = Vector2f::new(size.x * 0.3, size.y * 0.18);
+        let frame = center + Vector2f::new(0.0, -size.y * 0.06);
+        let c = rect_corners(frame, half);
+        let line = thickness / 2.0;
+        backend.draw_polyline(&[c[0], c[1], c[2], c[3], c[0]], line, Color::BLACK);
+        if unit.kind() == UnitType::Infantry || unit.kind() == UnitType::Mechanized {
+            backend.draw_polyline(&[c[0], c[2]], line, Color::BLACK);
+            backend.draw_polyline(&[c[1], c[3]], line, Color::BLACK);
+        }
+        if unit.kind() == UnitType::Armoured || unit.kind() == UnitType::Mechanized {
+            let ellipse: Vec<_> = (0..=ELLIPSE_POINTS)
+                .map(|i| {
+                    let angle = 2.0 * std::f32::consts::PI * i as f32 / ELLIPSE_POINTS as f32;
+                    frame + Vector2f::new(half.x * 0.7 * angle.cos(), half.y * 0.6 * angle.sin())
+                })
+                .collect();
+            backend.draw_polyline(&ellipse, line, Color::BLACK);
+        }
+
+        let text_size = size.y * 0.16;
+        backend.draw_text(
+            center + Vector2f::new(0.0, -size.y * 0.3),
+            text_size,
+            unit.name(),
+            Color::BLACK,
+        );
+        if let Some(mc) = scenario.systems.moving.components.get(&id) {
+            backend.draw_text(
+                center + Vector2f::new(0.0, size.y * 0.36),
+                text_size,
+                &format!("{}/{}", mc.moving_pts(), mc.default_moving_pts()),
+                Color::BLACK,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //counts what was drawn
+    #[derive(Default)]
+    struct Recorder {
+        polygons: Vec<(usize, Color)>,
+        polylines: usize,
+        texts: Vec<String>,
+    }
+
+    impl Backend for Recorder {
+        fn fill_polygon(&mut self, points: &[Vector2f], color: Color) {
+            self.polygons.push((points.len(), color));
+        }
+
+        fn draw_polyline(&mut self, _points: &[Vector2f], _width: f32, _color: Color) {
+            self.polylines += 1;
+        }
+
+        fn draw_text(&mut self, _position: Vector2f, _size: f32, text: &str, _color: Color) {
+            self.texts.push(text.to_owned());
+        }
+    }
+
+    #[test]
+    fn drawing_scenes() {
+        let scenario = Scenario::test();
+        let layout = super::super::layout();
+        let mut recorder = Recorder::default();
+        let mut palette = Palette::new();
+        palette.set_hex_color(types::Field::Plain, Color::RED);
+        draw_map(
+            &mut recorder,
+            &scenario.map,
+            layout,
+            &palette,
+            Some(hexagons::OffsetKind::OddR),
+        );
+
+        let hexes = scenario.map.hexes().len();
+        let rivers = scenario.map.rivers().len();
+        assert_eq!(recorder.polygons.len(), hexes * 7 + rivers);
+        assert!(recorder.polygons.contains(&(6, Color::RED)));
+        assert_eq!(recorder.texts.len(), hexes);
+        assert!(recorder.texts.contains(&"0000".to_owned()));
+
+        let mut recorder = Recorder::default();
+        draw_units(&mut recorder, &scenario, layout);
+        let counters = stacked_units(&scenario);
+        assert_eq!(recorder.polygons.len(), 2 * counters.len());
+        assert!(recorder.polylines >= counters.len());
+        let first = &scenario.units.units()[&counters[0].1];
+        assert!(recorder.texts.contains(&first.name().to_owned()));
+
+        let mut recorder = Recorder::default();
+        draw_plain_units(&mut recorder, &scenario, layout, 1.0);
+        assert_eq!(recorder.polygons.len(), 2 * counters.len());
+        assert_eq!(recorder.polylines, 0);
+        assert!(recorder.texts.is_empty());
+    }
+}
@@ -0,0 +1,246 @@
+use std::fmt::Write;
+use std::fs;
+
+use super::palette::{Color, Palette};
+use super::{scene, Backend};
+use crate::game::Scenario;
+use crate::geometry::Vector2f;
+use crate::maps::*;
+
+fn color(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+//opacity is written only for translucent colors
+fn fill(color: Color) -> String {
+    match color.a {
+        255 => format!("fill=\"{}\"", self::color(color)),
+        a => format!(
+            "fill=\"{}\" fill-opacity=\"{:.2}\"",
+            self::color(color),
+            f32::from(a) / 255.0
+        ),
+    }
+}
+
+fn points(points: &[Vector2f]) -> String {
+    points
+        .iter()
+        .map(|p| format!("{:.2},{:.2}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// What gets exported besides the map itself.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SvgOptions {
+    pub layout: hexagons::Layout,
+    /// Kind of offset coordinates written over the hexes, none if not given.
+    pub coords: Option<hexagons::OffsetKind>,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            layout: hexagons::Layout {
+                orientation: hexagons::Orientation::POINTY,
+                size: Vector2f { x: 30.0, y: 30.0 },
+                origin: Vector2f { x: 0.0, y: 0.0 },
+            },
+            coords: Some(hexagons::OffsetKind::OddR),
+        }
+    }
+}
+
+/// SVG document built from the same scenes as the images of the map.
+/// Every element takes a single line and elements are sorted, so exports diff well.
+#[derive(Debug)]
+pub struct SvgExport<'a> {
+    options: SvgOptions,
+    palette: &'a Palette,
+    body: String,
+    //bounding box of everything written so far
+    min: Vector2f,
+    max: Vector2f,
+}
+
+impl<'a> SvgExport<'a> {
+    pub fn new(options: SvgOptions, palette: &'a Palette) -> Self {
+        SvgExport {
+            options,
+            palette,
+            body: String::new(),
+            min: Vector2f {
+                x: f32::MAX,
+                y: f32::MAX,
+            },
+            max: Vector2f {
+                x: f32::MIN,
+                y: f32::MIN,
+            },
+        }
+    }
+
+    fn include(&mut self, point: Vector2f) {
+        self.min.x = self.min.x.min(point.x);
+        self.min.y = self.min.y.min(point.y);
+        self.max.x = self.max.x.max(point.x);
+        self.max.y = self.max.y.max(point.y);
+    }
+
+    /// Hexes with their outlines, rivers and coordinates.
+    pub fn map(&mut self, map: &map::Map) -> &mut Self {
+        let (layout, palette, coords) = (self.options.layout, self.palette, self.options.coords);
+        self.body.push_str("<g id=\"map\">\n");
+        scene::draw_map(self, map, layout, palette, coords);
+        self.body.push_str("</g>\n");
+        self
+    }
+
+    /// Counters of the units placed on the map, drawn like the tokens in the game.
+    pub fn units(&mut self, scenario: &Scenario) -> &mut Self {
+        let layout = self.options.layout;
+        self.body.push_str("<g id=\"units\">\n");
+        scene::draw_units(self, scenario, layout);
+        self.body.push_str("</g>\n");
+        self
+    }
+
+    /// Complete document, with the view box fitted to its contents.
+    pub fn finish(&self) -> String {
+        let margin = self.options.layout.size.x * 0.5;
+        let (min, max) = if self.min.x <= self.max.x {
+            (self.min, self.max)
+        } else {
+            (Vector2f::default(), Vector2f::default())
+        };
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{:.2} {:.2} {:.2} {:.2}\" \
+             font-family=\"sans-serif\">\n{}</svg>\n",
+            min.x - margin,
+            min.y - margin,
+            max.x - min.x + 2.0 * margin,
+            max.y - min.y + 2.0 * margin,
+            self.body
+        )
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), &'static str> {
+        fs::write(path, self.finish()).map_err(|_| "Failed to write SVG file.")
+    }
+}
+
+impl<'a> Backend for SvgExport<'a> {
+    fn fill_polygon(&mut self, corners: &[Vector2f], color: Color) {
+        for corner in corners {
+            self.include(*corner);
+        }
+        writeln!(
+            self.body,
+            "<polygon points=\"{}\" {}/>",
+            points(corners),
+            fill(color)
+        )
+        .unwrap();
+    }
+
+    fn draw_polyline(&mut self, line: &[Vector2f], width: f32, color: Color) {
+        for point in line {
+            self.include(*point);
+        }
+        writeln!(
+            self.body,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{:.2}\"/>",
+            points(line),
+            self::color(color),
+            width
+        )
+        .unwrap();
+    }
+
+    fn draw_text(&mut self, position: Vector2f, size: f32, text: &str, color: Color) {
+        writeln!(
+            self.body,
+            "<text x=\"{:.2}\" y=\"{:.2}\" font-size=\"{:.2}\" {} text-anchor=\"middle\" \
+             dominant-baseline=\"middle\">{}</text>",
+            position.x,
+            position.y,
+            size,
+            fill(color),
+            escape(text)
+        )
+        .unwrap();
+    }
+}
+
+/// Current position of the scenario, with the default options.
+pub fn scenario_svg(scenario: &Scenario, palette: &Palette) -> String {
+    SvgExport::new(SvgOptions::default(), palette)
+        .map(&scenario.map)
+        .units(scenario)
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maps::types::Field;
+    use crate::render::palette::hex_color;
+
+    fn map_svg(map: &map::Map) -> String {
+        SvgExport::new(SvgOptions::default(), &Palette::new())
+            .map(map)
+            .finish()
+    }
+
+    #[test]
+    fn exporting_map() {
+        let mut map = map::Map::new();
+        let origin = hexagons::HexCoordinates::origin();
+        let neighbor = hexagons::HexCoordinates::new_axial(1, 0);
+        map.insert_hex(map::HexSite::new(origin, Field::Forest))
+            .unwrap();
+        map.insert_hex(map::HexSite::new(neighbor, Field::Plain))
+            .unwrap();
+        map.insert_river(map::RiverSite::new(origin, neighbor, types::River::Stream).unwrap())
+            .unwrap();
+
+        let svg = map_svg(&map);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>\n"));
+        //two hexes with six quads of outline each and a river
+        assert_eq!(svg.matches("<polygon").count(), 15);
+        assert!(svg.contains(&color(hex_color(Field::Forest))));
+        assert!(svg.contains(">0000</text>"));
+        //same input gives the same document
+        assert_eq!(svg, map_svg(&map));
+
+        let first_corner = scene::hex_corners(SvgOptions::default().layout, origin)[0];
+        assert!(svg.contains(&format!("{:.2},{:.2}", first_corner.x, first_corner.y)));
+    }
+
+    #[test]
+    fn exporting_scenario() {
+        let scenario = Scenario::test();
+        let svg = scenario_svg(&scenario, &Palette::new());
+        let units = scenario.units.units().len();
+        let polygons = 7 * scenario.map.hexes().len() + scenario.map.rivers().len();
+        assert_eq!(svg.matches("<polygon").count(), polygons + 2 * units);
+        assert!(svg.contains("<polyline"));
+
+        let options = SvgOptions {
+            coords: None,
+            ..SvgOptions::default()
+        };
+        let svg = SvgExport::new(options, &Palette::new())
+            .map(&scenario.map)
+            .finish();
+        assert!(!svg.contains("<text"));
+    }
+}
@@ -0,0 +1,342 @@
+extern crate log;
+
+use crate::game::{GameCommand, Scenario};
+use crate::history::Command;
+use crate::maps::hexagons::{HexCoordinates, OffsetKind};
+use crate::maps::map::Map;
+use crate::net::checksum;
+use crate::rng;
+
+use chrono::Local;
+
+use std::collections::HashMap;
+use std::fs;
+
+const VERSION: u32 = 2;
+
+/// Written after "scenario =", "test" or "generated" followed by the seed.
+pub fn scenario_value(seed: Option<u64>) -> String {
+    match seed {
+        Some(seed) => format!("generated {}", seed),
+        None => "test".to_owned(),
+    }
+}
+
+pub fn parse_scenario(value: &str) -> Result<Option<u64>, &'static str> {
+    match value.split_whitespace().collect::<Vec<_>>()[..] {
+        ["test"] => Ok(None),
+        ["generated", seed] => Ok(Some(seed.parse().map_err(|_| "Invalid seed.")?)),
+        _ => Err("Unknown scenario."),
+    }
+}
+
+/// Hash of the hexes and rivers of the map, the same map always gives the same hash.
+pub fn map_hash(map: &Map) -> u64 {
+    let label = |hex: HexCoordinates| hex.to_offset(OffsetKind::OddR).to_string();
+    let mut parts: Vec<_> = map
+        .hexes()
+        .values()
+        .map(|site| format!("{}:{:?}", label(*site.coord()), site.kind()))
+        .collect();
+    parts.extend(map.rivers().values().map(|river| {
+        let (a, b) = river.sides();
+        let mut sides = [label(*a), label(*b)];
+        sides.sort();
+        format!("{}-{}:{:?}", sides[0], sides[1], river.kind())
+    }));
+    parts.sort();
+    checksum(&parts.join(" "))
+}
+
+/// Splits text in the replay format into "key = value" lines and commands,
+/// "#" starts a comment. Any line that's neither fails with the error.
+pub fn parse_lines(
+    text: &str,
+    error: &'static str,
+) -> Result<(HashMap<String, String>, Vec<GameCommand>), &'static str> {
+    let mut values = HashMap::new();
+    let mut commands = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut parts = line.splitn(2, '=');
+        let key = parts.next().unwrap().trim();
+        match parts.next() {
+            Some(value) => {
+                values.insert(key.to_owned(), value.trim().to_owned());
+            }
+            None => commands.push(line.parse().map_err(|_| {
+                error!("Invalid line {}: {}", number + 1, line);
+                error
+            })?),
+        }
+    }
+    Ok((values, commands))
+}
+
+/// Scenario and the commands played in it. Neither map generation nor combat depend
+/// on anything but the seeds, so replaying the commands gives the same game.
+/// The hash of the map catches replays of maps generated differently.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+    seed: Option<u64>,
+    /// State of the dice before the first command.
+    dice: u64,
+    map: u64,
+    commands: Vec<GameCommand>,
+}
+
+impl Replay {
+    /// Starts recording the scenario, which must not have been played yet.
+    pub fn new(scenario: &Scenario) -> Self {
+        Replay {
+            seed: scenario.seed,
+            dice: scenario.dice.state(),
+            map: map_hash(&scenario.map),
+            commands: Vec::new(),
+        }
+    }
+
+    /// Seed of the generated map, None for the test scenario.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn commands(&self) -> &[GameCommand] {
+        &self.commands
+    }
+
+    /// Creates the scenario in the state before the first command.
+    pub fn scenario(&self) -> Scenario {
+        let mut scenario = match self.seed {
+            Some(seed) => Scenario::generated(seed),
+            None => Scenario::test(),
+        };
+        scenario.dice = rng::Rng::new(self.dice);
+        scenario
+    }
+
+    pub fn record(&mut self, command: GameCommand) {
+        self.commands.push(command);
+    }
+
+    /// Forgets the last command after it's undone.
+    pub fn undo(&mut self) {
+        self.commands.pop();
+    }
+
+    /// Writes the replay as text, one command per line.
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "# Combat theater replay\nversion = {}\nscenario = {}\ndice = {}\nmap = {:016x}\n",
+            VERSION,
+            scenario_value(self.seed),
+            self.dice,
+            self.map
+        );
+        for command in self.commands.iter() {
+            text.push_str(&format!("{}\n", command));
+        }
+        text
+    }
+
+    /// Parses "version", "scenario", "dice" and "map" lines followed by the commands,
+    /// "#" starts a comment. The map must be the one the scenario creates.
+    pub fn parse(text: &str) -> Result<Self, &'static str> {
+        const ERR: &str = "Invalid replay.";
+        let (values, commands) = parse_lines(text, ERR)?;
+        if values
+            .keys()
+            .any(|key| !["version", "scenario", "dice", "map"].contains(&key.as_str()))
+        {
+            return Err(ERR);
+        }
+        if values.get("version") != Some(&VERSION.to_string()) {
+            return Err("Unsupported replay version.");
+        }
+        let value = |key| values.get(key).map(String::as_str).ok_or(ERR);
+        let replay = Replay {
+            seed: parse_scenario(
+                values
+                    .get("scenario")
+                    .ok_or("Replay doesn't name its scenario.")?,
+            )?,
+            dice: value("dice")?.parse().map_err(|_| ERR)?,
+            map: u64::from_str_radix(value("map")?, 16).map_err(|_| ERR)?,
+            commands,
+        };
+        if map_hash(&replay.scenario().map) != replay.map {
+            return Err("Replay was played on another map.");
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), &'static str> {
+        fs::write(path, self.to_text()).map_err(|_| "Failed to write replay file.")?;
+        info!("Saved replay to {}.", path);
+        Ok(())
+    }
+
+    /// Saves the replay in the directory, named after the current time.
+    /// Returns the path of the written file.
+    pub fn save_to_dir(&self, directory: &str) -> Result<String, &'static str> {
+        fs::create_dir_all(directory).map_err(|_| "Failed to create replays directory.")?;
+        let path = format!(
+            "{}/{}.replay",
+            directory,
+            Local::now().format("%Y%m%d_%H%M%S")
+        );
+        self.save(&path)?;
+        Ok(path)
+    }
+
+    pub fn load(path: &str) -> Result<Self, &'static str> {
+        let text = fs::read_to_string(path).map_err(|_| "Failed to read replay file.")?;
+        Self::parse(&text)
+    }
+}
+
+/// Replay being watched, command after command.
+pub struct Playback {
+    replay: Replay,
+    scenario: Scenario,
+    position: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        let scenario = replay.scenario();
+        Playback {
+            replay,
+            scenario,
+            position: 0,
+        }
+    }
+
+    pub fn scenario(&self) -> &Scenario {
+        &self.scenario
+    }
+
+    /// Number of commands played so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position == self.replay.commands.len()
+    }
+
+    /// Plays the next command, returns None at the end of the replay.
+    pub fn step(&mut self) -> Result<Option<GameCommand>, &'static str> {
+        let command = match self.replay.commands.get(self.position) {
+            Some(command) => *command,
+            None => return Ok(None),
+        };
+        command.apply(&mut self.scenario).map_err(|err| {
+            error!("Replayed {} failed: {}", command, err);
+            "Replay doesn't match its scenario."
+        })?;
+        self.position += 1;
+        Ok(Some(command))
+    }
+
+    /// Plays commands until the turn passes to the other side, returns their number.
+    pub fn next_turn(&mut self) -> Result<usize, &'static str> {
+        let turn = (self.scenario.turn.number(), self.scenario.turn.side());
+        let mut count = 0;
+        while turn == (self.scenario.turn.number(), self.scenario.turn.side()) {
+            match self.step()? {
+                Some(_) => count += 1,
+                None => break,
+            }
+        }
+        Ok(count)
+    }
+
+    /// Plays the rest of the replay, returns the number of commands.
+    pub fn fast_forward(&mut self) -> Result<usize, &'static str> {
+        let mut count = 0;
+        while self.step()?.is_some() {
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::History;
+    use crate::units::unit::Side;
+
+    #[test]
+    fn recording_and_playing_back() {
+        let mut scenario = Scenario::generated(5);
+        let mut replay = Replay::new(&scenario);
+        let mut history = History::new();
+        let origin = scenario.systems.moving.components[&0].occupation.unwrap();
+        let next = origin.neighbors();
+        let commands = [
+            GameCommand::Move(0, next[0]),
+            GameCommand::Move(1, next[1]),
+            GameCommand::EndTurn,
+            GameCommand::NextPhase,
+            GameCommand::EndTurn,
+            GameCommand::Move(0, origin),
+        ];
+        for command in commands.iter() {
+            history.execute(*command, &mut scenario).unwrap();
+            replay.record(*command);
+        }
+        history.undo(&mut scenario).unwrap();
+        replay.undo();
+        assert_eq!(replay.commands(), &commands[..5]);
+
+        let text = replay.to_text();
+        assert!(text.contains("scenario = generated 5\ndice = 5\n"));
+        let loaded = Replay::parse(&text).unwrap();
+        assert_eq!(loaded, replay);
+
+        let mut playback = Playback::new(loaded);
+        assert_eq!(playback.next_turn(), Ok(3));
+        assert_eq!(playback.scenario().turn.side(), Side::Red);
+        assert_eq!(playback.fast_forward(), Ok(2));
+        assert!(playback.is_finished());
+        assert_eq!(playback.step(), Ok(None));
+        assert_eq!(playback.scenario().turn, scenario.turn);
+        assert_eq!(
+            playback.scenario().systems.moving.components,
+            scenario.systems.moving.components
+        );
+
+        let header = Replay::new(&Scenario::test()).to_text();
+        assert!(Replay::parse(&(header.clone() + "fly 1\n")).is_err());
+        assert!(Replay::parse(&header.replace("version = 2", "version = 1")).is_err());
+        assert!(Replay::parse("version = 2\nscenario = test\n").is_err());
+        let mut playback = Playback::new(Replay::parse(&(header.clone() + "attack 0 2")).unwrap());
+        assert!(playback.step().is_err());
+
+        //the dice continue from the recorded state
+        let mut scenario = Scenario::test();
+        scenario.dice = rng::Rng::new(6);
+        let replay = Replay::parse(&Replay::new(&scenario).to_text()).unwrap();
+        assert_eq!(replay.scenario().dice.state(), 6);
+
+        //a map generated differently doesn't match the hash
+        let mut scenario = Scenario::generated(5);
+        scenario
+            .map
+            .remove_hex(HexCoordinates::new_axial(0, 0))
+            .unwrap();
+        assert_eq!(
+            Replay::parse(&Replay::new(&scenario).to_text()),
+            Err("Replay was played on another map.")
+        );
+        assert_ne!(
+            map_hash(&scenario.map),
+            map_hash(&Scenario::generated(5).map)
+        );
+    }
+}
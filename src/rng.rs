@@ -0,0 +1,50 @@
+//Seeded pseudorandom numbers, so generating maps and rolling dice can be repeated exactly.
+
+pub fn splitmix64(mut state: u64) -> u64 {
+    state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    state = (state ^ (state >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    state = (state ^ (state >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    state ^ (state >> 31)
+}
+
+/// Simple deterministic random generator, used to pick river sources and roll combat dice.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    /// Generator continues from the state, Rng::new(rng.state()) rolls the same numbers.
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        splitmix64(self.state)
+    }
+
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn continuing_from_state() {
+        let mut rng = Rng::new(42);
+        rng.next_u64();
+        let mut copy = Rng::new(rng.state());
+        for _ in 0..10 {
+            assert_eq!(rng.next_u64(), copy.next_u64());
+        }
+        assert!((0..100).all(|_| rng.below(6) < 6));
+    }
+}
@@ -0,0 +1,166 @@
+extern crate log;
+extern crate sfml;
+
+use sfml::graphics::{Color, FloatRect, RenderTarget, Text, Transformable, View};
+use sfml::system::Vector2f;
+use sfml::window::Event;
+
+use super::map_view::MapView;
+use super::*;
+use crate::history::History;
+use crate::maps::hexagons::{self, Axis};
+use crate::maps::map::{Map, MapEdit};
+use crate::maps::types::{Field, River};
+
+/// Simple map editor, painting the hovered hex with the chosen terrain.
+/// Hexes can also be added and removed and rivers toggled on the hovered hexside.
+/// Every edit can be undone, except rotating or mirroring the whole map around the hovered hex,
+/// which starts the history anew.
+pub struct Editor {
+    map: Map,
+    view: MapView,
+    brush: Field,
+    history: History<MapEdit>,
+}
+
+impl Editor {
+    pub fn new(map: Map, window_size: Vector2f) -> Self {
+        let view = MapView::new(&map, window_size);
+        Editor {
+            map,
+            view,
+            brush: Field::Plain,
+            history: History::new(),
+        }
+    }
+
+    fn edit(&mut self, edit: MapEdit) {
+        match self.history.execute(edit, &mut self.map) {
+            Ok(()) => debug!("Edited map: {:?}.", edit),
+            Err(err) => warn!("Cannot edit map, {:?}: {}", edit, err),
+        }
+    }
+
+    fn paint(&mut self, ctx: &Context) {
+        let hex = self.view.hovered_hex(ctx);
+        //painting outside of the map isn't worth a warning
+        if self.map.hex_at(hex).is_some() {
+            self.edit(MapEdit::Paint(hex, self.brush));
+        }
+    }
+
+    fn toggle_hex(&mut self, ctx: &Context) {
+        let hex = self.view.hovered_hex(ctx);
+        self.edit(MapEdit::ToggleHex(hex, self.brush));
+    }
+
+    fn toggle_river(&mut self, ctx: &Context) {
+        let (side1, side2) = hexagons::world_point_to_edge(
+            self.view.mouse_world_pos(ctx).into(),
+            self.view.layout(),
+        );
+        self.edit(MapEdit::ToggleRiver(side1, side2, River::Stream));
+    }
+
+    //mirrors across the line through the hovered hex perpendicular to the closest hexside,
+    //so the hexes beyond it swap places with the ones on the opposite side
+    fn mirror(&mut self, ctx: &Context) {
+        let (hex, neighbor) = hexagons::world_point_to_edge(
+            self.view.mouse_world_pos(ctx).into(),
+            self.view.layout(),
+        );
+        let direction = neighbor - hex;
+        let axis = if direction.q() == 0 {
+            Axis::Q
+        } else if direction.p() == 0 {
+            Axis::R
+        } else {
+            Axis::S
+        };
+        let map = self.map.mirrored(hex, axis);
+        self.replace_map(map);
+    }
+
+    fn replace_map(&mut self, map: Map) {
+        self.map = map;
+        self.view.set_map(&self.map);
+        self.history = History::new();
+    }
+}
+
+impl State for Editor {
+    fn name(&self) -> &'static str {
+        "editor"
+    }
+
+    fn handle_event(
+        &mut self,
+        ctx: &mut Context,
+        _event: &Event,
+        action: Option<Action>,
+    ) -> Transition {
+        let action = match action {
+            Some(action) => action,
+            None => return Transition::None,
+        };
+        if self.view.handle_action(ctx, action) {
+            return Transition::None;
+        }
+        match action {
+            Action::Select => self.paint(ctx),
+            Action::BrushPlain => self.brush = Field::Plain,
+            Action::BrushForest => self.brush = Field::Forest,
+            Action::BrushHill => self.brush = Field::Hill,
+            Action::ToggleHex => self.toggle_hex(ctx),
+            Action::ToggleRiver => self.toggle_river(ctx),
+            Action::RotateMap => {
+                let map = self.map.rotated(self.view.hovered_hex(ctx), 1);
+                self.replace_map(map);
+            }
+            Action::MirrorMap => self.mirror(ctx),
+            Action::Undo => {
+                if let Err(err) = self.history.undo(&mut self.map) {
+                    warn!("{}", err);
+                }
+            }
+            Action::Redo => {
+                if let Err(err) = self.history.redo(&mut self.map) {
+                    warn!("{}", err);
+                }
+            }
+            Action::Quit => return Transition::Pop,
+            _ => {}
+        }
+        Transition::None
+    }
+
+    fn update(&mut self, ctx: &mut Context, seconds: f32) -> Transition {
+        self.view.update(ctx, seconds);
+        self.view.map_gfx.update(&self.map);
+        Transition::None
+    }
+
+    fn draw(&self, ctx: &Context, target: &mut dyn RenderTarget) {
+        target.clear(&Color::CYAN);
+        self.view.draw(ctx, target);
+        self.view.draw_hover(ctx, target);
+
+        let size = ctx.window_size;
+        target.set_view(&View::from_rect(&FloatRect::new(0.0, 0.0, size.x, size.y)));
+        let mut text = Text::new(
+            &format!(
+                "Brush: {:?} (1 - plain, 2 - forest, 3 - hill, Del - toggle hex, R - toggle river, Q - rotate, M - mirror)",
+                self.brush
+            ),
+            &ctx.font,
+            20,
+        );
+        text.set_fill_color(&Color::BLACK);
+        text.set_position((10.0, 10.0));
+        target.draw(&text);
+    }
+
+    fn resize(&mut self, size: Vector2f) {
+        self.view.resize(size);
+    }
+}
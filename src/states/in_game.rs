@@ -0,0 +1,414 @@
+extern crate log;
+extern crate sfml;
+
+use sfml::graphics::{FloatRect, PrimitiveType, RenderTarget, VertexArray};
+use sfml::system::{Vector2f, Vector2i};
+use sfml::window::Event;
+
+use super::map_view::MapView;
+use super::menu::{GameOver, Handoff, Pause};
+use super::*;
+use crate::game::{GameCommand, Scenario};
+use crate::graphics;
+use crate::graphics::hud::Hud;
+use crate::maps::hexagons;
+use crate::players::Players;
+use crate::render;
+use crate::replay::Replay;
+use crate::systems::influence::InfluenceMap;
+use crate::systems::moving::{self, MovementRange};
+
+pub struct InGame {
+    scenario: Scenario,
+    view: MapView,
+    tokens: graphics::tokens::Tokens<'static>,
+    selection: Option<(i32, MovementRange)>,
+    players: Players,
+    replay: Replay,
+    //the turn passed in a hot-seat game, the board must be hidden from the next player
+    handoff: bool,
+    hud: Hud,
+    //click over the HUD, waiting for it to be built in the next update
+    hud_click: Option<Vector2i>,
+    //hexes the enemy can attack next turn are tinted
+    shows_threats: bool,
+    //influence the tint is drawn from, dropped by every command and built again when shown
+    threats: Option<InfluenceMap>,
+}
+
+impl InGame {
+    pub fn new(scenario: Scenario, hot_seat: bool, window_size: Vector2f) -> Self {
+        let view = MapView::new(&scenario.map, window_size);
+        let tokens = graphics::tokens::Tokens::new(
+            view.map_gfx.layout.clone(),
+            &scenario.units,
+            &scenario.systems.moving,
+        );
+        InGame {
+            replay: Replay::new(&scenario),
+            players: Players::new(&scenario, hot_seat),
+            handoff: false,
+            scenario,
+            view,
+            tokens,
+            selection: None,
+            hud: Hud::new(),
+            hud_click: None,
+            shows_threats: false,
+            threats: None,
+        }
+    }
+
+    fn select_unit(&self, unit_id: i32) -> Option<(i32, MovementRange)> {
+        match self.scenario.movement_range(unit_id) {
+            Ok(range) => Some((unit_id, range)),
+            Err(err) => {
+                warn!("Cannot select unit {}: {}", unit_id, err);
+                None
+            }
+        }
+    }
+
+    fn execute(&mut self, command: GameCommand) {
+        self.threats = None;
+        match self.players.execute(command, &mut self.scenario) {
+            Ok(passed) => {
+                self.replay.record(command);
+                self.handoff = passed && self.players.is_hot_seat();
+            }
+            Err(err) => warn!("Cannot execute {:?}: {}", command, err),
+        }
+    }
+
+    fn undo(&mut self) {
+        self.threats = None;
+        match self.players.undo(&mut self.scenario) {
+            Ok(command) => {
+                debug!("Undone {:?}.", command);
+                self.replay.undo();
+            }
+            Err(err) => warn!("{}", err),
+        }
+        self.selection = None;
+    }
+
+    fn redo(&mut self) {
+        self.threats = None;
+        match self.players.redo(&mut self.scenario) {
+            Ok(command) => {
+                debug!("Redone {:?}.", command);
+                self.replay.record(command);
+            }
+            Err(err) => warn!("{}", err),
+        }
+        self.selection = None;
+    }
+
+    fn is_enemy(&self, unit_id: i32) -> bool {
+        self.scenario
+            .units
+            .units()
+            .get(&unit_id)
+            .is_some_and(|u| u.side() != self.scenario.turn.side())
+    }
+
+    fn end_turn(&mut self) {
+        self.execute(GameCommand::EndTurn);
+        self.selection = None;
+    }
+
+    fn unit_info(&self, unit_id: i32) -> Vec<String> {
+        let unit = &self.scenario.units.units()[&unit_id];
+        let mut lines = vec![
+            unit.name().to_owned(),
+            format!("{:?} {:?}", unit.side(), unit.kind()),
+        ];
+        if let Some(mc) = self.scenario.systems.moving.components.get(&unit_id) {
+            lines.push(format!(
+                "Moving points: {}/{}",
+                mc.moving_pts(),
+                mc.default_moving_pts()
+            ));
+            if self.scenario.systems.combat.has_attacked(unit_id) {
+                lines.push("Attacked this turn".to_owned());
+            }
+            if let Some(hex) = mc.occupation {
+                let offset = hex.to_offset(self.view.map_gfx.coords_kind);
+                lines.push(format!("Position: {}", offset));
+            }
+        }
+        lines
+    }
+
+    fn terrain_info(&self, ctx: &Context) -> Vec<String> {
+        let map = &self.scenario.map;
+        let hex = self.view.hovered_hex(ctx);
+        let site = match map.hex_at(hex) {
+            Some(site) => site,
+            None => return Vec::new(),
+        };
+        if let Some(fog) = self.players.fog(self.scenario.turn.side()) {
+            if !fog.is_explored(&hex) {
+                return vec![hex.to_offset(self.view.map_gfx.coords_kind).to_string()];
+            }
+        }
+
+        let offset = hex.to_offset(self.view.map_gfx.coords_kind);
+        let mut lines = vec![format!(
+            "{} {:?}, entering cost {}",
+            offset,
+            site.kind(),
+            moving::cost_of_entering_hex(site.kind())
+        )];
+        let (side1, side2) = hexagons::world_point_to_edge(
+            self.view.mouse_world_pos(ctx).into(),
+            self.view.layout(),
+        );
+        if let Some(river) = map.river_between(side1, side2) {
+            lines.push(format!(
+                "{:?} river, crossing cost {}",
+                river.kind(),
+                moving::cost_of_crossing_river(river.kind())
+            ));
+        }
+        lines
+    }
+
+    fn build_hud(&mut self, ctx: &Context) {
+        let size = ctx.window_size;
+        self.hud
+            .begin(ctx.input.mouse_position(), self.hud_click.take());
+
+        let turn = self.scenario.turn;
+        self.hud.panel(
+            Vector2f { x: 10.0, y: 10.0 },
+            220.0,
+            &[
+                format!("Turn {}", turn.number()),
+                format!("{:?} side, {:?} phase", turn.side(), turn.phase()),
+            ],
+        );
+
+        if let Some((unit_id, _)) = self.selection {
+            let lines = self.unit_info(unit_id);
+            let height =
+                lines.len() as f32 * graphics::hud::LINE_HEIGHT + 2.0 * graphics::hud::PADDING;
+            self.hud.panel(
+                Vector2f {
+                    x: 10.0,
+                    y: size.y - height - 10.0,
+                },
+                220.0,
+                &lines,
+            );
+        }
+
+        let button =
+            |index: f32| FloatRect::new(size.x - 110.0, size.y - 40.0 * index, 100.0, 30.0);
+        if self.hud.button(button(4.0), "Next phase") {
+            self.execute(GameCommand::NextPhase);
+            self.selection = None;
+        }
+        if self.hud.button(button(3.0), "End turn") {
+            self.end_turn();
+        }
+        let history = &self.players.player(turn.side()).history;
+        let (can_undo, can_redo) = (history.can_undo(), history.can_redo());
+        if !can_undo {
+            self.hud.disabled_button(button(2.0), "Undo");
+        } else if self.hud.button(button(2.0), "Undo") {
+            self.undo();
+        }
+        if !can_redo {
+            self.hud.disabled_button(button(1.0), "Redo");
+        } else if self.hud.button(button(1.0), "Redo") {
+            self.redo();
+        }
+
+        //tooltip is declared last, so it's drawn over other widgets
+        let lines = self.terrain_info(ctx);
+        if !lines.is_empty() && !self.hud.contains(ctx.input.mouse_position()) {
+            let mouse = ctx.input.mouse_position();
+            let position = Vector2f {
+                x: mouse.x as f32 + 16.0,
+                y: mouse.y as f32 + 16.0,
+            };
+            self.hud.tooltip(position, 230.0, &lines);
+        }
+    }
+
+    fn click(&mut self, ctx: &Context) {
+        let world_pos = self.view.mouse_world_pos(ctx);
+        let hex = self.view.hovered_hex(ctx);
+        self.selection = match (self.tokens.unit_at(world_pos), self.selection.take()) {
+            (Some(target), Some((unit_id, _)))
+                if self.scenario.can_attack(unit_id) && self.is_enemy(target) =>
+            {
+                self.execute(GameCommand::Attack(unit_id, target));
+                None
+            }
+            (Some(unit_id), _) => self.select_unit(unit_id),
+            (None, Some((unit_id, range)))
+                if range.contains(&hex) && self.scenario.can_move(unit_id) =>
+            {
+                self.execute(GameCommand::Move(unit_id, hex));
+                self.select_unit(unit_id)
+            }
+            _ => None,
+        };
+    }
+}
+
+impl State for InGame {
+    fn name(&self) -> &'static str {
+        "in game"
+    }
+
+    fn handle_event(
+        &mut self,
+        ctx: &mut Context,
+        _event: &Event,
+        action: Option<Action>,
+    ) -> Transition {
+        let action = match action {
+            Some(action) => action,
+            None => return Transition::None,
+        };
+        if self.view.handle_action(ctx, action) {
+            return Transition::None;
+        }
+
+        match action {
+            Action::Select if self.hud.contains(ctx.input.mouse_position()) => {
+                self.hud_click = Some(ctx.input.mouse_position())
+            }
+            Action::Select => self.click(ctx),
+            Action::Deselect => self.selection = None,
+            Action::CenterOnSelection => {
+                let occupation = self
+                    .selection
+                    .as_ref()
+                    .and_then(|(id, _)| self.scenario.systems.moving.components.get(id))
+                    .and_then(|mc| mc.occupation);
+                if let Some(hex) = occupation {
+                    let layout = self.view.layout();
+                    self.view.camera.center_on_hex(hex, layout);
+                }
+            }
+            Action::EndTurn => self.end_turn(),
+            Action::Snapshot => {
+                let palette = &self.view.map_gfx.theme().palette;
+                let res = render::save_turn_snapshot(&self.scenario, "snapshots", 800)
+                    .and_then(|_| render::save_turn_svg(&self.scenario, "snapshots", palette));
+                if let Err(err) = res {
+                    warn!("{}", err);
+                }
+            }
+            Action::DangerZones => self.shows_threats = !self.shows_threats,
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+            Action::Quit => return Transition::Push(Box::new(Pause::new())),
+            _ => {}
+        }
+        Transition::None
+    }
+
+    fn update(&mut self, ctx: &mut Context, seconds: f32) -> Transition {
+        self.view.update(ctx, seconds);
+        self.view.map_gfx.update(&self.scenario.map);
+        let (players, scenario) = (&self.players, &self.scenario);
+        self.tokens
+            .update(&scenario.units, &scenario.systems.moving)
+            .retain(|id| players.shows_unit(id, scenario));
+        self.build_hud(ctx);
+        if self.shows_threats && self.threats.is_none() {
+            let side = self.scenario.turn.side();
+            self.threats = Some(InfluenceMap::new(
+                side,
+                &self.scenario.units,
+                &self.scenario.systems.moving,
+                &self.scenario.map,
+                self.players.fog(side),
+            ));
+        }
+
+        if let Some(side) = self.scenario.winner() {
+            if let Err(err) = self.replay.save_to_dir("replays") {
+                warn!("{}", err);
+            }
+            return Transition::Push(Box::new(GameOver::new(side)));
+        }
+        if self.handoff {
+            self.handoff = false;
+            self.selection = None;
+            return Transition::Push(Box::new(Handoff::new(self.scenario.turn.side())));
+        }
+        Transition::None
+    }
+
+    fn draw(&self, ctx: &Context, target: &mut dyn RenderTarget) {
+        target.clear(&sfml::graphics::Color::CYAN);
+        self.view.draw(ctx, target);
+
+        //drawn below the fog, so hexes never seen stay covered
+        let fog = self.players.fog(self.scenario.turn.side());
+        if let Some(influence) = self.threats.as_ref().filter(|_| self.shows_threats) {
+            let mut shape = VertexArray::new(PrimitiveType::Triangles, 0);
+            for (hex, threat) in influence.danger_zone() {
+                graphics::shapes::append_hex(
+                    &mut shape,
+                    self.view.layout(),
+                    *hex,
+                    graphics::shapes::threat_color(*threat),
+                );
+            }
+            target.draw(&shape);
+        }
+
+        if let Some(fog) = fog {
+            let hexes = self.scenario.map.hexes().values().map(|site| *site.coord());
+            let (unexplored, out_of_sight): (Vec<_>, Vec<_>) = hexes
+                .filter(|hex| !fog.is_visible(hex))
+                .partition(|hex| !fog.is_explored(hex));
+            let layout = self.view.layout();
+            target.draw(&graphics::shapes::hexes_shape(
+                layout,
+                unexplored,
+                graphics::shapes::UNEXPLORED_COLOR,
+            ));
+            target.draw(&graphics::shapes::hexes_shape(
+                layout,
+                out_of_sight,
+                graphics::shapes::OUT_OF_SIGHT_COLOR,
+            ));
+        }
+
+        if let Some((_, range)) = &self.selection {
+            let shape = graphics::shapes::hexes_shape(
+                self.view.layout(),
+                range.costs.keys().cloned(),
+                graphics::shapes::HIGHLIGHT_COLOR,
+            );
+            target.draw(&shape);
+        }
+
+        self.tokens
+            .draw(target, &ctx.font, self.view.map_gfx.theme());
+
+        if let Some((unit_id, range)) = &self.selection {
+            if let Some(token) = self.tokens.tokens.get(unit_id) {
+                target.draw(token.highlight_shape());
+            }
+            if let Some(path) = range.path_to(self.view.hovered_hex(ctx)) {
+                target.draw(&graphics::shapes::path_shape(self.view.layout(), &path));
+            }
+        }
+
+        self.view.draw_hover(ctx, target);
+        self.hud.draw(target, &ctx.font, ctx.window_size);
+    }
+
+    fn resize(&mut self, size: Vector2f) {
+        self.view.resize(size);
+    }
+}
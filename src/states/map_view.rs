@@ -0,0 +1,145 @@
+extern crate sfml;
+
+use sfml::graphics::RenderTarget;
+use sfml::system::Vector2f;
+
+use std::rc::Rc;
+
+use super::Context;
+use crate::graphics;
+use crate::graphics::camera::Camera;
+use crate::input::Action;
+use crate::maps::*;
+
+/// Map graphics together with the camera looking at them, shared by the game and the editor.
+pub struct MapView {
+    pub map_gfx: graphics::map::Map,
+    pub camera: Camera,
+}
+
+impl MapView {
+    pub fn new(map: &map::Map, window_size: Vector2f) -> Self {
+        let map_gfx = graphics::map::Map::new(
+            map,
+            hexagons::Layout {
+                orientation: hexagons::Orientation::POINTY,
+                size: (50.0, 50.0).into(),
+                origin: (0.0, 0.0).into(),
+            },
+        );
+        let mut camera = Camera::new(window_size);
+        camera.set_bounds(map_gfx.bounds());
+        MapView { map_gfx, camera }
+    }
+
+    /// Shows another map, keeping the layout and the camera where they are.
+    pub fn set_map(&mut self, map: &map::Map) {
+        self.map_gfx = graphics::map::Map::new(map, self.layout());
+        self.camera.set_bounds(self.map_gfx.bounds());
+    }
+
+    pub fn layout(&self) -> hexagons::Layout {
+        *self.map_gfx.layout.borrow()
+    }
+
+    pub fn mouse_world_pos(&self, ctx: &Context) -> Vector2f {
+        self.camera.pixel_to_world(ctx.input.mouse_position())
+    }
+
+    pub fn hovered_hex(&self, ctx: &Context) -> hexagons::HexCoordinates {
+        hexagons::world_point_to_hex(self.mouse_world_pos(ctx).into(), self.layout())
+    }
+
+    /// Handles camera and layout actions, returns false for the remaining ones.
+    pub fn handle_action(&mut self, ctx: &Context, action: Action) -> bool {
+        let mouse = ctx.input.mouse_position();
+        match action {
+            //panning with keys lasts as long as they are held, see update
+            Action::PanLeft | Action::PanRight | Action::PanUp | Action::PanDown => {}
+            Action::ZoomIn => self.camera.zoom_at(0.9, mouse),
+            Action::ZoomOut => self.camera.zoom_at(1.1, mouse),
+            Action::DragPan => self.camera.start_drag(mouse),
+            Action::FlattenHexes => {
+                self.map_gfx.layout.borrow_mut().size.y *= 0.95;
+                self.camera.set_bounds(self.map_gfx.bounds());
+            }
+            Action::StretchHexes => {
+                self.map_gfx.layout.borrow_mut().size.y *= 1.05;
+                self.camera.set_bounds(self.map_gfx.bounds());
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn update(&mut self, ctx: &Context, seconds: f32) {
+        let theme = ctx.themes.current();
+        if !Rc::ptr_eq(&theme, self.map_gfx.theme()) {
+            self.map_gfx.set_theme(theme);
+        }
+
+        let input = &ctx.input;
+        if input.is_held(Action::DragPan) {
+            self.camera.drag_to(input.mouse_position());
+        } else {
+            self.camera.end_drag();
+            if ctx.has_focus && input.is_mouse_inside() {
+                self.camera.edge_scroll(input.mouse_position(), seconds);
+            }
+        }
+
+        let held_direction = |action, direction| {
+            if input.is_held(action) {
+                direction
+            } else {
+                0.0
+            }
+        };
+        let direction = Vector2f {
+            x: held_direction(Action::PanRight, 1.0) + held_direction(Action::PanLeft, -1.0),
+            y: held_direction(Action::PanDown, 1.0) + held_direction(Action::PanUp, -1.0),
+        };
+        self.camera.pan(direction * self.camera.pan_speed * seconds);
+    }
+
+    pub fn resize(&mut self, size: Vector2f) {
+        self.camera.resize(size.x as u32, size.y as u32);
+    }
+
+    pub fn draw(&self, ctx: &Context, target: &mut dyn RenderTarget) {
+        target.set_view(&self.camera.view());
+        self.map_gfx.draw_hexes(target);
+        self.map_gfx.draw_rivers(target);
+        self.map_gfx.draw_outlines(target);
+        self.map_gfx.draw_coords(target, &ctx.font);
+    }
+
+    /// Highlights the hovered hex and the hexside and corner closest to the cursor.
+    pub fn draw_hover(&self, ctx: &Context, target: &mut dyn RenderTarget) {
+        let layout = self.layout();
+        let hex = self.hovered_hex(ctx);
+        if self.map_gfx.contains(&hex) {
+            let shape = graphics::shapes::hexes_shape(
+                layout,
+                std::iter::once(hex),
+                graphics::shapes::HIGHLIGHT_COLOR,
+            );
+            target.draw(&shape);
+        }
+
+        let (side1, side2) =
+            hexagons::world_point_to_edge(self.mouse_world_pos(ctx).into(), layout);
+        if self.map_gfx.contains(&side1) && self.map_gfx.contains(&side2) {
+            let edge = graphics::shapes::EdgeShape::new(layout, side1, side2);
+            target.draw(edge.highlight_shape());
+        }
+
+        let corner = hexagons::world_point_to_corner(self.mouse_world_pos(ctx).into(), layout);
+        if [corner.0, corner.1, corner.2]
+            .iter()
+            .all(|hex| self.map_gfx.contains(hex))
+        {
+            target.draw(&graphics::shapes::corner_shape(layout, corner));
+        }
+    }
+}
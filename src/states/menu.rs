@@ -0,0 +1,415 @@
+extern crate sfml;
+
+use sfml::graphics::{
+    Color, FloatRect, Font, RectangleShape, RenderTarget, Shape, Text, Transformable, View,
+};
+use sfml::system::Vector2f;
+use sfml::window::{mouse, Event, Key};
+
+use super::in_game::InGame;
+use super::*;
+use crate::game::Scenario;
+use crate::units::unit::Side;
+
+const ITEM_SIZE: Vector2f = Vector2f { x: 300.0, y: 40.0 };
+const ITEM_SPACING: f32 = 50.0;
+
+/// Vertical list of options, chosen with the arrow keys and Return or with the mouse.
+#[derive(Debug, Clone)]
+pub struct Menu {
+    title: String,
+    items: Vec<&'static str>,
+    selected: usize,
+}
+
+impl Menu {
+    pub fn new(title: &str, items: &[&'static str]) -> Self {
+        Menu {
+            title: title.to_owned(),
+            items: items.to_vec(),
+            selected: 0,
+        }
+    }
+
+    fn item_rect(&self, index: usize, window_size: Vector2f) -> FloatRect {
+        FloatRect::new(
+            (window_size.x - ITEM_SIZE.x) / 2.0,
+            window_size.y * 0.3 + index as f32 * ITEM_SPACING,
+            ITEM_SIZE.x,
+            ITEM_SIZE.y,
+        )
+    }
+
+    fn item_at(&self, x: i32, y: i32, window_size: Vector2f) -> Option<usize> {
+        let point = Vector2f {
+            x: x as f32,
+            y: y as f32,
+        };
+        (0..self.items.len()).find(|i| self.item_rect(*i, window_size).contains(point))
+    }
+
+    /// Returns the index of the chosen item.
+    pub fn handle_event(&mut self, event: &Event, window_size: Vector2f) -> Option<usize> {
+        let count = self.items.len();
+        match *event {
+            Event::KeyPressed { code: Key::Up, .. } => {
+                self.selected = (self.selected + count - 1) % count;
+                None
+            }
+            Event::KeyPressed {
+                code: Key::Down, ..
+            } => {
+                self.selected = (self.selected + 1) % count;
+                None
+            }
+            Event::KeyPressed {
+                code: Key::Return, ..
+            } => Some(self.selected),
+            Event::MouseMoved { x, y } => {
+                if let Some(index) = self.item_at(x, y, window_size) {
+                    self.selected = index;
+                }
+                None
+            }
+            Event::MouseButtonPressed {
+                button: mouse::Button::Left,
+                x,
+                y,
+            } => self.item_at(x, y, window_size),
+            _ => None,
+        }
+    }
+
+    /// Draws the menu in window coordinates, overlays dim the state below them.
+    pub fn draw(
+        &self,
+        target: &mut dyn RenderTarget,
+        font: &Font,
+        window_size: Vector2f,
+        dim: bool,
+    ) {
+        target.set_view(&View::from_rect(&FloatRect::new(
+            0.0,
+            0.0,
+            window_size.x,
+            window_size.y,
+        )));
+
+        if dim {
+            let mut background = RectangleShape::new();
+            background.set_size(window_size);
+            background.set_fill_color(&Color::rgba(0, 0, 0, 150));
+            target.draw(&background);
+        }
+
+        draw_centered_text(
+            target,
+            font,
+            &self.title,
+            48,
+            Vector2f {
+                x: window_size.x / 2.0,
+                y: window_size.y * 0.15,
+            },
+        );
+
+        for (index, item) in self.items.iter().enumerate() {
+            let rect = self.item_rect(index, window_size);
+            let mut shape = RectangleShape::new();
+            shape.set_size(ITEM_SIZE);
+            shape.set_position((rect.left, rect.top));
+            shape.set_outline_thickness(-2.0);
+            shape.set_outline_color(&Color::BLACK);
+            if index == self.selected {
+                shape.set_fill_color(&Color::rgb(255, 220, 120));
+            } else {
+                shape.set_fill_color(&Color::rgb(200, 200, 200));
+            }
+            target.draw(&shape);
+
+            draw_centered_text(
+                target,
+                font,
+                item,
+                24,
+                Vector2f {
+                    x: rect.left + rect.width / 2.0,
+                    y: rect.top + rect.height / 2.0,
+                },
+            );
+        }
+    }
+}
+
+fn draw_centered_text(
+    target: &mut dyn RenderTarget,
+    font: &Font,
+    string: &str,
+    size: u32,
+    position: Vector2f,
+) {
+    let mut text = Text::new(string, font, size);
+    text.set_fill_color(&Color::BLACK);
+    let bounds = text.local_bounds();
+    text.set_origin((
+        bounds.left + bounds.width / 2.0,
+        bounds.top + bounds.height / 2.0,
+    ));
+    text.set_position(position);
+    target.draw(&text);
+}
+
+pub struct MainMenu {
+    menu: Menu,
+}
+
+impl MainMenu {
+    pub fn new() -> Self {
+        MainMenu {
+            menu: Menu::new("Combat theater", &["New game", "Map editor", "Quit"]),
+        }
+    }
+}
+
+impl State for MainMenu {
+    fn name(&self) -> &'static str {
+        "main menu"
+    }
+
+    fn handle_event(
+        &mut self,
+        ctx: &mut Context,
+        event: &Event,
+        action: Option<Action>,
+    ) -> Transition {
+        if action == Some(Action::Quit) {
+            return Transition::Quit;
+        }
+        match self.menu.handle_event(event, ctx.window_size) {
+            Some(0) => Transition::Push(Box::new(ScenarioSelect::new())),
+            Some(1) => Transition::Push(Box::new(super::editor::Editor::new(
+                crate::maps::map::Map::create_test_map(),
+                ctx.window_size,
+            ))),
+            Some(_) => Transition::Quit,
+            None => Transition::None,
+        }
+    }
+
+    fn draw(&self, ctx: &Context, target: &mut dyn RenderTarget) {
+        target.clear(&Color::CYAN);
+        self.menu.draw(target, &ctx.font, ctx.window_size, false);
+    }
+}
+
+pub struct ScenarioSelect {
+    menu: Menu,
+}
+
+impl ScenarioSelect {
+    pub fn new() -> Self {
+        ScenarioSelect {
+            menu: Menu::new(
+                "Select scenario",
+                &[
+                    "Test scenario",
+                    "Random terrain",
+                    "Hot-seat, test scenario",
+                    "Hot-seat, random terrain",
+                    "Back",
+                ],
+            ),
+        }
+    }
+}
+
+impl State for ScenarioSelect {
+    fn name(&self) -> &'static str {
+        "scenario select"
+    }
+
+    fn handle_event(
+        &mut self,
+        ctx: &mut Context,
+        event: &Event,
+        action: Option<Action>,
+    ) -> Transition {
+        if action == Some(Action::Quit) {
+            return Transition::Pop;
+        }
+        let (scenario, hot_seat) = match self.menu.handle_event(event, ctx.window_size) {
+            Some(index @ 0..=3) if index % 2 == 0 => (Scenario::test(), index > 1),
+            Some(index @ 0..=3) => (
+                Scenario::generated(chrono::Local::now().timestamp() as u64),
+                index > 1,
+            ),
+            Some(_) => return Transition::Pop,
+            None => return Transition::None,
+        };
+        Transition::Replace(Box::new(InGame::new(scenario, hot_seat, ctx.window_size)))
+    }
+
+    fn draw(&self, ctx: &Context, target: &mut dyn RenderTarget) {
+        target.clear(&Color::CYAN);
+        self.menu.draw(target, &ctx.font, ctx.window_size, false);
+    }
+}
+
+pub struct Pause {
+    menu: Menu,
+}
+
+impl Pause {
+    pub fn new() -> Self {
+        Pause {
+            menu: Menu::new("Paused", &["Resume", "Main menu", "Quit"]),
+        }
+    }
+}
+
+impl State for Pause {
+    fn name(&self) -> &'static str {
+        "pause"
+    }
+
+    fn handle_event(
+        &mut self,
+        ctx: &mut Context,
+        event: &Event,
+        action: Option<Action>,
+    ) -> Transition {
+        if action == Some(Action::Quit) {
+            return Transition::Pop;
+        }
+        match self.menu.handle_event(event, ctx.window_size) {
+            Some(0) => Transition::Pop,
+            Some(1) => Transition::Reset(Box::new(MainMenu::new())),
+            Some(_) => Transition::Quit,
+            None => Transition::None,
+        }
+    }
+
+    fn draw(&self, ctx: &Context, target: &mut dyn RenderTarget) {
+        self.menu.draw(target, &ctx.font, ctx.window_size, true);
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+/// Hides the board between the turns of a hot-seat game, until the next player is ready.
+pub struct Handoff {
+    menu: Menu,
+}
+
+impl Handoff {
+    pub fn new(side: Side) -> Self {
+        Handoff {
+            menu: Menu::new(
+                &format!("Pass the game to the {:?} player", side),
+                &["Ready"],
+            ),
+        }
+    }
+}
+
+impl State for Handoff {
+    fn name(&self) -> &'static str {
+        "handoff"
+    }
+
+    fn handle_event(
+        &mut self,
+        ctx: &mut Context,
+        event: &Event,
+        _action: Option<Action>,
+    ) -> Transition {
+        match self.menu.handle_event(event, ctx.window_size) {
+            Some(_) => Transition::Pop,
+            None => Transition::None,
+        }
+    }
+
+    fn draw(&self, ctx: &Context, target: &mut dyn RenderTarget) {
+        target.clear(&Color::CYAN);
+        self.menu.draw(target, &ctx.font, ctx.window_size, false);
+    }
+}
+
+pub struct GameOver {
+    menu: Menu,
+}
+
+impl GameOver {
+    pub fn new(winner: Side) -> Self {
+        GameOver {
+            menu: Menu::new(&format!("{:?} side wins", winner), &["Main menu", "Quit"]),
+        }
+    }
+}
+
+impl State for GameOver {
+    fn name(&self) -> &'static str {
+        "game over"
+    }
+
+    fn handle_event(
+        &mut self,
+        ctx: &mut Context,
+        event: &Event,
+        _action: Option<Action>,
+    ) -> Transition {
+        match self.menu.handle_event(event, ctx.window_size) {
+            Some(0) => Transition::Reset(Box::new(MainMenu::new())),
+            Some(_) => Transition::Quit,
+            None => Transition::None,
+        }
+    }
+
+    fn draw(&self, ctx: &Context, target: &mut dyn RenderTarget) {
+        self.menu.draw(target, &ctx.font, ctx.window_size, true);
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: Key) -> Event {
+        Event::KeyPressed {
+            code,
+            alt: false,
+            ctrl: false,
+            shift: false,
+            system: false,
+        }
+    }
+
+    #[test]
+    fn menu_navigation() {
+        let size = Vector2f { x: 800.0, y: 600.0 };
+        let mut menu = Menu::new("Test", &["First", "Second", "Third"]);
+
+        assert_eq!(menu.handle_event(&key(Key::Up), size), None);
+        assert_eq!(menu.selected, 2);
+        menu.handle_event(&key(Key::Down), size);
+        menu.handle_event(&key(Key::Down), size);
+        assert_eq!(menu.handle_event(&key(Key::Return), size), Some(1));
+
+        //the second item spans from y = 230 to 270
+        menu.handle_event(&Event::MouseMoved { x: 400, y: 190 }, size);
+        assert_eq!(menu.selected, 0);
+        let click = |x, y| Event::MouseButtonPressed {
+            button: mouse::Button::Left,
+            x,
+            y,
+        };
+        assert_eq!(menu.handle_event(&click(400, 240), size), Some(1));
+        assert_eq!(menu.handle_event(&click(10, 240), size), None);
+    }
+}
@@ -0,0 +1,242 @@
+pub mod editor;
+pub mod in_game;
+pub mod map_view;
+pub mod menu;
+
+extern crate log;
+extern crate sfml;
+
+use sfml::graphics::{Font, RenderTarget};
+use sfml::system::Vector2f;
+use sfml::window::Event;
+
+use crate::graphics::theme::Themes;
+use crate::input::{Action, Input};
+
+/// Resources shared by all states.
+pub struct Context {
+    pub font: Font,
+    pub input: Input,
+    pub themes: Themes,
+    pub window_size: Vector2f,
+    pub has_focus: bool,
+}
+
+/// Change of the state stack requested by its top state.
+pub enum Transition {
+    None,
+    Push(Box<dyn State>),
+    Pop,
+    Replace(Box<dyn State>),
+    /// Clears the stack and starts again from the given state.
+    Reset(Box<dyn State>),
+    Quit,
+}
+
+pub trait State {
+    fn name(&self) -> &'static str;
+
+    fn handle_event(
+        &mut self,
+        ctx: &mut Context,
+        event: &Event,
+        action: Option<Action>,
+    ) -> Transition;
+
+    fn update(&mut self, _ctx: &mut Context, _seconds: f32) -> Transition {
+        Transition::None
+    }
+
+    fn draw(&self, ctx: &Context, target: &mut dyn RenderTarget);
+
+    /// Called for every state on the stack, not only the top one.
+    fn resize(&mut self, _size: Vector2f) {}
+
+    /// Overlays, like the pause menu, are drawn over the state below them.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+/// Only the top state receives events and updates, the rest are just drawn if visible.
+#[derive(Default)]
+pub struct StateStack {
+    states: Vec<Box<dyn State>>,
+}
+
+impl StateStack {
+    pub fn new(initial: Box<dyn State>) -> Self {
+        StateStack {
+            states: vec![initial],
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    pub fn top_name(&self) -> Option<&'static str> {
+        self.states.last().map(|s| s.name())
+    }
+
+    pub fn handle_event(&mut self, ctx: &mut Context, event: &Event) {
+        let action = ctx.input.handle_event(event);
+        match *event {
+            Event::Resized { width, height } => {
+                ctx.window_size = Vector2f {
+                    x: width as f32,
+                    y: height as f32,
+                };
+                for state in self.states.iter_mut() {
+                    state.resize(ctx.window_size);
+                }
+            }
+            Event::LostFocus => ctx.has_focus = false,
+            Event::GainedFocus => ctx.has_focus = true,
+            _ => {}
+        }
+        //themes can be switched in any state
+        if action == Some(Action::NextTheme) {
+            ctx.themes.next();
+            return;
+        }
+
+        let transition = match self.states.last_mut() {
+            Some(state) => state.handle_event(ctx, event, action),
+            None => return,
+        };
+        self.apply(ctx, transition);
+    }
+
+    pub fn update(&mut self, ctx: &mut Context, seconds: f32) {
+        let transition = match self.states.last_mut() {
+            Some(state) => state.update(ctx, seconds),
+            None => return,
+        };
+        self.apply(ctx, transition);
+    }
+
+    //states drawn in a frame, starting from the topmost one that covers the whole window
+    fn visible(&self) -> &[Box<dyn State>] {
+        let first = self
+            .states
+            .iter()
+            .rposition(|s| !s.is_overlay())
+            .unwrap_or(0);
+        &self.states[first..]
+    }
+
+    pub fn draw(&self, ctx: &Context, target: &mut dyn RenderTarget) {
+        for state in self.visible() {
+            state.draw(ctx, target);
+        }
+    }
+
+    fn apply(&mut self, ctx: &Context, transition: Transition) {
+        match transition {
+            Transition::None => return,
+            Transition::Push(state) => self.states.push(state),
+            Transition::Pop => {
+                self.states.pop();
+            }
+            Transition::Replace(state) => {
+                self.states.pop();
+                self.states.push(state);
+            }
+            Transition::Reset(state) => {
+                self.states.clear();
+                self.states.push(state);
+            }
+            Transition::Quit => self.states.clear(),
+        }
+
+        //the new top state could be created before the last resize
+        if let Some(state) = self.states.last_mut() {
+            state.resize(ctx.window_size);
+        }
+        debug!("Current state: {:?}.", self.top_name());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Bindings;
+    use sfml::window::Key;
+
+    //pushes a new state on Return, pops itself on Escape
+    struct TestState {
+        name: &'static str,
+        overlay: bool,
+    }
+
+    impl State for TestState {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn handle_event(
+            &mut self,
+            _ctx: &mut Context,
+            _event: &Event,
+            action: Option<Action>,
+        ) -> Transition {
+            match action {
+                Some(Action::EndTurn) => Transition::Push(Box::new(TestState {
+                    name: "overlay",
+                    overlay: true,
+                })),
+                Some(Action::Quit) => Transition::Pop,
+                Some(Action::Undo) => Transition::Quit,
+                _ => Transition::None,
+            }
+        }
+
+        fn draw(&self, _ctx: &Context, _target: &mut dyn RenderTarget) {}
+
+        fn is_overlay(&self) -> bool {
+            self.overlay
+        }
+    }
+
+    fn press(stack: &mut StateStack, ctx: &mut Context, code: Key, ctrl: bool) {
+        let event = Event::KeyPressed {
+            code,
+            alt: false,
+            ctrl,
+            shift: false,
+            system: false,
+        };
+        stack.handle_event(ctx, &event);
+    }
+
+    #[test]
+    fn state_transitions() {
+        let mut ctx = Context {
+            font: Font::from_file("resources/fonts/OpenSans-Regular.ttf").unwrap(),
+            input: Input::new(Bindings::default()),
+            themes: Themes::default(),
+            window_size: Vector2f { x: 800.0, y: 600.0 },
+            has_focus: true,
+        };
+        let mut stack = StateStack::new(Box::new(TestState {
+            name: "game",
+            overlay: false,
+        }));
+
+        press(&mut stack, &mut ctx, Key::Return, false);
+        press(&mut stack, &mut ctx, Key::Return, false);
+        assert_eq!(stack.top_name(), Some("overlay"));
+
+        //drawing needs no window, overlays are drawn over the state below them
+        let visible: Vec<_> = stack.visible().iter().map(|s| s.name()).collect();
+        assert_eq!(visible, vec!["game", "overlay", "overlay"]);
+
+        press(&mut stack, &mut ctx, Key::Escape, false);
+        press(&mut stack, &mut ctx, Key::Escape, false);
+        assert_eq!(stack.top_name(), Some("game"));
+
+        press(&mut stack, &mut ctx, Key::Z, true);
+        assert!(stack.is_empty());
+    }
+}
@@ -0,0 +1,247 @@
+use super::moving::MovingSystem;
+use crate::maps::*;
+use crate::rng;
+use crate::units::unit::{Unit, UnitType};
+
+use std::collections::HashSet;
+
+pub fn attack_strength(kind: UnitType) -> i32 {
+    match kind {
+        UnitType::Infantry => 2,
+        UnitType::Mechanized => 3,
+        UnitType::Armoured => 4,
+    }
+}
+
+pub fn defence_strength(kind: UnitType) -> i32 {
+    match kind {
+        UnitType::Infantry => 3,
+        UnitType::Mechanized => 3,
+        UnitType::Armoured => 2,
+    }
+}
+
+pub fn terrain_defence_bonus(field: types::Field) -> i32 {
+    match field {
+        types::Field::Plain => 0,
+        types::Field::Forest => 1,
+        types::Field::Hill => 2,
+    }
+}
+
+pub fn river_defence_bonus(river: types::River) -> i32 {
+    match river {
+        types::River::Small => 2,
+        types::River::Stream => 1,
+    }
+}
+
+/// Lowest roll of a die, increased by the odds, that eliminates the defender.
+pub const ELIMINATING_ROLL: i32 = 4;
+
+/// Rolls a six-sided die.
+pub fn roll_die(dice: &mut rng::Rng) -> i32 {
+    dice.below(6) as i32 + 1
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CombatResult {
+    DefenderEliminated,
+    AttackRepelled,
+}
+
+/// Attacks made during the current side turn, each unit attacks at most once.
+/// The attacker rolls a die and adds the odds, its attack less the defence,
+/// so even odds win half of the time and three points more always win.
+#[derive(Debug, Default)]
+pub struct CombatSystem {
+    attacked: HashSet<i32>,
+}
+
+impl CombatSystem {
+    pub fn new() -> Self {
+        CombatSystem::default()
+    }
+
+    pub fn has_attacked(&self, unit_id: i32) -> bool {
+        self.attacked.contains(&unit_id)
+    }
+
+    /// Units that attacked this turn, sorted by id.
+    pub fn attacked_units(&self) -> Vec<i32> {
+        let mut ids: Vec<_> = self.attacked.iter().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    pub fn mark_attacked(&mut self, unit_id: i32) {
+        self.attacked.insert(unit_id);
+    }
+
+    /// Lets all units attack again.
+    pub fn reset(&mut self) {
+        self.attacked.clear();
+    }
+
+    /// Defence of the unit standing on the hex, attacked from the neighboring one.
+    pub fn defence(
+        defender: &Unit,
+        hex: hexagons::HexCoordinates,
+        from: hexagons::HexCoordinates,
+        map: &map::Map,
+    ) -> i32 {
+        let terrain = map
+            .hex_at(hex)
+            .map_or(0, |site| terrain_defence_bonus(site.kind()));
+        let river = map
+            .river_between(hex, from)
+            .map_or(0, |river| river_defence_bonus(river.kind()));
+        defence_strength(defender.kind()) + terrain + river
+    }
+
+    /// Resolves an attack between units on neighboring hexes,
+    /// an eliminated defender is removed from the map.
+    pub fn attack(
+        &mut self,
+        attacker: &Unit,
+        defender: &Unit,
+        moving: &mut MovingSystem,
+        map: &map::Map,
+        dice: &mut rng::Rng,
+    ) -> Result<CombatResult, &'static str> {
+        if attacker.side() == defender.side() {
+            return Err("Units of the same side cannot attack each other.");
+        }
+        if self.has_attacked(attacker.id()) {
+            return Err("Unit has already attacked this turn.");
+        }
+        let position = |id: i32| {
+            moving
+                .components
+                .get(&id)
+                .and_then(|mc| mc.occupation)
+                .ok_or("Unit doesn't occupy any hex.")
+        };
+        let from = position(attacker.id())?;
+        let hex = position(defender.id())?;
+        if from.distance_to(&hex) != 1 {
+            return Err("Only units on neighboring hexes can be attacked.");
+        }
+
+        self.mark_attacked(attacker.id());
+        let attack = attack_strength(attacker.kind());
+        let defence = Self::defence(defender, hex, from, map);
+        let roll = roll_die(dice);
+        debug!(
+            "Unit {} attacks unit {}, {} against {}, rolled {}.",
+            attacker.id(),
+            defender.id(),
+            attack,
+            defence,
+            roll
+        );
+        if roll + attack - defence >= ELIMINATING_ROLL {
+            moving
+                .components
+                .get_mut(&defender.id())
+                .unwrap()
+                .occupation = None;
+            Ok(CombatResult::DefenderEliminated)
+        } else {
+            Ok(CombatResult::AttackRepelled)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maps::hexagons::HexCoordinates;
+    use crate::maps::map::*;
+    use crate::maps::types::*;
+    use crate::systems::components::MovingComponent;
+    use crate::units::unit::Side;
+
+    #[test]
+    fn resolving_attacks() {
+        let mut map = Map::new();
+        let plain = HexCoordinates::new_axial(0, 0);
+        let hill = HexCoordinates::new_axial(0, 1);
+        let far = HexCoordinates::new_axial(0, 3);
+        map.insert_hex(HexSite::new(plain, Field::Plain)).unwrap();
+        map.insert_hex(HexSite::new(hill, Field::Hill)).unwrap();
+        map.insert_hex(HexSite::new(far, Field::Plain)).unwrap();
+
+        let units = [
+            Unit::new("tank".to_owned(), UnitType::Armoured, Side::Blue, 0),
+            Unit::new("rifles".to_owned(), UnitType::Infantry, Side::Red, 1),
+            Unit::new("guards".to_owned(), UnitType::Infantry, Side::Red, 2),
+            Unit::new("escort".to_owned(), UnitType::Infantry, Side::Blue, 3),
+        ];
+        let mut moving = MovingSystem::new();
+        for (unit, hex) in units.iter().zip([plain, hill, far, plain].iter()) {
+            let mut mc = MovingComponent::new(unit.id(), 8);
+            mc.occupation = Some(*hex);
+            moving.components.insert(unit.id(), mc);
+        }
+
+        let mut combat = CombatSystem::new();
+        let mut dice = rng::Rng::new(7);
+        assert!(combat
+            .attack(&units[0], &units[3], &mut moving, &map, &mut dice)
+            .is_err());
+        assert!(combat
+            .attack(&units[0], &units[2], &mut moving, &map, &mut dice)
+            .is_err());
+        assert!(!combat.has_attacked(0));
+
+        //4 against 3 + 2 for the hill, the die has to roll 5 or 6
+        let roll = roll_die(&mut dice.clone());
+        let expected = if roll >= 5 {
+            CombatResult::DefenderEliminated
+        } else {
+            CombatResult::AttackRepelled
+        };
+        assert_eq!(
+            combat.attack(&units[0], &units[1], &mut moving, &map, &mut dice),
+            Ok(expected)
+        );
+        assert!(combat.has_attacked(0));
+        assert!(combat
+            .attack(&units[0], &units[1], &mut moving, &map, &mut dice)
+            .is_err());
+
+        //4 against 3 on a plain, the die has to roll 3 or more
+        moving.components.get_mut(&0).unwrap().occupation = Some(hill);
+        loop {
+            combat.reset();
+            moving.components.get_mut(&1).unwrap().occupation = Some(plain);
+            let roll = roll_die(&mut dice.clone());
+            let res = combat.attack(&units[0], &units[1], &mut moving, &map, &mut dice);
+            if roll >= 3 {
+                assert_eq!(res, Ok(CombatResult::DefenderEliminated));
+                break;
+            }
+            assert_eq!(res, Ok(CombatResult::AttackRepelled));
+        }
+        assert_eq!(moving.components[&1].occupation, None);
+    }
+
+    #[test]
+    fn rolling_dice() {
+        let mut dice = rng::Rng::new(1);
+        let mut counts = [0; 6];
+        for _ in 0..600 {
+            counts[roll_die(&mut dice) as usize - 1] += 1;
+        }
+        assert!(counts.iter().all(|count| *count > 50));
+
+        //the same seed rolls the same numbers
+        let rolls = |seed| {
+            let mut dice = rng::Rng::new(seed);
+            (0..10).map(|_| roll_die(&mut dice)).collect::<Vec<_>>()
+        };
+        assert_eq!(rolls(3), rolls(3));
+        assert_ne!(rolls(3), rolls(4));
+    }
+}
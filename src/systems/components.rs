@@ -3,7 +3,7 @@ use crate::maps::*;
 pub trait Component {
     fn update(&mut self) {}
 
-    fn owner_id(&self)-> i32;
+    fn owner_id(&self) -> i32;
 }
 
 #[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
@@ -15,22 +15,29 @@ pub struct MovingComponent {
 }
 
 impl Component for MovingComponent {
-    fn owner_id(&self)-> i32 {
+    fn owner_id(&self) -> i32 {
         self.owner_id
     }
 }
 
-
 impl MovingComponent {
-    pub  fn new(owner_id: i32, moving_pts: i32) -> Self {
+    pub fn new(owner_id: i32, moving_pts: i32) -> Self {
         MovingComponent {
             def_moving_pts: moving_pts,
             current_moving_pts: moving_pts,
             occupation: None,
-            owner_id
+            owner_id,
         }
     }
 
+    pub fn moving_pts(&self) -> i32 {
+        self.current_moving_pts
+    }
+
+    pub fn default_moving_pts(&self) -> i32 {
+        self.def_moving_pts
+    }
+
     pub fn reduce_mv_pts(&mut self, cost: i32) -> Result<&mut Self, &'static str> {
         if self.current_moving_pts < cost {
             return Err("Cost of movement higher than available moving_pts.");
@@ -39,8 +46,11 @@ impl MovingComponent {
         Ok(self)
     }
 
+    pub fn set_mv_pts(&mut self, moving_pts: i32) {
+        self.current_moving_pts = moving_pts;
+    }
+
     pub fn reest_mv_pts(&mut self) {
         self.current_moving_pts = self.def_moving_pts;
     }
-
 }
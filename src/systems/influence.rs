@@ -0,0 +1,233 @@
+use super::combat;
+use super::moving::{self, MovementRange, MovingSystem};
+use super::vision::FogOfWar;
+use crate::maps::*;
+use crate::units::unit::Side;
+use crate::units::unit_set::UnitSet;
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Hexes the unit can reach on its next turn, when it gets all its moving points back.
+pub fn reach_next_turn(
+    unit_id: i32,
+    moving: &MovingSystem,
+    map: &map::Map,
+    blocked: &HashSet<hexagons::HexCoordinates>,
+) -> MovementRange {
+    let mut next_turn = MovingSystem::new();
+    if let Some(mc) = moving.components.get(&unit_id) {
+        let mut mc = *mc;
+        mc.reest_mv_pts();
+        next_turn.components.insert(unit_id, mc);
+    }
+    next_turn
+        .movement_range(unit_id, map, blocked)
+        .unwrap_or_default()
+}
+
+//units attack their neighbors, so they strike one hex beyond their reach
+fn attack_zone(
+    unit_id: i32,
+    moving: &MovingSystem,
+    map: &map::Map,
+    blocked: &HashSet<hexagons::HexCoordinates>,
+) -> HashSet<hexagons::HexCoordinates> {
+    reach_next_turn(unit_id, moving, map, blocked)
+        .costs
+        .keys()
+        .flat_map(|hex| hex.neighbors().to_vec())
+        .filter(|hex| map.hex_at(*hex).is_some())
+        .collect()
+}
+
+//moving points needed to get from each hex to the nearest objective, searched from
+//all objectives at once, so entering a hex costs what leaving it does here
+fn distances_to(
+    objectives: &[hexagons::HexCoordinates],
+    map: &map::Map,
+) -> HashMap<hexagons::HexCoordinates, i32> {
+    let mut distances = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    for hex in objectives.iter() {
+        distances.insert(*hex, 0);
+        queue.push(Reverse((0, hex.q(), hex.p())));
+    }
+
+    while let Some(Reverse((cost, q, p))) = queue.pop() {
+        let hex = hexagons::HexCoordinates::new_axial(p, q);
+        if cost > distances[&hex] {
+            continue;
+        }
+        let site = match map.hex_at(hex) {
+            Some(site) => site,
+            None => continue,
+        };
+        for next in hex.neighbors().iter() {
+            if map.hex_at(*next).is_none() {
+                continue;
+            }
+            let mut next_cost = cost + moving::cost_of_entering_hex(site.kind());
+            if let Some(river) = map.river_between(hex, *next) {
+                next_cost += moving::cost_of_crossing_river(river.kind());
+            }
+            if distances.get(next).is_none_or(|c| next_cost < *c) {
+                distances.insert(*next, next_cost);
+                queue.push(Reverse((next_cost, next.q(), next.p())));
+            }
+        }
+    }
+    distances
+}
+
+/// Balance of power over the map, as one side sees it before the next turn.
+/// The objectives are the enemy units, destroying them is the only way to win.
+#[derive(Debug, Clone, Default)]
+pub struct InfluenceMap {
+    threat: HashMap<hexagons::HexCoordinates, i32>,
+    support: HashMap<hexagons::HexCoordinates, i32>,
+    distance: HashMap<hexagons::HexCoordinates, i32>,
+}
+
+impl InfluenceMap {
+    /// With fog of war only the enemy units in sight are counted.
+    pub fn new(
+        side: Side,
+        units: &UnitSet,
+        moving: &MovingSystem,
+        map: &map::Map,
+        fog: Option<&FogOfWar>,
+    ) -> Self {
+        let mut influence = InfluenceMap::default();
+        //enemies out of sight neither count nor stand in the way of the side's units
+        let in_play: Vec<_> = units
+            .units()
+            .iter()
+            .filter(|(id, unit)| {
+                unit.side() == side || fog.is_none_or(|fog| fog.shows_unit(**id, units, moving))
+            })
+            .filter_map(|(id, unit)| {
+                let hex = moving.components.get(id)?.occupation?;
+                Some((*id, unit, hex))
+            })
+            .collect();
+        let hexes_of = |own: bool| -> HashSet<_> {
+            in_play
+                .iter()
+                .filter(|(_, unit, _)| (unit.side() == side) == own)
+                .map(|(_, _, hex)| *hex)
+                .collect()
+        };
+        let (own_hexes, enemy_hexes) = (hexes_of(true), hexes_of(false));
+
+        let mut objectives = Vec::new();
+        for (id, unit, hex) in in_play.iter() {
+            let (values, blocked) = if unit.side() == side {
+                (&mut influence.support, &enemy_hexes)
+            } else {
+                objectives.push(*hex);
+                (&mut influence.threat, &own_hexes)
+            };
+            for hex in attack_zone(*id, moving, map, blocked) {
+                *values.entry(hex).or_default() += combat::attack_strength(unit.kind());
+            }
+        }
+
+        influence.distance = distances_to(&objectives, map);
+        influence
+    }
+
+    /// Sum of the attack strength of the enemy units, that can attack the hex next turn.
+    pub fn threat(&self, hex: &hexagons::HexCoordinates) -> i32 {
+        self.threat.get(hex).cloned().unwrap_or(0)
+    }
+
+    /// Sum of the attack strength of the side's own units, that can attack the hex next turn.
+    pub fn support(&self, hex: &hexagons::HexCoordinates) -> i32 {
+        self.support.get(hex).cloned().unwrap_or(0)
+    }
+
+    /// Moving points needed to reach the nearest objective, None if there are none left
+    /// or there's no way to any of them.
+    pub fn distance_to_objective(&self, hex: &hexagons::HexCoordinates) -> Option<i32> {
+        self.distance.get(hex).cloned()
+    }
+
+    /// Hexes the enemy can attack next turn, with the threat to each of them.
+    pub fn danger_zone(&self) -> impl Iterator<Item = (&hexagons::HexCoordinates, &i32)> {
+        self.threat.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Scenario;
+    use crate::maps::hexagons::HexCoordinates;
+    use crate::maps::map::*;
+    use crate::maps::types::*;
+
+    #[test]
+    fn weighing_threats() {
+        let scenario = Scenario::test();
+        let influence = |fog| {
+            InfluenceMap::new(
+                Side::Blue,
+                &scenario.units,
+                &scenario.systems.moving,
+                &scenario.map,
+                fog,
+            )
+        };
+        let blue = HexCoordinates::new_axial(0, 0);
+        let red = HexCoordinates::new_axial(-2, 3);
+        let all = influence(None);
+
+        //blue mechanized and infantry units, and the red armoured one, reach each other
+        assert_eq!(all.support(&blue), 5);
+        assert_eq!(all.threat(&blue), 4);
+        assert_eq!(all.support(&red), 5);
+        assert_eq!(all.distance_to_objective(&red), Some(0));
+        //every hex on the way costs at least a moving point
+        assert!(all.distance_to_objective(&blue) >= Some(blue.distance_to(&red)));
+        assert!(all.danger_zone().all(|(_, threat)| *threat == 4));
+        let far = HexCoordinates::new_axial(40, 40);
+        assert_eq!(all.threat(&far), 0);
+        assert_eq!(all.distance_to_objective(&far), None);
+
+        //red unit is out of sight of the blue ones
+        let mut fog = FogOfWar::new(Side::Blue);
+        fog.update(&scenario.units, &scenario.systems.moving, &scenario.map);
+        let seen = influence(Some(&fog));
+        assert_eq!(seen.threat(&blue), 0);
+        assert_eq!(seen.distance_to_objective(&blue), None);
+        assert_eq!(seen.support(&blue), 5);
+    }
+
+    #[test]
+    fn measuring_the_way_to_objectives() {
+        let hex = |q| HexCoordinates::new_axial(q, 0);
+        let mut map = Map::new();
+        for (q, kind) in [(0, Field::Plain), (1, Field::Plain), (2, Field::Forest)].iter() {
+            map.insert_hex(HexSite::new(hex(*q), *kind)).unwrap();
+        }
+        //the last hex is beyond a hole in the map
+        map.insert_hex(HexSite::new(hex(3), Field::Plain)).unwrap();
+        map.insert_hex(HexSite::new(hex(5), Field::Plain)).unwrap();
+
+        let distances = distances_to(&[hex(0)], &map);
+        assert_eq!(distances.get(&hex(0)), Some(&0));
+        assert_eq!(distances.get(&hex(1)), Some(&1));
+        //leaving the forest is free, entering it isn't
+        assert_eq!(distances.get(&hex(2)), Some(&2));
+        assert_eq!(distances.get(&hex(3)), Some(&4));
+        assert_eq!(distances.get(&hex(5)), None);
+
+        //a stream right before the objective has to be crossed from anywhere
+        map.insert_river(RiverSite::new(hex(0), hex(1), River::Stream).unwrap())
+            .unwrap();
+        let distances = distances_to(&[hex(0)], &map);
+        assert_eq!(distances.get(&hex(1)), Some(&5));
+        assert_eq!(distances.get(&hex(3)), Some(&8));
+    }
+}
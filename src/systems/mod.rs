@@ -1,16 +1,22 @@
+pub mod combat;
 pub mod components;
+pub mod influence;
 pub mod moving;
+pub mod vision;
 
+use combat::CombatSystem;
 use moving::MovingSystem;
 
 pub struct GameSystems {
     pub moving: MovingSystem,
+    pub combat: CombatSystem,
 }
 
 impl GameSystems {
     pub fn new() -> Self {
         Self {
             moving: MovingSystem::new(),
+            combat: CombatSystem::new(),
         }
     }
-}
\ No newline at end of file
+}
@@ -1,7 +1,51 @@
 use super::components::*;
 use crate::maps::*;
 
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+pub fn cost_of_entering_hex(field: types::Field) -> i32 {
+    match field {
+        types::Field::Plain => 1,
+        types::Field::Forest => 2,
+        types::Field::Hill => 3,
+    }
+}
+
+pub fn cost_of_crossing_river(river: types::River) -> i32 {
+    match river {
+        types::River::Small => 6,
+        types::River::Stream => 4,
+    }
+}
+
+/// Hexes that a unit can reach with its current moving points.
+#[derive(Debug, Clone, Default)]
+pub struct MovementRange {
+    pub costs: HashMap<hexagons::HexCoordinates, i32>,
+    pub previous: HashMap<hexagons::HexCoordinates, hexagons::HexCoordinates>,
+}
+
+impl MovementRange {
+    pub fn contains(&self, hex: &hexagons::HexCoordinates) -> bool {
+        self.costs.contains_key(hex)
+    }
+
+    /// Returns the cheapest path from the start to the hex, both ends included.
+    pub fn path_to(&self, hex: hexagons::HexCoordinates) -> Option<Vec<hexagons::HexCoordinates>> {
+        if !self.contains(&hex) {
+            return None;
+        }
+        let mut path = vec![hex];
+        let mut current = hex;
+        while let Some(prev) = self.previous.get(&current) {
+            path.push(*prev);
+            current = *prev;
+        }
+        path.reverse();
+        Some(path)
+    }
+}
 
 pub struct MovingSystem {
     pub components: HashMap<i32, MovingComponent>,
@@ -23,4 +67,175 @@ impl MovingSystem {
             .filter(|(_, mc)| mc.occupation == Some(hex))
             .collect()
     }
+
+    /// Dijkstra search over the map limited by the unit's moving points.
+    /// Blocked hexes, held by the enemy, can be neither entered nor passed through.
+    pub fn movement_range(
+        &self,
+        unit_id: i32,
+        map: &map::Map,
+        blocked: &HashSet<hexagons::HexCoordinates>,
+    ) -> Result<MovementRange, &'static str> {
+        let mc = self
+            .components
+            .get(&unit_id)
+            .ok_or("Unit not registered in moving system.")?;
+        let start = mc.occupation.ok_or("Unit doesn't occupy any hex.")?;
+
+        let mut range = MovementRange::default();
+        range.costs.insert(start, 0);
+
+        let mut queue = BinaryHeap::new();
+        queue.push(Reverse((0, start.q(), start.p())));
+
+        while let Some(Reverse((cost, q, p))) = queue.pop() {
+            let hex = hexagons::HexCoordinates::new_axial(p, q);
+            if cost > range.costs[&hex] {
+                continue;
+            }
+
+            for next in hex.neighbors().iter() {
+                let site = match map.hex_at(*next) {
+                    Some(site) if !blocked.contains(next) => site,
+                    _ => continue,
+                };
+
+                let mut next_cost = cost + cost_of_entering_hex(site.kind());
+                if let Some(river) = map.river_between(hex, *next) {
+                    next_cost += cost_of_crossing_river(river.kind());
+                }
+
+                if next_cost > mc.moving_pts() {
+                    continue;
+                }
+                if range.costs.get(next).is_none_or(|c| next_cost < *c) {
+                    range.costs.insert(*next, next_cost);
+                    range.previous.insert(*next, hex);
+                    queue.push(Reverse((next_cost, next.q(), next.p())));
+                }
+            }
+        }
+
+        Ok(range)
+    }
+
+    /// Moves the unit along the cheapest path, paying its cost with moving points.
+    pub fn move_unit(
+        &mut self,
+        unit_id: i32,
+        target: hexagons::HexCoordinates,
+        map: &map::Map,
+        blocked: &HashSet<hexagons::HexCoordinates>,
+    ) -> Result<&mut MovingComponent, &'static str> {
+        if blocked.contains(&target) {
+            return Err("Hex is occupied by the enemy.");
+        }
+        let range = self.movement_range(unit_id, map, blocked)?;
+        let cost = *range
+            .costs
+            .get(&target)
+            .ok_or("Target hex is out of the unit's range.")?;
+
+        let mc = self.components.get_mut(&unit_id).unwrap();
+        mc.reduce_mv_pts(cost)?;
+        mc.occupation = Some(target);
+        Ok(mc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maps::hexagons::HexCoordinates;
+    use crate::maps::map::*;
+    use crate::maps::types::*;
+
+    //a row of hexes: plain, forest, plain with a stream between the last two
+    fn test_setup() -> (Map, MovingSystem) {
+        let mut map = Map::new();
+        for (q, kind) in [Field::Plain, Field::Forest, Field::Plain, Field::Plain]
+            .iter()
+            .enumerate()
+        {
+            map.insert_hex(HexSite::new(HexCoordinates::new_axial(0, q as i32), *kind))
+                .unwrap();
+        }
+        map.insert_river(
+            RiverSite::new(
+                HexCoordinates::new_axial(0, 2),
+                HexCoordinates::new_axial(0, 3),
+                River::Stream,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let mut system = MovingSystem::new();
+        let mut mc = MovingComponent::new(0, 8);
+        mc.occupation = Some(HexCoordinates::new_axial(0, 0));
+        system.components.insert(0, mc);
+        (map, system)
+    }
+
+    #[test]
+    fn movement_costs() {
+        let (map, system) = test_setup();
+        let range = system.movement_range(0, &map, &HashSet::new()).unwrap();
+
+        assert_eq!(range.costs[&HexCoordinates::new_axial(0, 0)], 0);
+        assert_eq!(range.costs[&HexCoordinates::new_axial(0, 1)], 2);
+        assert_eq!(range.costs[&HexCoordinates::new_axial(0, 2)], 3);
+        assert_eq!(range.costs[&HexCoordinates::new_axial(0, 3)], 8);
+
+        assert_eq!(
+            range.path_to(HexCoordinates::new_axial(0, 2)).unwrap(),
+            vec![
+                HexCoordinates::new_axial(0, 0),
+                HexCoordinates::new_axial(0, 1),
+                HexCoordinates::new_axial(0, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn moving_units() {
+        let (map, mut system) = test_setup();
+
+        let mc = system
+            .move_unit(0, HexCoordinates::new_axial(0, 2), &map, &HashSet::new())
+            .unwrap();
+        assert_eq!(mc.moving_pts(), 5);
+        assert_eq!(mc.occupation, Some(HexCoordinates::new_axial(0, 2)));
+
+        let range = system.movement_range(0, &map, &HashSet::new()).unwrap();
+        assert_eq!(range.costs[&HexCoordinates::new_axial(0, 3)], 5);
+        assert_eq!(range.costs[&HexCoordinates::new_axial(0, 0)], 3);
+
+        let mc = system
+            .move_unit(0, HexCoordinates::new_axial(0, 3), &map, &HashSet::new())
+            .unwrap();
+        assert_eq!(mc.moving_pts(), 0);
+        assert!(system
+            .move_unit(0, HexCoordinates::new_axial(0, 2), &map, &HashSet::new())
+            .is_err());
+        assert!(system
+            .move_unit(1, HexCoordinates::origin(), &map, &HashSet::new())
+            .is_err());
+    }
+    #[test]
+    fn blocked_hexes() {
+        let (map, mut system) = test_setup();
+        let blocked: HashSet<_> = [HexCoordinates::new_axial(0, 1)].iter().cloned().collect();
+
+        //the map is a single row, so there's no way around the blocked hex
+        let range = system.movement_range(0, &map, &blocked).unwrap();
+        assert_eq!(range.costs.len(), 1);
+        assert!(system
+            .move_unit(0, HexCoordinates::new_axial(0, 1), &map, &blocked)
+            .is_err());
+        assert!(system
+            .move_unit(0, HexCoordinates::new_axial(0, 2), &map, &blocked)
+            .is_err());
+        assert_eq!(system.components[&0].moving_pts(), 8);
+    }
 }
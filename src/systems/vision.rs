@@ -0,0 +1,99 @@
+use super::moving::MovingSystem;
+use crate::maps::*;
+use crate::units::unit::Side;
+use crate::units::unit_set::UnitSet;
+
+use std::collections::HashSet;
+
+/// Distance in hexes, at which units spot the enemy.
+pub const SIGHT_RANGE: i32 = 2;
+
+/// Hexes of the map within sight of the units of the side.
+pub fn visible_hexes(
+    side: Side,
+    units: &UnitSet,
+    moving: &MovingSystem,
+    map: &map::Map,
+) -> HashSet<hexagons::HexCoordinates> {
+    moving
+        .components
+        .iter()
+        .filter(|(id, _)| units.units().get(id).map(|u| u.side()) == Some(side))
+        .filter_map(|(_, mc)| mc.occupation)
+        .flat_map(|hex| shapes::hexagon_coords(hex, SIGHT_RANGE))
+        .filter(|hex| map.hex_at(*hex).is_some())
+        .collect()
+}
+
+/// What one side knows about the map, enemy units are seen only on the visible hexes,
+/// and the terrain of the hexes that were never seen stays unknown.
+#[derive(Debug, Clone)]
+pub struct FogOfWar {
+    side: Side,
+    visible: HashSet<hexagons::HexCoordinates>,
+    explored: HashSet<hexagons::HexCoordinates>,
+}
+
+impl FogOfWar {
+    pub fn new(side: Side) -> Self {
+        FogOfWar {
+            side,
+            visible: HashSet::new(),
+            explored: HashSet::new(),
+        }
+    }
+
+    /// Looks around from the current positions of the units, explored hexes are remembered.
+    pub fn update(&mut self, units: &UnitSet, moving: &MovingSystem, map: &map::Map) {
+        self.visible = visible_hexes(self.side, units, moving, map);
+        self.explored.extend(self.visible.iter().cloned());
+    }
+
+    pub fn is_visible(&self, hex: &hexagons::HexCoordinates) -> bool {
+        self.visible.contains(hex)
+    }
+
+    pub fn is_explored(&self, hex: &hexagons::HexCoordinates) -> bool {
+        self.explored.contains(hex)
+    }
+
+    /// Whether the unit can be seen by the side, which always knows where its own units are.
+    pub fn shows_unit(&self, unit_id: i32, units: &UnitSet, moving: &MovingSystem) -> bool {
+        let occupation = moving.components.get(&unit_id).and_then(|mc| mc.occupation);
+        match (units.units().get(&unit_id), occupation) {
+            (Some(unit), _) if unit.side() == self.side => true,
+            (Some(_), Some(hex)) => self.is_visible(&hex),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Scenario;
+    use crate::maps::hexagons::HexCoordinates;
+
+    #[test]
+    fn seeing_enemies() {
+        let mut scenario = Scenario::test();
+        let blue = HexCoordinates::new_axial(0, 0);
+        let mut fog = FogOfWar::new(Side::Blue);
+        fog.update(&scenario.units, &scenario.systems.moving, &scenario.map);
+        assert!(fog.is_visible(&blue));
+        assert!(fog.is_visible(&HexCoordinates::new_axial(SIGHT_RANGE, 0)));
+        assert!(!fog.is_explored(&HexCoordinates::new_axial(SIGHT_RANGE + 1, 0)));
+        //red unit 2 stands on (-2, 3), too far away
+        assert!(fog.shows_unit(0, &scenario.units, &scenario.systems.moving));
+        assert!(!fog.shows_unit(2, &scenario.units, &scenario.systems.moving));
+
+        for id in 0..2 {
+            let mc = scenario.systems.moving.components.get_mut(&id).unwrap();
+            mc.occupation = Some(HexCoordinates::new_axial(-1, 3));
+        }
+        fog.update(&scenario.units, &scenario.systems.moving, &scenario.map);
+        assert!(fog.shows_unit(2, &scenario.units, &scenario.systems.moving));
+        assert!(!fog.is_visible(&blue));
+        assert!(fog.is_explored(&blue));
+    }
+}
@@ -0,0 +1,108 @@
+use std::str::FromStr;
+
+use crate::maps::hexagons::OffsetCoordinates;
+
+pub const HELP: &str = "Commands:
+  map               draw the map
+  units             list units with their positions
+  info HEX          terrain of the hex and units on it
+  threats           enemy attack strength that can reach each hex next turn
+  move UNIT HEX     move the unit in the movement phase
+  attack UNIT UNIT  attack a neighboring enemy in the combat phase
+  place UNIT HEX    deploy the unit in the first movement phase of its side
+  undo              take back the last move
+  redo              make the move taken back again
+  next              go to the next phase
+  end               end the turn of the current side
+  quit              leave the game
+Hexes are given by offset coordinates, e.g. 0304 or 3,4, units by their ids.
+";
+
+/// Command typed by the player, every one of them has a single letter shortcut.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Command {
+    Help,
+    Map,
+    Units,
+    Info(OffsetCoordinates),
+    Threats,
+    Move(i32, OffsetCoordinates),
+    Attack(i32, i32),
+    Place(i32, OffsetCoordinates),
+    Undo,
+    Redo,
+    NextPhase,
+    EndTurn,
+    Quit,
+}
+
+fn unit_id(word: &str) -> Result<i32, &'static str> {
+    word.parse().map_err(|_| "Invalid unit id.")
+}
+
+impl FromStr for Command {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<_> = s.split_whitespace().collect();
+        match words[..] {
+            ["help"] | ["h"] | ["?"] => Ok(Command::Help),
+            ["map"] | ["p"] => Ok(Command::Map),
+            ["units"] | ["u"] => Ok(Command::Units),
+            ["info", hex] | ["i", hex] => Ok(Command::Info(hex.parse()?)),
+            ["threats"] | ["t"] => Ok(Command::Threats),
+            ["move", unit, hex] | ["m", unit, hex] => {
+                Ok(Command::Move(unit_id(unit)?, hex.parse()?))
+            }
+            ["attack", attacker, defender] | ["a", attacker, defender] => {
+                Ok(Command::Attack(unit_id(attacker)?, unit_id(defender)?))
+            }
+            ["place", unit, hex] | ["l", unit, hex] => {
+                Ok(Command::Place(unit_id(unit)?, hex.parse()?))
+            }
+            ["undo"] | ["z"] => Ok(Command::Undo),
+            ["redo"] | ["y"] => Ok(Command::Redo),
+            ["next"] | ["n"] => Ok(Command::NextPhase),
+            ["end"] | ["e"] => Ok(Command::EndTurn),
+            ["quit"] | ["q"] => Ok(Command::Quit),
+            [] => Err("Empty command."),
+            _ => Err("Unknown command, type help for the list of commands."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_commands() {
+        assert_eq!("help".parse(), Ok(Command::Help));
+        assert_eq!(" units ".parse(), Ok(Command::Units));
+        assert_eq!(
+            "move 2 0304".parse(),
+            Ok(Command::Move(2, OffsetCoordinates::new(3, 4)))
+        );
+        assert_eq!(
+            "m 0  -1,5".parse(),
+            Ok(Command::Move(0, OffsetCoordinates::new(-1, 5)))
+        );
+        assert_eq!("a 0 2".parse(), Ok(Command::Attack(0, 2)));
+        assert_eq!(
+            "info C7".parse(),
+            Ok(Command::Info(OffsetCoordinates::new(2, 7)))
+        );
+        assert_eq!("e".parse(), Ok(Command::EndTurn));
+        assert_eq!("threats".parse(), Ok(Command::Threats));
+        assert_eq!("z".parse(), Ok(Command::Undo));
+        assert_eq!(
+            "place 1 0304".parse(),
+            Ok(Command::Place(1, OffsetCoordinates::new(3, 4)))
+        );
+
+        assert!("".parse::<Command>().is_err());
+        assert!("move x 0304".parse::<Command>().is_err());
+        assert!("move 1".parse::<Command>().is_err());
+        assert!("fly 1 0304".parse::<Command>().is_err());
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+
+use crate::game::Scenario;
+use crate::maps::hexagons::{HexCoordinates, OffsetKind};
+use crate::maps::types::Field;
+use crate::render::scene::stacked_units;
+use crate::systems::influence::InfluenceMap;
+use crate::systems::vision::FogOfWar;
+use crate::units::unit::{Side, UnitType};
+
+//width of a hex in characters, odd rows are shifted right by half of it
+const CELL: usize = 4;
+//room for row numbers
+const MARGIN: usize = 5;
+
+/// How the map is drawn in the terminal.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct TextOptions {
+    /// Symbols and box drawing characters instead of plain ASCII.
+    pub unicode: bool,
+    /// ANSI colors for the units of both sides.
+    pub color: bool,
+}
+
+pub fn field_glyph(kind: Field, unicode: bool) -> char {
+    match (kind, unicode) {
+        (Field::Plain, false) => '.',
+        (Field::Forest, false) => '%',
+        (Field::Hill, false) => '^',
+        (Field::Plain, true) => '·',
+        (Field::Forest, true) => '♣',
+        (Field::Hill, true) => '▲',
+    }
+}
+
+/// Letter of the unit kind, uppercase for blue units and lowercase for red ones,
+/// so the sides can be told apart without colors.
+pub fn unit_glyph(kind: UnitType, side: Side) -> char {
+    let glyph = match kind {
+        UnitType::Infantry => 'I',
+        UnitType::Mechanized => 'M',
+        UnitType::Armoured => 'A',
+    };
+    match side {
+        Side::Blue => glyph,
+        Side::Red => glyph.to_ascii_lowercase(),
+    }
+}
+
+//hexes never seen by the player
+fn unknown_glyph(unicode: bool) -> char {
+    if unicode {
+        '░'
+    } else {
+        '#'
+    }
+}
+
+pub fn legend(unicode: bool) -> String {
+    let rivers = if unicode { "│ ╱ ╲" } else { "| / \\" };
+    format!(
+        "{} plain  {} forest  {} hill  {} rivers  I M A blue units  i m a red units\n",
+        field_glyph(Field::Plain, unicode),
+        field_glyph(Field::Forest, unicode),
+        field_glyph(Field::Hill, unicode),
+        rivers
+    )
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Cell {
+    glyph: char,
+    side: Option<Side>,
+}
+
+const BLANK: Cell = Cell {
+    glyph: ' ',
+    side: None,
+};
+
+/// Draws the map in "odd-r" offset coordinates, numbered along the edges.
+/// Each row of hexes takes two lines, the second one shows rivers between rows.
+/// Stacks of units show the unit on top and their size.
+/// With fog of war, unexplored terrain and enemy units out of sight are hidden.
+pub fn draw_map(scenario: &Scenario, options: TextOptions, fog: Option<&FogOfWar>) -> String {
+    draw(scenario, options, fog, None)
+}
+
+/// Map with the threat to each hex the enemy can attack next turn, on the left of the hex.
+pub fn draw_threats(
+    scenario: &Scenario,
+    options: TextOptions,
+    fog: Option<&FogOfWar>,
+    influence: &InfluenceMap,
+) -> String {
+    draw(scenario, options, fog, Some(influence))
+}
+
+fn draw(
+    scenario: &Scenario,
+    options: TextOptions,
+    fog: Option<&FogOfWar>,
+    influence: Option<&InfluenceMap>,
+) -> String {
+    let map = &scenario.map;
+    let offsets: Vec<_> = map
+        .hexes()
+        .values()
+        .map(|site| site.coord().to_offset(OffsetKind::OddR))
+        .collect();
+    if offsets.is_empty() {
+        return "The map is empty.\n".to_owned();
+    }
+    let min_col = offsets.iter().map(|o| o.col).min().unwrap();
+    let max_col = offsets.iter().map(|o| o.col).max().unwrap();
+    let min_row = offsets.iter().map(|o| o.row).min().unwrap();
+    let max_row = offsets.iter().map(|o| o.row).max().unwrap();
+
+    //column and line of the middle character of the hex
+    let position = |hex: HexCoordinates| {
+        let offset = hex.to_offset(OffsetKind::OddR);
+        let x = MARGIN + 1 + CELL * (offset.col - min_col) as usize + 2 * (offset.row & 1) as usize;
+        (x, 2 * (offset.row - min_row) as usize)
+    };
+    let width = MARGIN + CELL * (max_col - min_col + 1) as usize + 2;
+    let height = 2 * (max_row - min_row) as usize + 1;
+    let mut grid = vec![vec![BLANK; width]; height];
+
+    let explored = |hex| fog.is_none_or(|fog| fog.is_explored(hex));
+    for site in map.hexes().values() {
+        let (x, y) = position(*site.coord());
+        let glyph = if explored(site.coord()) {
+            field_glyph(site.kind(), options.unicode)
+        } else {
+            unknown_glyph(options.unicode)
+        };
+        for cell in grid[y][x - 1..=x + 1].iter_mut() {
+            cell.glyph = glyph;
+        }
+    }
+
+    //hexsides are perpendicular to the line connecting the hex centers
+    for site in map.rivers().values() {
+        let (side1, side2) = site.sides();
+        if !explored(side1) || !explored(side2) {
+            continue;
+        }
+        let (a, b) = (position(*side1), position(*side2));
+        let (upper, lower) = if a.1 <= b.1 { (a, b) } else { (b, a) };
+        let glyph = match (upper.1 == lower.1, upper.0 < lower.0, options.unicode) {
+            (true, _, false) => '|',
+            (false, true, false) => '/',
+            (false, false, false) => '\\',
+            (true, _, true) => '│',
+            (false, true, true) => '╱',
+            (false, false, true) => '╲',
+        };
+        grid[(upper.1 + lower.1) / 2][(upper.0 + lower.0) / 2].glyph = glyph;
+    }
+
+    let mut stacks: HashMap<HexCoordinates, usize> = HashMap::new();
+    let units = &scenario.units;
+    let moving = &scenario.systems.moving;
+    for (_, id, hex) in stacked_units(scenario) {
+        if !fog.is_none_or(|fog| fog.shows_unit(id, units, moving)) {
+            continue;
+        }
+        let unit = &units.units()[&id];
+        let (x, y) = position(hex);
+        grid[y][x] = Cell {
+            glyph: unit_glyph(unit.kind(), unit.side()),
+            side: Some(unit.side()),
+        };
+        *stacks.entry(hex).or_default() += 1;
+    }
+    for (hex, size) in stacks {
+        if size > 1 {
+            let (x, y) = position(hex);
+            grid[y][x + 1].glyph = std::char::from_digit(size as u32, 10).unwrap_or('+');
+        }
+    }
+    for (hex, threat) in influence.into_iter().flat_map(InfluenceMap::danger_zone) {
+        if explored(hex) {
+            let (x, y) = position(*hex);
+            grid[y][x - 1] = Cell {
+                glyph: std::char::from_digit((*threat).min(9) as u32, 10).unwrap_or('+'),
+                side: None,
+            };
+        }
+    }
+
+    let mut text = " ".repeat(MARGIN);
+    for col in min_col..=max_col {
+        text.push_str(&format!("{:^width$}", col, width = CELL));
+    }
+    text = text.trim_end().to_owned();
+    text.push('\n');
+    for (y, line) in grid.iter().enumerate() {
+        let mut row = if y % 2 == 0 {
+            format!("{:>width$} ", min_row + y as i32 / 2, width = MARGIN - 1)
+        } else {
+            " ".repeat(MARGIN)
+        };
+        for cell in line[MARGIN..].iter() {
+            match (cell.side, options.color) {
+                (Some(Side::Blue), true) => {
+                    row.push_str(&format!("\x1b[1;34m{}\x1b[0m", cell.glyph))
+                }
+                (Some(Side::Red), true) => {
+                    row.push_str(&format!("\x1b[1;31m{}\x1b[0m", cell.glyph))
+                }
+                _ => row.push(cell.glyph),
+            }
+        }
+        text.push_str(row.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maps::hexagons::OffsetCoordinates;
+    use crate::maps::map::*;
+    use crate::maps::types::River;
+
+    fn hex(col: i32, row: i32) -> HexCoordinates {
+        HexCoordinates::from_offset(OffsetCoordinates::new(col, row), OffsetKind::OddR)
+    }
+
+    #[test]
+    fn drawing_maps() {
+        let mut scenario = Scenario::test();
+        let mut map = Map::new();
+        for row in 0..2 {
+            for col in 0..3 {
+                map.insert_hex(HexSite::new(hex(col, row), Field::Plain))
+                    .unwrap();
+            }
+        }
+        map.set_hex_kind(hex(1, 0), Field::Forest).unwrap();
+        map.set_hex_kind(hex(2, 1), Field::Hill).unwrap();
+        map.insert_river(RiverSite::new(hex(0, 0), hex(1, 0), River::Stream).unwrap())
+            .unwrap();
+        map.insert_river(RiverSite::new(hex(1, 0), hex(1, 1), River::Small).unwrap())
+            .unwrap();
+        map.insert_river(RiverSite::new(hex(2, 0), hex(1, 1), River::Small).unwrap())
+            .unwrap();
+        scenario.map = map;
+        for (id, mc) in scenario.systems.moving.components.iter_mut() {
+            mc.occupation = Some(if *id == 2 { hex(2, 1) } else { hex(0, 0) });
+        }
+
+        let expected = "      0   1   2
+   0 .I2|%%% ...
+           / \\
+   1   ... ... ^a^
+";
+        assert_eq!(draw_map(&scenario, TextOptions::default(), None), expected);
+
+        let options = TextOptions {
+            unicode: true,
+            color: true,
+        };
+        let text = draw_map(&scenario, options, None);
+        assert!(text.contains('♣'));
+        assert!(text.contains('╱'));
+        assert!(text.contains("\x1b[1;31ma\x1b[0m"));
+
+        let influence = InfluenceMap::new(
+            Side::Blue,
+            &scenario.units,
+            &scenario.systems.moving,
+            &scenario.map,
+            None,
+        );
+        //the red armoured unit can attack every hex of the small map
+        let expected = "      0   1   2
+   0 4I2|4%% 4..
+           / \\
+   1   4.. 4.. 4a^
+";
+        assert_eq!(
+            draw_threats(&scenario, TextOptions::default(), None, &influence),
+            expected
+        );
+
+        //the hill with the red unit is too far for the blue units to see
+        let mut fog = FogOfWar::new(Side::Blue);
+        fog.update(&scenario.units, &scenario.systems.moving, &scenario.map);
+        let expected = "      0   1   2
+   0 .I2|%%% ...
+           / \\
+   1   ... ... ###
+";
+        assert_eq!(
+            draw_map(&scenario, TextOptions::default(), Some(&fog)),
+            expected
+        );
+    }
+}
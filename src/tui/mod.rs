@@ -0,0 +1,544 @@
+//! Text frontend, playing the game by typing commands. It needs neither a window
+//! nor a terminal library, so it works over SSH and reads scripted games in CI.
+
+pub mod command;
+pub mod map_text;
+pub mod playback;
+pub mod remote;
+
+extern crate log;
+
+use std::io::{self, BufRead, Write};
+
+use crate::ai::Ai;
+use crate::game::{GameCommand, Scenario};
+use crate::maps::hexagons::{HexCoordinates, OffsetCoordinates, OffsetKind};
+use crate::players::Players;
+use crate::replay::Replay;
+use crate::systems::combat;
+use crate::systems::influence::InfluenceMap;
+use crate::systems::moving;
+use crate::systems::vision::FogOfWar;
+use command::Command;
+use map_text::TextOptions;
+
+const COORDS_KIND: OffsetKind = OffsetKind::OddR;
+
+fn label(hex: HexCoordinates) -> String {
+    hex.to_offset(COORDS_KIND).to_string()
+}
+
+fn hex_on_map(
+    scenario: &Scenario,
+    offset: OffsetCoordinates,
+) -> Result<HexCoordinates, &'static str> {
+    let hex = HexCoordinates::from_offset(offset, COORDS_KIND);
+    match scenario.map.hex_at(hex) {
+        Some(_) => Ok(hex),
+        None => Err("No such hex on the map."),
+    }
+}
+
+fn status(scenario: &Scenario) -> String {
+    let turn = scenario.turn;
+    format!(
+        "Turn {}, {:?} side, {:?} phase.\n",
+        turn.number(),
+        turn.side(),
+        turn.phase()
+    )
+}
+
+/// Units the side can see, with their positions and what they can still do.
+fn units_table<F: Fn(i32) -> bool>(scenario: &Scenario, shows_unit: F) -> String {
+    let units = scenario.units.units();
+    let mut ids: Vec<_> = units.keys().cloned().filter(|id| shows_unit(*id)).collect();
+    ids.sort();
+
+    let mut text = format!(
+        "{:>3} {:<16} {:<5} {:<11} {:>6} {:>6}\n",
+        "id", "name", "side", "kind", "hex", "moving"
+    );
+    for id in ids {
+        let unit = &units[&id];
+        let mc = scenario.systems.moving.components.get(&id);
+        let hex = mc
+            .and_then(|mc| mc.occupation)
+            .map_or("-".to_owned(), label);
+        let moving_pts = mc.map_or("-".to_owned(), |mc| {
+            format!("{}/{}", mc.moving_pts(), mc.default_moving_pts())
+        });
+        let attacked = if scenario.systems.combat.has_attacked(id) {
+            " attacked"
+        } else {
+            ""
+        };
+        text.push_str(&format!(
+            "{:>3} {:<16} {:<5} {:<11} {:>6} {:>6}{}\n",
+            id,
+            unit.name(),
+            format!("{:?}", unit.side()),
+            format!("{:?}", unit.kind()),
+            hex,
+            moving_pts,
+            attacked
+        ));
+    }
+    text
+}
+
+/// Terrain of the hex and the units on it the side can see.
+fn hex_info<F: Fn(i32) -> bool>(scenario: &Scenario, hex: HexCoordinates, shows_unit: F) -> String {
+    let map = &scenario.map;
+    let site = map.hex_at(hex).unwrap();
+    let mut text = format!(
+        "{} {:?}, entering cost {}, defence bonus {}\n",
+        label(hex),
+        site.kind(),
+        moving::cost_of_entering_hex(site.kind()),
+        combat::terrain_defence_bonus(site.kind())
+    );
+    for next in hex.neighbors().iter() {
+        if let Some(river) = map.river_between(hex, *next) {
+            text.push_str(&format!(
+                "{:?} river towards {}, crossing cost {}, defence bonus {}\n",
+                river.kind(),
+                label(*next),
+                moving::cost_of_crossing_river(river.kind()),
+                combat::river_defence_bonus(river.kind())
+            ));
+        }
+    }
+
+    let mut ids: Vec<_> = scenario
+        .systems
+        .moving
+        .components
+        .iter()
+        .filter(|(_, mc)| mc.occupation == Some(hex))
+        .map(|(id, _)| *id)
+        .filter(|id| shows_unit(*id))
+        .collect();
+    ids.sort();
+    for id in ids {
+        let unit = &scenario.units.units()[&id];
+        text.push_str(&format!(
+            "Unit {}: {}, {:?} {:?}\n",
+            id,
+            unit.name(),
+            unit.side(),
+            unit.kind()
+        ));
+    }
+    text
+}
+
+/// Map with the danger zones of the side on turn, only enemies out of the fog count.
+fn threats(scenario: &Scenario, options: TextOptions, fog: Option<&FogOfWar>) -> String {
+    let influence = InfluenceMap::new(
+        scenario.turn.side(),
+        &scenario.units,
+        &scenario.systems.moving,
+        &scenario.map,
+        fog,
+    );
+    map_text::draw_threats(scenario, options, fog, &influence)
+        + "Digits left of the hexes: enemy attack strength that can reach them next turn.\n"
+}
+
+/// Scenario played in the terminal, with the same rules as in the window.
+pub struct Tui {
+    scenario: Scenario,
+    players: Players,
+    replay: Replay,
+    options: TextOptions,
+    //the turn passed in a hot-seat game, the board is hidden until the next player is ready
+    handoff: bool,
+    //played by mail, the player stops when the turn passes
+    mail: bool,
+    //computer playing the other side
+    ai: Option<Ai>,
+}
+
+impl Tui {
+    pub fn new(scenario: Scenario, options: TextOptions) -> Self {
+        Self::with_players(scenario, options, false)
+    }
+
+    /// Game of two players sharing the terminal, each of them sees only what their units see.
+    pub fn hot_seat(scenario: Scenario, options: TextOptions) -> Self {
+        Self::with_players(scenario, options, true)
+    }
+
+    fn with_players(scenario: Scenario, options: TextOptions, hot_seat: bool) -> Self {
+        Tui {
+            replay: Replay::new(&scenario),
+            players: Players::new(&scenario, hot_seat),
+            scenario,
+            options,
+            handoff: false,
+            mail: false,
+            ai: None,
+        }
+    }
+
+    /// Game against the computer playing one of the sides.
+    pub fn against(scenario: Scenario, options: TextOptions, ai: Ai) -> Self {
+        let mut tui = Self::with_players(scenario, options, false);
+        tui.ai = Some(ai);
+        tui
+    }
+
+    /// Game played by mail, continued from the commands played so far. The player
+    /// sees what the units of the side to move see, and plays until the turn passes.
+    pub fn by_mail(replay: &Replay, options: TextOptions) -> Result<Self, &'static str> {
+        let mut tui = Self::with_players(replay.scenario(), options, true);
+        for command in replay.commands() {
+            tui.play(*command)
+                .map_err(|_| "Game doesn't match its scenario.")?;
+        }
+        tui.handoff = false;
+        tui.mail = true;
+        Ok(tui)
+    }
+
+    /// Commands played so far.
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    fn play(&mut self, command: GameCommand) -> Result<(), &'static str> {
+        let passed = self.players.execute(command, &mut self.scenario)?;
+        self.replay.record(command);
+        self.handoff = passed && self.players.is_hot_seat();
+        Ok(())
+    }
+
+    //lets the computer play, if it's its turn, returning what it did
+    fn computer_turn(&mut self) -> Result<String, &'static str> {
+        let ai = match self.ai {
+            Some(ai) if ai.side() == self.scenario.turn.side() => ai,
+            _ => return Ok(String::new()),
+        };
+        let mut text = String::new();
+        while let Some(command) = ai.next_command(&self.scenario) {
+            self.play(command)?;
+            text.push_str(&format!("Computer: {}\n", command));
+        }
+        Ok(text + &self.map() + &self.status())
+    }
+
+    fn handoff_text(&self) -> String {
+        if self.mail {
+            return format!(
+                "Turn over, the {:?} side plays next.\n",
+                self.scenario.turn.side()
+            );
+        }
+        format!(
+            "Pass the game to the {:?} player and press enter.\n",
+            self.scenario.turn.side()
+        )
+    }
+
+    fn hex(&self, offset: OffsetCoordinates) -> Result<HexCoordinates, &'static str> {
+        hex_on_map(&self.scenario, offset)
+    }
+
+    fn describe(command: GameCommand) -> String {
+        match command {
+            GameCommand::Move(unit_id, hex) => {
+                format!("move of unit {} to {}", unit_id, label(hex))
+            }
+            GameCommand::Place(unit_id, hex) => {
+                format!("placing unit {} on {}", unit_id, label(hex))
+            }
+            _ => format!("{:?}", command),
+        }
+    }
+
+    fn status(&self) -> String {
+        status(&self.scenario)
+    }
+
+    fn map(&self) -> String {
+        let fog = self.players.fog(self.scenario.turn.side());
+        map_text::draw_map(&self.scenario, self.options, fog)
+            + &map_text::legend(self.options.unicode)
+    }
+
+    fn units(&self) -> String {
+        units_table(&self.scenario, |id| {
+            self.players.shows_unit(id, &self.scenario)
+        })
+    }
+
+    fn info(&self, hex: HexCoordinates) -> String {
+        hex_info(&self.scenario, hex, |id| {
+            self.players.shows_unit(id, &self.scenario)
+        })
+    }
+
+    /// Executes the command, returning the text shown to the player.
+    pub fn execute(&mut self, command: Command) -> Result<String, &'static str> {
+        match command {
+            Command::Help => Ok(command::HELP.to_owned()),
+            Command::Map => Ok(self.map() + &self.status()),
+            Command::Units => Ok(self.units()),
+            Command::Threats => {
+                let fog = self.players.fog(self.scenario.turn.side());
+                Ok(threats(&self.scenario, self.options, fog))
+            }
+            Command::Info(offset) => Ok(self.info(self.hex(offset)?)),
+            Command::Move(unit_id, offset) => {
+                let hex = self.hex(offset)?;
+                self.play(GameCommand::Move(unit_id, hex))?;
+                let reached = self.scenario.systems.moving.components[&unit_id].occupation;
+                let text = match reached {
+                    Some(stop) if stop != hex => format!(
+                        "Unit {} ran into the enemy and stopped on {}.",
+                        unit_id,
+                        label(stop)
+                    ),
+                    _ => format!("Unit {} moved to {}.", unit_id, offset),
+                };
+                Ok(format!("{}\n{}", text, self.map()))
+            }
+            Command::Place(unit_id, offset) => {
+                let hex = self.hex(offset)?;
+                self.play(GameCommand::Place(unit_id, hex))?;
+                Ok(format!(
+                    "Unit {} placed on {}.\n{}",
+                    unit_id,
+                    offset,
+                    self.map()
+                ))
+            }
+            Command::Attack(attacker, defender) => {
+                self.play(GameCommand::Attack(attacker, defender))?;
+                let eliminated = self
+                    .scenario
+                    .systems
+                    .moving
+                    .components
+                    .get(&defender)
+                    .is_none_or(|mc| mc.occupation.is_none());
+                let res = if eliminated {
+                    "eliminated"
+                } else {
+                    "repelled the attack"
+                };
+                Ok(format!("Unit {} {}.\n{}", defender, res, self.map()))
+            }
+            Command::Undo => {
+                let command = self.players.undo(&mut self.scenario)?;
+                self.replay.undo();
+                Ok(format!(
+                    "Undone {}.\n{}",
+                    Self::describe(command),
+                    self.map()
+                ))
+            }
+            Command::Redo => {
+                let command = self.players.redo(&mut self.scenario)?;
+                self.replay.record(command);
+                Ok(format!(
+                    "Redone {}.\n{}",
+                    Self::describe(command),
+                    self.map()
+                ))
+            }
+            Command::NextPhase => {
+                self.play(GameCommand::NextPhase)?;
+                if self.handoff {
+                    return Ok(self.handoff_text());
+                }
+                Ok(self.status())
+            }
+            Command::EndTurn => {
+                self.play(GameCommand::EndTurn)?;
+                if self.handoff {
+                    return Ok(self.handoff_text());
+                }
+                Ok(self.map() + &self.status())
+            }
+            Command::Quit => Ok(String::new()),
+        }
+    }
+
+    /// Reads commands line by line, until the input ends, the player quits or one side wins.
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, output: &mut W) -> io::Result<()> {
+        write!(output, "{}{}", self.map(), self.status())?;
+        //the computer may have the first move, and may even win with it
+        match self.computer_turn() {
+            Ok(text) => write!(output, "{}", text)?,
+            Err(err) => writeln!(output, "{}", err)?,
+        }
+        if let Some(side) = self.scenario.winner() {
+            writeln!(output, "{:?} side wins.", side)?;
+            return Ok(());
+        }
+        write!(output, "> ")?;
+        output.flush()?;
+        for line in input.lines() {
+            let line = line?;
+            if self.handoff {
+                //whatever was typed, the board of the previous player is cleared first
+                self.handoff = false;
+                write!(output, "\x1b[2J\x1b[H{}{}> ", self.map(), self.status())?;
+                output.flush()?;
+                continue;
+            }
+            let res = match line.parse() {
+                _ if line.trim().is_empty() => Ok(String::new()),
+                Ok(Command::Quit) => return Ok(()),
+                Ok(command) => self.execute(command),
+                Err(err) => Err(err),
+            }
+            .and_then(|text| Ok(text + &self.computer_turn()?));
+            match res {
+                Ok(text) => write!(output, "{}", text)?,
+                Err(err) => writeln!(output, "{}", err)?,
+            }
+            if self.mail && self.handoff {
+                return Ok(());
+            }
+            if let Some(side) = self.scenario.winner() {
+                writeln!(output, "{:?} side wins.", side)?;
+                return Ok(());
+            }
+            write!(output, "> ")?;
+            output.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Phase;
+    use crate::rng;
+    use crate::units::unit::Side;
+
+    //test scenario whose first die rolls a six
+    fn lucky_scenario() -> Scenario {
+        let mut scenario = Scenario::test();
+        scenario.dice = rng::Rng::new(6);
+        scenario
+    }
+
+    #[test]
+    fn scripted_game() {
+        let mut tui = Tui::new(lucky_scenario(), TextOptions::default());
+        let units = tui.execute(Command::Units).unwrap();
+        assert_eq!(units.lines().count(), 1 + tui.scenario.units.units().len());
+
+        //offset coordinates of the hexes used by the test units
+        let red = label(HexCoordinates::new_axial(-2, 3));
+        let aside = label(HexCoordinates::new_axial(1, 0));
+        let next_to_red = label(HexCoordinates::new_axial(-1, 3));
+        let script = format!(
+            "help\n\nfly\nmove 2 {next}\nmove 0 {aside}\nundo\nredo\nmove 0 {next}\nundo\n\
+             units\nnext\ninfo {red}\nattack 0 2\nend\n",
+            aside = aside,
+            next = next_to_red,
+            red = red
+        );
+        let mut output = Vec::new();
+        tui.run(script.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.starts_with(&map_text::draw_map(
+            &Scenario::test(),
+            TextOptions::default(),
+            None
+        )));
+        assert!(output.contains(command::HELP));
+        assert!(output.contains("Unknown command"));
+        assert!(output.contains("Unit cannot move now."));
+        assert!(output.contains(&format!("Unit 0 moved to {}.", aside)));
+        assert!(output.contains(&format!("Undone move of unit 0 to {}.", aside)));
+        assert!(output.contains(&format!("Redone move of unit 0 to {}.", aside)));
+        //the red unit came into sight, so the last move can't be undone
+        assert!(output.contains(&format!("Unit 0 moved to {}.", next_to_red)));
+        assert!(output.contains("Nothing to undo."));
+        assert!(output.contains(&format!(" {} ", next_to_red)));
+        assert!(output.contains("Combat phase"));
+        assert!(output.contains("Unit 2: test unit 2, Red Armoured"));
+        //the only red unit is gone, so the game ends before the last command
+        assert!(output.contains("Unit 2 eliminated.\n"));
+        assert!(output.ends_with("Blue side wins.\n"));
+        assert_eq!(tui.scenario.turn.side(), Side::Blue);
+        assert_eq!(
+            tui.replay().commands(),
+            &[
+                GameCommand::Move(0, HexCoordinates::new_axial(1, 0)),
+                GameCommand::Move(0, HexCoordinates::new_axial(-1, 3)),
+                GameCommand::NextPhase,
+                GameCommand::Attack(0, 2)
+            ]
+        );
+
+        let mut tui = Tui::new(Scenario::test(), TextOptions::default());
+        tui.run("next\nquit\nnext\n".as_bytes(), &mut Vec::new())
+            .unwrap();
+        assert_eq!(tui.scenario.turn.phase(), Phase::Combat);
+    }
+
+    #[test]
+    fn hot_seat_game() {
+        let mut tui = Tui::hot_seat(Scenario::test(), TextOptions::default());
+        let mut output = Vec::new();
+        tui.run("units\nend\nunits\n\nunits\n".as_bytes(), &mut output)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let parts: Vec<_> = output
+            .split("Pass the game to the Red player and press enter.\n")
+            .collect();
+        assert_eq!(parts.len(), 2);
+
+        //blue sees its own units only, red player's commands wait for the board to be shown
+        assert!(parts[0].contains("test unit 1"));
+        assert!(!parts[0].contains("test unit 2"));
+        assert!(!parts[1].contains("test unit 1"));
+        assert!(parts[1].starts_with("> \x1b[2J"));
+        assert_eq!(parts[1].matches("test unit 2").count(), 1);
+        assert_eq!(tui.scenario.turn.side(), Side::Red);
+    }
+
+    #[test]
+    fn playing_by_mail() {
+        let mut replay = Replay::new(&Scenario::test());
+        replay.record(GameCommand::EndTurn);
+        let mut tui = Tui::by_mail(&replay, TextOptions::default()).unwrap();
+        assert_eq!(tui.scenario.turn.side(), Side::Red);
+
+        let mut output = Vec::new();
+        tui.run("units\nend\nunits\n".as_bytes(), &mut output)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(!output.contains("test unit 1"));
+        assert!(output.ends_with("Turn over, the Blue side plays next.\n"));
+        assert_eq!(tui.replay().commands().len(), 2);
+    }
+
+    #[test]
+    fn playing_against_computer() {
+        let ai = Ai::new(Side::Red, Default::default());
+        let mut tui = Tui::against(Scenario::test(), TextOptions::default(), ai);
+        let mut output = Vec::new();
+        tui.run("end\n".as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Computer: end\n"));
+        assert!(output.ends_with("Turn 2, Blue side, Movement phase.\n> "));
+
+        let ai = Ai::new(Side::Blue, Default::default());
+        let mut tui = Tui::against(lucky_scenario(), TextOptions::default(), ai);
+        let mut output = Vec::new();
+        tui.run("".as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Computer: attack 0 2\n"));
+        assert!(output.ends_with("Blue side wins.\n"));
+    }
+}
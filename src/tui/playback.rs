@@ -0,0 +1,102 @@
+use std::io::{self, BufRead, Write};
+
+use super::map_text::{self, TextOptions};
+use super::status;
+use crate::replay::Playback;
+
+pub const HELP: &str = "Playback:
+  (enter)  play the turn of the current side
+  step     play a single command
+  ff       fast-forward to the end
+  quit     stop watching
+";
+
+/// Shows the replay in the terminal, side turn after side turn.
+pub fn watch<R: BufRead, W: Write>(
+    playback: &mut Playback,
+    options: TextOptions,
+    input: R,
+    output: &mut W,
+) -> io::Result<()> {
+    let draw = |playback: &Playback| {
+        map_text::draw_map(playback.scenario(), options, None) + &status(playback.scenario())
+    };
+    write!(output, "{}{}> ", HELP, draw(playback))?;
+    output.flush()?;
+    for line in input.lines() {
+        let res = match line?.trim() {
+            "" | "n" | "next" => playback.next_turn().map(|count| count > 0),
+            "s" | "step" => match playback.step() {
+                Ok(Some(command)) => {
+                    writeln!(output, "{}", command)?;
+                    Ok(true)
+                }
+                Ok(None) => Ok(false),
+                Err(err) => Err(err),
+            },
+            "f" | "ff" => playback.fast_forward().map(|count| count > 0),
+            "q" | "quit" => return Ok(()),
+            _ => {
+                write!(output, "{}> ", HELP)?;
+                continue;
+            }
+        };
+        match res {
+            Ok(true) => write!(output, "{}", draw(playback))?,
+            Ok(false) => {}
+            Err(err) => writeln!(output, "{}", err)?,
+        }
+        if playback.is_finished() {
+            writeln!(
+                output,
+                "End of the replay, {} commands.",
+                playback.position()
+            )?;
+            return Ok(());
+        }
+        write!(output, "> ")?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameCommand, Scenario};
+    use crate::replay::Replay;
+
+    #[test]
+    fn watching_replays() {
+        let scenario = Scenario::test();
+        let mut replay = Replay::new(&scenario);
+        replay.record(GameCommand::NextPhase);
+        replay.record(GameCommand::NextPhase);
+        replay.record(GameCommand::EndTurn);
+
+        let mut playback = Playback::new(replay.clone());
+        let mut output = Vec::new();
+        watch(
+            &mut playback,
+            TextOptions::default(),
+            "\nstep\nq\n".as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Turn 1, Red side, Movement phase."));
+        assert!(output.contains("> end\n"));
+        assert!(output.ends_with("End of the replay, 3 commands.\n"));
+
+        let mut playback = Playback::new(replay);
+        let mut output = Vec::new();
+        watch(
+            &mut playback,
+            TextOptions::default(),
+            "ff\nq\n".as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        assert!(playback.is_finished());
+    }
+}
@@ -0,0 +1,210 @@
+extern crate log;
+
+use std::io::{self, BufRead, Write};
+
+use super::command::{self, Command};
+use super::map_text::{self, TextOptions};
+use super::{hex_info, hex_on_map, status, threats, units_table};
+use crate::game::GameCommand;
+use crate::net::client::Client;
+use crate::net::Packet;
+use crate::systems::vision::FogOfWar;
+
+/// Game against a player on another machine, the host decides what the commands do.
+/// Each side sees the map only through the fog of war of its units.
+pub struct Remote {
+    client: Client,
+    fog: FogOfWar,
+    options: TextOptions,
+}
+
+impl Remote {
+    pub fn new(client: Client, options: TextOptions) -> Self {
+        let mut remote = Remote {
+            fog: FogOfWar::new(client.side()),
+            client,
+            options,
+        };
+        remote.update_fog();
+        remote
+    }
+
+    fn update_fog(&mut self) {
+        let scenario = self.client.scenario();
+        self.fog
+            .update(&scenario.units, &scenario.systems.moving, &scenario.map);
+    }
+
+    fn shows_unit(&self, unit_id: i32) -> bool {
+        let scenario = self.client.scenario();
+        self.fog
+            .shows_unit(unit_id, &scenario.units, &scenario.systems.moving)
+    }
+
+    fn is_my_turn(&self) -> bool {
+        self.client.scenario().turn.side() == self.client.side()
+    }
+
+    fn map(&self) -> String {
+        map_text::draw_map(self.client.scenario(), self.options, Some(&self.fog))
+            + &map_text::legend(self.options.unicode)
+            + &status(self.client.scenario())
+    }
+
+    //a dropped connection is made again once, the host sends what was missed meanwhile
+    fn receive(&mut self) -> io::Result<Packet> {
+        let packet = match self.client.receive() {
+            Err(ref err) if err.kind() != io::ErrorKind::InvalidData => {
+                warn!("Connection lost, reconnecting: {}", err);
+                self.client.reconnect()?;
+                self.client.receive()?
+            }
+            res => res?,
+        };
+        if let Packet::Delta(_) = packet {
+            self.update_fog();
+        }
+        Ok(packet)
+    }
+
+    /// Sends the command to the host, returning the reason if it's rejected.
+    fn play(&mut self, command: GameCommand) -> io::Result<Result<(), String>> {
+        if let Err(err) = self.client.send(command) {
+            warn!("Connection lost, reconnecting: {}", err);
+            self.client.reconnect()?;
+            self.client.send(command)?;
+        }
+        loop {
+            match self.receive()? {
+                Packet::Delta(_) => return Ok(Ok(())),
+                Packet::Rejected(reason) => return Ok(Err(reason)),
+                _ => {}
+            }
+        }
+    }
+
+    /// Applies the commands of the other side until it passes the turn or the game ends.
+    pub fn wait(&mut self) -> io::Result<()> {
+        while !self.is_my_turn() && self.client.scenario().winner().is_none() {
+            self.receive()?;
+        }
+        Ok(())
+    }
+
+    /// Executes the command, returning the text shown to the player.
+    pub fn execute(&mut self, command: Command) -> io::Result<String> {
+        let scenario = self.client.scenario();
+        let hex = |offset| hex_on_map(scenario, offset);
+        let game_command = match command {
+            Command::Help => return Ok(command::HELP.to_owned()),
+            Command::Map => return Ok(self.map()),
+            Command::Units => return Ok(units_table(scenario, |id| self.shows_unit(id))),
+            Command::Threats => return Ok(threats(scenario, self.options, Some(&self.fog))),
+            Command::Info(offset) => {
+                return Ok(match hex(offset) {
+                    Ok(hex) => hex_info(scenario, hex, |id| self.shows_unit(id)),
+                    Err(err) => format!("{}\n", err),
+                })
+            }
+            Command::Undo | Command::Redo => {
+                return Ok("Moves can't be taken back in network games.\n".to_owned())
+            }
+            Command::Quit => return Ok(String::new()),
+            Command::Move(unit_id, offset) => {
+                hex(offset).map(|hex| GameCommand::Move(unit_id, hex))
+            }
+            Command::Place(unit_id, offset) => {
+                hex(offset).map(|hex| GameCommand::Place(unit_id, hex))
+            }
+            Command::Attack(attacker, defender) => Ok(GameCommand::Attack(attacker, defender)),
+            Command::NextPhase => Ok(GameCommand::NextPhase),
+            Command::EndTurn => Ok(GameCommand::EndTurn),
+        };
+        let res = match game_command {
+            Ok(game_command) => self.play(game_command)?,
+            Err(err) => Err(err.to_owned()),
+        };
+        Ok(match res {
+            Ok(()) => self.map(),
+            Err(reason) => format!("{}\n", reason),
+        })
+    }
+
+    /// Reads commands on the turns of the player, and waits for the opponent
+    /// on the other turns, until the input ends, the player quits or one side wins.
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, output: &mut W) -> io::Result<()> {
+        write!(output, "{}", self.map())?;
+        let mut lines = input.lines();
+        loop {
+            if let Some(side) = self.client.scenario().winner() {
+                writeln!(output, "{:?} side wins.", side)?;
+                return Ok(());
+            }
+            if !self.is_my_turn() {
+                let side = self.client.scenario().turn.side();
+                writeln!(output, "Waiting for the {:?} player.", side)?;
+                output.flush()?;
+                self.wait()?;
+                write!(output, "{}", self.map())?;
+                continue;
+            }
+
+            write!(output, "> ")?;
+            output.flush()?;
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return Ok(()),
+            };
+            let text = match line.parse() {
+                _ if line.trim().is_empty() => String::new(),
+                Ok(Command::Quit) => return Ok(()),
+                Ok(command) => self.execute(command)?,
+                Err(err) => format!("{}\n", err),
+            };
+            write!(output, "{}", text)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Scenario;
+    use crate::maps::hexagons::HexCoordinates;
+    use crate::net::host::Host;
+    use crate::units::unit::Side;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn playing_remote_game() {
+        let host = Arc::new(Host::bind("127.0.0.1:0", Scenario::test()).unwrap());
+        let address = host.local_addr().unwrap();
+        let server = Arc::clone(&host);
+        thread::spawn(move || server.run());
+
+        let blue = Client::connect(address, Side::Blue).unwrap();
+        let mut red = Client::connect(address, Side::Red).unwrap();
+        let player = thread::spawn(move || {
+            let mut remote = Remote::new(blue, TextOptions::default());
+            let mut output = Vec::new();
+            let input = "z\nmove 0 9999\nmove 0 2,-1\nend\n".as_bytes();
+            remote.run(input, &mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        });
+
+        for _ in 0..2 {
+            assert!(matches!(red.receive().unwrap(), Packet::Delta(_)));
+        }
+        let moved = red.scenario().systems.moving.components[&0].occupation;
+        assert_eq!(moved, Some(HexCoordinates::new_axial(-1, 3)));
+        red.send(GameCommand::EndTurn).unwrap();
+        red.receive().unwrap();
+
+        let output = player.join().unwrap();
+        assert!(output.contains("Moves can't be taken back in network games.\n"));
+        assert!(output.contains("No such hex on the map.\n"));
+        assert!(output.contains("Waiting for the Red player.\n"));
+        assert!(output.ends_with("Turn 2, Blue side, Movement phase.\n> "));
+    }
+}
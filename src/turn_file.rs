@@ -0,0 +1,289 @@
+extern crate log;
+
+use crate::game::{GameCommand, Scenario};
+use crate::history::Command;
+use crate::maps::hexagons::OffsetKind;
+use crate::net::{checksum, parse_side, side_name};
+use crate::replay::{self, Replay};
+use crate::units::unit::Side;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use std::fs;
+
+const VERSION: u32 = 2;
+
+type HmacSha256 = Hmac<Sha256>;
+
+//HMAC of the text, nobody without the key can make a matching one,
+//so an empty key, which everyone knows, is refused
+fn authenticator(key: &str, text: &str) -> Result<HmacSha256, &'static str> {
+    if key.is_empty() {
+        return Err("Turn files need a key agreed on by the players.");
+    }
+    let mut mac =
+        HmacSha256::new_from_slice(key.as_bytes()).expect("HMAC takes keys of any length.");
+    mac.update(text.as_bytes());
+    Ok(mac)
+}
+
+/// Hash of everything that changes while the game is played, the map is given by the seed.
+pub fn state_hash(scenario: &Scenario) -> u64 {
+    let turn = scenario.turn;
+    let mut text = format!(
+        "{} {:?} {:?} {:?} {}",
+        turn.number(),
+        turn.side(),
+        turn.phase(),
+        scenario.systems.combat.attacked_units(),
+        scenario.dice.state()
+    );
+    let components = &scenario.systems.moving.components;
+    let mut ids: Vec<_> = components.keys().cloned().collect();
+    ids.sort();
+    for id in ids {
+        let mc = &components[&id];
+        let hex = mc.occupation.map(|hex| hex.to_offset(OffsetKind::OddR));
+        text.push_str(&format!(" {}:{:?}:{}", id, hex, mc.moving_pts()));
+    }
+    checksum(&text)
+}
+
+fn scenario_after(replay: &Replay, count: usize) -> Result<Scenario, &'static str> {
+    let mut scenario = replay.scenario();
+    for command in replay.commands()[..count].iter() {
+        command
+            .apply(&mut scenario)
+            .map_err(|_| "Game doesn't match its scenario.")?;
+    }
+    Ok(scenario)
+}
+
+//plays the commands, which must all be of the side and end its turn, or the game
+fn play_turn(
+    scenario: &mut Scenario,
+    side: Side,
+    commands: &[GameCommand],
+) -> Result<(), &'static str> {
+    for command in commands.iter() {
+        if scenario.turn.side() != side || scenario.winner().is_some() {
+            return Err("Turn file has commands of another turn.");
+        }
+        command.apply(scenario)?;
+    }
+    if scenario.turn.side() == side && scenario.winner().is_none() {
+        return Err("The turn isn't finished.");
+    }
+    Ok(())
+}
+
+/// Commands of a side turn for games played by mail. The hashes of the game before
+/// and after the turn let the opponent check that both play the same game,
+/// and the signature, an HMAC-SHA256 made with a key the players agree on,
+/// that nobody edited the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurnFile {
+    seed: Option<u64>,
+    /// Number of commands played in the game before the turn.
+    first: usize,
+    side: Side,
+    before: u64,
+    after: u64,
+    commands: Vec<GameCommand>,
+}
+
+impl TurnFile {
+    /// Takes the last finished side turn of the game.
+    pub fn export(replay: &Replay) -> Result<Self, &'static str> {
+        let mut scenario = replay.scenario();
+        //commands starting the side turns
+        let mut starts = vec![0];
+        for (index, command) in replay.commands().iter().enumerate() {
+            let side = scenario.turn.side();
+            command
+                .apply(&mut scenario)
+                .map_err(|_| "Game doesn't match its scenario.")?;
+            if scenario.turn.side() != side || scenario.winner().is_some() {
+                starts.push(index + 1);
+            }
+        }
+        if starts.last() != Some(&replay.commands().len()) || starts.len() < 2 {
+            return Err("The turn isn't finished.");
+        }
+
+        let first = starts[starts.len() - 2];
+        let before = scenario_after(replay, first)?;
+        Ok(TurnFile {
+            seed: replay.seed(),
+            first,
+            side: before.turn.side(),
+            before: state_hash(&before),
+            after: state_hash(&scenario),
+            commands: replay.commands()[first..].to_vec(),
+        })
+    }
+
+    pub fn side(&self) -> Side {
+        self.side
+    }
+
+    pub fn commands(&self) -> &[GameCommand] {
+        &self.commands
+    }
+
+    /// Checks the turn against the game of the opponent and adds its commands there.
+    pub fn import(&self, replay: &mut Replay) -> Result<(), &'static str> {
+        if self.seed != replay.seed() {
+            return Err("Turn file belongs to another game.");
+        }
+        let played = replay.commands().len();
+        if self.first < played {
+            return Err("Turn file was already imported.");
+        }
+        if self.first > played {
+            return Err("Turn files before this one are missing.");
+        }
+
+        let mut scenario = scenario_after(replay, played)?;
+        if state_hash(&scenario) != self.before {
+            return Err("Games differ before the turn.");
+        }
+        play_turn(&mut scenario, self.side, &self.commands)?;
+        if state_hash(&scenario) != self.after {
+            return Err("Turn doesn't give the same result.");
+        }
+        for command in self.commands.iter() {
+            replay.record(*command);
+        }
+        info!(
+            "Imported turn of the {:?} side, {} commands.",
+            self.side,
+            self.commands.len()
+        );
+        Ok(())
+    }
+
+    /// Writes the turn in the replay format, followed by the signature.
+    pub fn to_text(&self, key: &str) -> Result<String, &'static str> {
+        let mut text = format!(
+            "# Combat theater turn\nversion = {}\nscenario = {}\nfirst = {}\n\
+             side = {}\nbefore = {:016x}\nafter = {:016x}\n",
+            VERSION,
+            replay::scenario_value(self.seed),
+            self.first,
+            side_name(self.side),
+            self.before,
+            self.after
+        );
+        for command in self.commands.iter() {
+            text.push_str(&format!("{}\n", command));
+        }
+        let signature: String = authenticator(key, &text)?
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        Ok(text + &format!("signature = {}\n", signature))
+    }
+
+    pub fn parse(text: &str, key: &str) -> Result<Self, &'static str> {
+        let pos = text.rfind("signature =").ok_or("Turn file isn't signed.")?;
+        let (signed, signature) = text.split_at(pos);
+        let signature = signature["signature =".len()..].trim();
+        const ERR: &str = "Invalid turn file.";
+        let bytes = (0..signature.len())
+            .step_by(2)
+            .map(|i| {
+                signature
+                    .get(i..i + 2)
+                    .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or(ERR)?;
+        authenticator(key, signed)?
+            .verify_slice(&bytes)
+            .map_err(|_| "Wrong signature, the file was changed or the key differs.")?;
+
+        let (values, commands) = replay::parse_lines(signed, ERR)?;
+        let value = |key| values.get(key).map(String::as_str).ok_or(ERR);
+        let hash = |key| u64::from_str_radix(value(key)?, 16).map_err(|_| ERR);
+        if value("version")? != VERSION.to_string() {
+            return Err("Unsupported turn file version.");
+        }
+        Ok(TurnFile {
+            seed: replay::parse_scenario(value("scenario")?)?,
+            first: value("first")?.parse().map_err(|_| ERR)?,
+            side: parse_side(value("side")?)?,
+            before: hash("before")?,
+            after: hash("after")?,
+            commands,
+        })
+    }
+
+    pub fn save(&self, path: &str, key: &str) -> Result<(), &'static str> {
+        fs::write(path, self.to_text(key)?).map_err(|_| "Failed to write turn file.")?;
+        info!("Saved turn to {}.", path);
+        Ok(())
+    }
+
+    pub fn load(path: &str, key: &str) -> Result<Self, &'static str> {
+        let text = fs::read_to_string(path).map_err(|_| "Failed to read turn file.")?;
+        Self::parse(&text, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maps::hexagons::HexCoordinates;
+
+    #[test]
+    fn mailing_turns() {
+        let scenario = Scenario::test();
+        let mut blue = Replay::new(&scenario);
+        let mut red = blue.clone();
+        blue.record(GameCommand::Move(0, HexCoordinates::new_axial(-1, 3)));
+        assert_eq!(TurnFile::export(&blue), Err("The turn isn't finished."));
+        blue.record(GameCommand::NextPhase);
+        blue.record(GameCommand::NextPhase);
+
+        let turn = TurnFile::export(&blue).unwrap();
+        assert_eq!(turn.side(), Side::Blue);
+        assert!(turn.to_text("").is_err());
+        let text = turn.to_text("secret").unwrap();
+        assert!(text.contains("\nside = blue\nbefore = "));
+        let signature = text.rsplit("signature = ").next().unwrap().trim();
+        assert_eq!(signature.len(), 64);
+        assert!(TurnFile::parse(&text, "guess").is_err());
+        assert!(TurnFile::parse(&text, "").is_err());
+        assert!(TurnFile::parse(&text.replace(signature, &signature[..16]), "secret").is_err());
+        assert!(TurnFile::parse(&text.replace("next\nsign", "end\nsign"), "secret").is_err());
+        let loaded = TurnFile::parse(&text, "secret").unwrap();
+        assert_eq!(loaded, turn);
+
+        loaded.import(&mut red).unwrap();
+        assert_eq!(red, blue);
+        assert_eq!(
+            loaded.import(&mut red),
+            Err("Turn file was already imported.")
+        );
+
+        //red answers, and blue gets only the commands of the red turn
+        red.record(GameCommand::EndTurn);
+        let answer = TurnFile::export(&red).unwrap();
+        assert_eq!(answer.commands(), &[GameCommand::EndTurn]);
+        answer.import(&mut blue).unwrap();
+        assert_eq!(blue, red);
+
+        //a file signed with the right key still has to match the game it's played in
+        let mut forged = TurnFile::export(&blue).unwrap();
+        forged.first = 0;
+        let forged = TurnFile::parse(&forged.to_text("secret").unwrap(), "secret").unwrap();
+        assert_eq!(
+            forged.import(&mut Replay::new(&scenario)),
+            Err("Games differ before the turn.")
+        );
+    }
+}
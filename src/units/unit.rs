@@ -1,22 +1,34 @@
-extern crate sfml;
-
 use crate::maps::*;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum UnitType {
+    Infantry,
     Mechanized,
+    Armoured,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Side {
+    Blue,
+    Red,
 }
 
 #[derive(Debug, Clone)]
 pub struct Unit {
     name: String,
     kind: UnitType,
+    side: Side,
     id: i32,
 }
 
 impl Unit {
-    pub fn new(name: String, kind: UnitType, id: i32) ->Self {
-        Unit{name, kind ,id}
+    pub fn new(name: String, kind: UnitType, side: Side, id: i32) -> Self {
+        Unit {
+            name,
+            kind,
+            side,
+            id,
+        }
     }
 
     pub fn name(&self) -> &str {
@@ -27,6 +39,10 @@ impl Unit {
         self.kind
     }
 
+    pub fn side(&self) -> Side {
+        self.side
+    }
+
     pub fn id(&self) -> i32 {
         self.id
     }
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 
 use super::unit::*;
 
+use crate::maps::hexagons::HexCoordinates;
 use crate::systems::*;
 
 pub struct UnitSet {
@@ -17,17 +18,22 @@ impl UnitSet {
         }
     }
 
+    pub fn units(&self) -> &HashMap<i32, Unit> {
+        &self.units
+    }
+
     pub fn push_unit(
         &mut self,
         systems: &mut GameSystems,
         kind: UnitType,
+        side: Side,
         name: String,
     ) -> Result<i32, &'static str> {
-        let unit = Unit::new(name, kind, self.current_free_id);
+        let unit = Unit::new(name, kind, side, self.current_free_id);
         self.current_free_id += 1;
-        self.units
-            .insert(unit.id(), unit.clone())
-            .ok_or("Unit set already contains unit with this id.");
+        if self.units.insert(unit.id(), unit.clone()).is_some() {
+            return Err("Unit set already contains unit with this id.");
+        }
 
         UnitSet::register_unit_in_game_systems(systems, &unit)?;
 
@@ -38,15 +44,22 @@ impl UnitSet {
         systems: &mut GameSystems,
         unit: &Unit,
     ) -> Result<(), &'static str> {
-        match unit.kind() {
-            UnitType::Mechanized => {
-                systems
-                    .moving
-                    .components
-                    .insert(unit.id(), components::MovingComponent::new(unit.id(), 15))
-                    .ok_or("Moving system already registered unit with this id.");
-            }
-            _ => unreachable!(),
+        let moving_pts = match unit.kind() {
+            UnitType::Infantry => 8,
+            UnitType::Mechanized => 15,
+            UnitType::Armoured => 12,
+        };
+
+        if systems
+            .moving
+            .components
+            .insert(
+                unit.id(),
+                components::MovingComponent::new(unit.id(), moving_pts),
+            )
+            .is_some()
+        {
+            return Err("Moving system already registered unit with this id.");
         }
 
         Ok(())
@@ -54,13 +67,32 @@ impl UnitSet {
 
     pub fn create_test_unit_set(systems: &mut GameSystems) -> Self {
         let mut set = Self::new();
-        set.push_unit(systems, UnitType::Mechanized, String::from("test unit 0")).unwrap();
-        set.push_unit(systems, UnitType::Mechanized, String::from("test unit 1")).unwrap();
-        set.push_unit(systems, UnitType::Mechanized, String::from("test unit 2")).unwrap();
+        let units = [
+            (
+                UnitType::Mechanized,
+                Side::Blue,
+                HexCoordinates::new_axial(0, 0),
+            ),
+            (
+                UnitType::Infantry,
+                Side::Blue,
+                HexCoordinates::new_axial(0, 0),
+            ),
+            (
+                UnitType::Armoured,
+                Side::Red,
+                HexCoordinates::new_axial(-2, 3),
+            ),
+        ];
+        for (i, (kind, side, hex)) in units.iter().enumerate() {
+            let id = set
+                .push_unit(systems, *kind, *side, format!("test unit {}", i))
+                .unwrap();
+            systems.moving.components.get_mut(&id).unwrap().occupation = Some(*hex);
+        }
         set
     }
 }
 
 #[cfg(test)]
-mod tests {
-}
+mod tests {}